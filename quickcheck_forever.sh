@@ -4,8 +4,7 @@ export QUICKCHECK_TESTS=1000
 
 while true
 do
-    # cargo test --no-default-features --features bad_crypto prop_
-    cargo test prop_
+    cargo test --no-default-features --features bad_crypto prop_
     if [[ x$? != x0 ]] ; then
         exit $?
     fi
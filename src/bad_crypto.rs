@@ -7,16 +7,19 @@
 use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use thiserror::Error;
 
+use crate::voter_keys::{self, VoterKey};
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Failed Verification")]
     FailedVerification,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PublicKey(u64);
 
 impl PublicKey {
@@ -51,7 +54,7 @@ impl core::fmt::Display for PublicKey {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SecretKey(u64);
 
 impl SecretKey {
@@ -73,5 +76,35 @@ impl SecretKey {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Signature(u64);
+
+/// The elders of a section, each voter is identified by their own public key.
+pub type PublicKeySet = voter_keys::PublicKeySet<PublicKey>;
+
+/// A secret key for every possible `NodeId`, mirrors blsttc's `SecretKeySet` API.
+pub type SecretKeySet = voter_keys::SecretKeySet<PublicKey>;
+
+/// A super majority of signatures, one per voter.
+pub type MultiSignature = voter_keys::MultiSignature<Signature>;
+
+impl VoterKey for PublicKey {
+    type SecretKey = SecretKey;
+    type Signature = Signature;
+
+    fn random_secret_key(rng: impl Rng + CryptoRng) -> SecretKey {
+        SecretKey::random(rng)
+    }
+
+    fn from_secret_key(secret_key: &SecretKey) -> Self {
+        secret_key.public_key()
+    }
+
+    fn sign(secret_key: &SecretKey, msg: &[u8]) -> Signature {
+        secret_key.sign(msg)
+    }
+
+    fn is_valid(&self, msg: &[u8], sig: &Signature) -> bool {
+        self.verify(msg, sig).is_ok()
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+
+use blsttc::{PublicKey, PublicKeySet, SecretKeyShare, Signature, SignatureShare};
+
+use crate::{NodeId, Result, SignatureScheme, UniqueSignature};
+
+impl SignatureScheme for PublicKeySet {
+    type PublicKey = PublicKey;
+    type SecretKeyShare = SecretKeyShare;
+    type SignatureShare = SignatureShare;
+    type Signature = Signature;
+
+    fn public_key(&self) -> PublicKey {
+        PublicKeySet::public_key(self)
+    }
+
+    fn sign(secret_key: &SecretKeyShare, msg: &[u8]) -> SignatureShare {
+        secret_key.sign(msg)
+    }
+
+    fn verify_share(&self, voter: NodeId, msg: &[u8], sig: &SignatureShare) -> bool {
+        self.public_key_share(voter as u64).verify(sig, msg)
+    }
+
+    fn threshold(&self) -> usize {
+        PublicKeySet::threshold(self)
+    }
+
+    fn combine_signatures(&self, shares: &BTreeMap<NodeId, SignatureShare>) -> Result<Signature> {
+        let shares = shares.iter().map(|(id, sig)| (*id as u64, sig));
+        Ok(PublicKeySet::combine_signatures(self, shares)?)
+    }
+
+    fn verify(public_key: &PublicKey, msg: &[u8], sig: &Signature) -> bool {
+        public_key.verify(sig, msg)
+    }
+}
+
+impl UniqueSignature for PublicKeySet {}
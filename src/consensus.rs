@@ -1,37 +1,115 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
 
-use blsttc::{PublicKeySet, SecretKeyShare, SignatureShare};
-use log::info;
-use serde::Serialize;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 
+use crate::observer::{ConsensusEvent, ConsensusObserver};
 use crate::sn_membership::Generation;
+use crate::store::{ConsensusState, VoteLog};
 use crate::vote::{Ballot, Proposition, SignedVote, Vote};
-use crate::{Decision, Fault, NodeId, Result, VoteCount};
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Consensus<T: Proposition> {
-    pub elders: PublicKeySet,
+use crate::{
+    Decision, DecisionCertificate, Error, Fault, NodeId, PublicKeySet, Result, SignatureScheme,
+    VoteCount,
+};
+
+#[derive(Debug, Clone)]
+pub struct Consensus<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub elders: S,
     pub n_elders: usize,
-    pub secret_key: (NodeId, SecretKeyShare),
-    pub processed_votes_cache: BTreeSet<SignatureShare>,
-    pub votes: BTreeMap<NodeId, SignedVote<T>>,
-    pub faults: BTreeMap<NodeId, Fault<T>>,
-    pub decision: Option<Decision<T>>,
+    pub secret_key: (NodeId, S::SecretKeyShare),
+    pub processed_votes_cache: BTreeSet<S::SignatureShare>,
+    pub votes: BTreeMap<NodeId, SignedVote<T, S>>,
+    pub faults: BTreeMap<NodeId, Fault<T, S>>,
+    pub decision: Option<Decision<T, S>>,
+    pub certificate: Option<DecisionCertificate<T, S>>,
+    /// A vote that is enough for others to reach our decision, it is kept as it was
+    /// signed when we decided and never re-signed with a key we were given since.
+    pub decision_vote: Option<SignedVote<T, S>>,
+    /// The single value `decision` resolved to, only recorded by a `Handover`.
+    /// It is derived locally and is not part of the decision others sign.
+    pub resolution: Option<T>,
+    /// Handles are shared by clones and ignored when comparing instances.
+    pub vote_log: Option<Arc<dyn VoteLog<T, S> + Send + Sync>>,
+    pub observer: Option<Arc<dyn ConsensusObserver<T> + Send + Sync>>,
+    /// How long we wait for progress before re-broadcasting or asking for anti-entropy.
+    pub timeout: Duration,
+    /// Set while we don't hold our share of the key of `elders`, e.g. once the elders
+    /// were rotated. Votes are still handled so we follow the decision, but we don't
+    /// cast any until we're given our key.
+    pub awaiting_secret_key: bool,
+    /// When we last saw progress, along with the number of votes processed by then.
+    last_progress: Option<(Duration, usize)>,
+    timeouts: usize,
+    merge_rounds: usize,
+    /// The first and the latest time given to `tick` while a vote was in progress,
+    /// decision latency is measured with the caller's clock.
+    #[cfg(feature = "metrics")]
+    started: Option<Duration>,
+    #[cfg(feature = "metrics")]
+    last_tick: Option<Duration>,
+}
+
+impl<T: Proposition, S: SignatureScheme> PartialEq for Consensus<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "metrics")]
+        if (self.started, self.last_tick) != (other.started, other.last_tick) {
+            return false;
+        }
+
+        self.elders == other.elders
+            && self.n_elders == other.n_elders
+            && self.secret_key == other.secret_key
+            && self.processed_votes_cache == other.processed_votes_cache
+            && self.votes == other.votes
+            && self.faults == other.faults
+            && self.decision == other.decision
+            && self.certificate == other.certificate
+            && self.decision_vote == other.decision_vote
+            && self.resolution == other.resolution
+            && self.timeout == other.timeout
+            && self.awaiting_secret_key == other.awaiting_secret_key
+            && self.last_progress == other.last_progress
+            && self.timeouts == other.timeouts
+            && self.merge_rounds == other.merge_rounds
+    }
 }
 
+impl<T: Proposition, S: SignatureScheme> Eq for Consensus<T, S> {}
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum VoteResponse<T: Proposition> {
+#[allow(clippy::large_enum_variant)]
+pub enum VoteResponse<T: Proposition, S: SignatureScheme = PublicKeySet> {
     WaitingForMoreVotes,
-    Broadcast(SignedVote<T>),
+    Broadcast(SignedVote<T, S>),
 }
 
-impl<T: Proposition> Consensus<T> {
-    pub fn from(
-        secret_key: (NodeId, SecretKeyShare),
-        elders: PublicKeySet,
-        n_elders: usize,
-    ) -> Self {
-        Consensus::<T> {
+/// What to do when a generation has not made progress within the timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum TimeoutResponse<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    /// Our vote may have been dropped, broadcast it again.
+    Rebroadcast(SignedVote<T, S>),
+    /// Re-broadcasting didn't help, send our digest to ask others for the votes we're missing.
+    AntiEntropy(VoteDigest<S>),
+}
+
+/// A compact summary of the votes a peer has seen in a generation, a peer sends its
+/// digest to be sent only the votes it is missing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteDigest<S: SignatureScheme = PublicKeySet> {
+    pub gen: Generation,
+    pub decided: bool,
+    /// Signatures of every vote processed so far, including the votes nested in ballots.
+    pub votes: BTreeSet<S::SignatureShare>,
+}
+
+impl<T: Proposition, S: SignatureScheme> Consensus<T, S> {
+    pub fn from(secret_key: (NodeId, S::SecretKeyShare), elders: S, n_elders: usize) -> Self {
+        Consensus::<T, S> {
             elders,
             n_elders,
             secret_key,
@@ -39,18 +117,87 @@ impl<T: Proposition> Consensus<T> {
             votes: Default::default(),
             faults: Default::default(),
             decision: None,
+            certificate: None,
+            decision_vote: None,
+            resolution: None,
+            vote_log: None,
+            observer: None,
+            timeout: DEFAULT_TIMEOUT,
+            awaiting_secret_key: false,
+            last_progress: None,
+            timeouts: 0,
+            merge_rounds: 0,
+            #[cfg(feature = "metrics")]
+            started: None,
+            #[cfg(feature = "metrics")]
+            last_tick: None,
+        }
+    }
+
+    /// Every vote we cast is appended to `vote_log` before it is returned for broadcasting.
+    /// Votes already in the log are reloaded so that we can't contradict a vote we cast
+    /// before restarting.
+    pub fn with_vote_log(
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
+        n_elders: usize,
+        vote_log: Arc<dyn VoteLog<T, S> + Send + Sync>,
+    ) -> Result<Self> {
+        let mut consensus = Consensus::from(secret_key, elders, n_elders);
+        for vote in vote_log.load()? {
+            vote.validate(&consensus.elders, &consensus.processed_votes_cache)?;
+            consensus.log_processed_signed_vote(&vote);
+        }
+        consensus.vote_log = Some(vote_log);
+        Ok(consensus)
+    }
+
+    pub fn restore(secret_key: (NodeId, S::SecretKeyShare), state: ConsensusState<T, S>) -> Self {
+        let mut consensus = Consensus::from(secret_key, state.elders, state.n_elders);
+        for vote in state.votes.values() {
+            consensus.log_processed_signed_vote(vote);
+        }
+        consensus.faults = state.faults;
+        consensus.decision = state.decision;
+        consensus.certificate = state.certificate;
+        consensus.decision_vote = state.decision_vote;
+        consensus
+    }
+
+    pub fn state(&self) -> ConsensusState<T, S> {
+        ConsensusState {
+            elders: self.elders.clone(),
+            n_elders: self.n_elders,
+            votes: self.votes.clone(),
+            faults: self.faults.clone(),
+            decision: self.decision.clone(),
+            certificate: self.certificate.clone(),
+            decision_vote: self.decision_vote.clone(),
+        }
+    }
+
+    /// Notifies `observer` of every state transition from now on.
+    pub fn set_observer(&mut self, observer: impl ConsensusObserver<T> + Send + Sync + 'static) {
+        self.observer = Some(Arc::new(observer));
+    }
+
+    pub(crate) fn observe(&mut self, event: ConsensusEvent<T>) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_event("vote", &event);
+        if let Some(observer) = self.observer.as_ref() {
+            observer.observe(&event);
         }
     }
 
-    pub fn sign<M: Serialize>(&self, msg: &M) -> Result<SignatureShare> {
-        Ok(self.secret_key.1.sign(bincode::serialize(msg)?))
+    pub fn sign<M: Serialize>(&self, msg: &M) -> Result<S::SignatureShare> {
+        Ok(S::sign(&self.secret_key.1, &bincode::serialize(msg)?))
     }
 
     pub fn id(&self) -> NodeId {
         self.secret_key.0
     }
 
-    pub fn faults(&self) -> BTreeSet<Fault<T>> {
+    pub fn faults(&self) -> BTreeSet<Fault<T, S>> {
         BTreeSet::from_iter(self.faults.values().cloned())
     }
 
@@ -60,16 +207,17 @@ impl<T: Proposition> Consensus<T> {
 
     pub fn build_super_majority_vote(
         &self,
-        votes: BTreeSet<SignedVote<T>>,
-        faults: BTreeSet<Fault<T>>,
+        votes: BTreeSet<SignedVote<T, S>>,
+        faults: BTreeSet<Fault<T, S>>,
         gen: Generation,
-    ) -> Result<SignedVote<T>> {
+    ) -> Result<SignedVote<T, S>> {
         let faulty = BTreeSet::from_iter(faults.iter().map(Fault::voter_at_fault));
 
-        let proposals = VoteCount::count(&votes, &faulty)
-            .candidate_with_most_votes()
-            .map(|(candidate, _)| candidate.proposals.clone())
-            .unwrap_or_default()
+        let candidate_proposals = crate::vote::super_majority_candidate(&votes, &faulty).proposals;
+
+        let certificate_share = (self.id(), self.sign(&(gen, &candidate_proposals))?);
+
+        let proposals = candidate_proposals
             .into_iter()
             .map(|proposal| {
                 let sig = self.sign(&proposal)?;
@@ -77,7 +225,12 @@ impl<T: Proposition> Consensus<T> {
             })
             .collect::<Result<_>>()?;
 
-        let ballot = Ballot::SuperMajority { votes, proposals }.simplify();
+        let ballot = Ballot::SuperMajority {
+            votes,
+            proposals,
+            certificate_share,
+        }
+        .simplify();
 
         let vote = Vote {
             gen,
@@ -87,11 +240,100 @@ impl<T: Proposition> Consensus<T> {
         self.sign_vote(vote)
     }
 
+    pub fn digest(&self, gen: Generation) -> VoteDigest<S> {
+        VoteDigest {
+            gen,
+            decided: self.decision.is_some(),
+            votes: self.processed_votes_cache.clone(),
+        }
+    }
+
+    /// The votes needed by a peer with the given digest of this generation.
+    ///
+    /// Once we've decided, the decision is sent as a single super majority vote
+    /// rather than every vote that led to it.
+    pub fn missing_votes(&self, digest: &VoteDigest<S>) -> Result<Vec<SignedVote<T, S>>> {
+        if digest.decided {
+            return Ok(Vec::new());
+        }
+
+        if let Some(vote) = &self.decision_vote {
+            return Ok(vec![vote.clone()]);
+        }
+
+        Ok(self
+            .votes
+            .values()
+            .filter(|vote| !digest.votes.contains(&vote.sig))
+            .cloned()
+            .collect())
+    }
+
+    /// Drives timeouts, `now` is the time since some epoch of the caller's choosing so
+    /// that simulations can control the clock.
+    ///
+    /// Processing a new vote counts as progress, if there's a vote in progress and none
+    /// was processed within `timeout` since the last progress or timeout, we time out.
+    /// The `sn_consensus_decision_seconds` metric is measured with this clock as well, as
+    /// the time between the first and the last tick before the decision.
+    pub fn tick(&mut self, now: Duration) -> Option<TimeoutResponse<T, S>> {
+        #[cfg(feature = "metrics")]
+        if self.decision.is_none() && !self.votes.is_empty() {
+            self.started.get_or_insert(now);
+            self.last_tick = Some(now);
+        }
+
+        if self.decision.is_some() || self.votes.is_empty() {
+            self.last_progress = None;
+            return None;
+        }
+
+        let processed = self.processed_votes_cache.len();
+        match self.last_progress {
+            Some((at, seen)) if seen == processed => {
+                if now.saturating_sub(at) < self.timeout {
+                    return None;
+                }
+                self.last_progress = Some((now, processed));
+                self.on_timeout()
+            }
+            _ => {
+                self.last_progress = Some((now, processed));
+                self.timeouts = 0;
+                None
+            }
+        }
+    }
+
+    /// Called when a generation has stalled, on the first timeout we re-broadcast our
+    /// vote, after that we escalate to anti-entropy.
+    pub fn on_timeout(&mut self) -> Option<TimeoutResponse<T, S>> {
+        if self.decision.is_some() {
+            return None;
+        }
+        let gen = self.votes.values().next()?.vote.gen;
+
+        self.timeouts += 1;
+        match self.votes.get(&self.id()) {
+            Some(vote) if self.timeouts == 1 => {
+                info!("[{}] timed out, re-broadcasting our vote", self.id());
+                Some(TimeoutResponse::Rebroadcast(vote.clone()))
+            }
+            _ => {
+                info!("[{}] timed out, escalating to anti-entropy", self.id());
+                Some(TimeoutResponse::AntiEntropy(self.digest(gen)))
+            }
+        }
+    }
+
     // handover: gen = gen
     // membership: gen = pending_gen
     /// Handles a signed vote
     /// Returns the vote we cast and the reached consensus vote in case consensus was reached
-    pub fn handle_signed_vote(&mut self, signed_vote: SignedVote<T>) -> Result<VoteResponse<T>> {
+    pub fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<T, S>,
+    ) -> Result<VoteResponse<T, S>> {
         info!("[{}] handling vote {:?}", self.id(), signed_vote);
 
         if self.decision.is_some() {
@@ -106,27 +348,59 @@ impl<T: Proposition> Consensus<T> {
 
         signed_vote.validate(&self.elders, &self.processed_votes_cache)?;
 
+        let gen = signed_vote.vote.gen;
+        self.observe(ConsensusEvent::VoteReceived {
+            gen,
+            voter: signed_vote.voter,
+        });
+
         if let Err(faults) = signed_vote.detect_byzantine_faults(
             &self.elders,
             &self.votes,
             &self.processed_votes_cache,
         ) {
             info!("[{}] Found faults {:?}", self.id(), faults);
-            self.faults.extend(faults);
+            for (voter, fault) in faults {
+                if self.faults.insert(voter, fault).is_none() {
+                    self.observe(ConsensusEvent::FaultDetected { gen, voter });
+                }
+            }
+        }
+
+        // Adopt the faults proven by others, elders that hold different evidence would
+        // otherwise count different candidates and may keep the vote split forever.
+        for fault in signed_vote
+            .unpack_votes()
+            .flat_map(|v| v.vote.faults.iter())
+        {
+            let voter = fault.voter_at_fault();
+            if !self.faults.contains_key(&voter) && fault.validate(&self.elders).is_ok() {
+                info!("[{}] adopting fault {:?}", self.id(), fault);
+                self.faults.insert(voter, fault.clone());
+                self.observe(ConsensusEvent::FaultDetected { gen, voter });
+            }
         }
 
         if self.faults.contains_key(&signed_vote.voter) {
-            info!("[{}] dropping vote from faulty voter", self.id());
-            return Ok(VoteResponse::WaitingForMoreVotes);
+            // A tie is broken over the final votes of every elder, faulty ones included.
+            let past_merge_bound =
+                !crate::vote::final_votes(self.votes.values().chain(std::iter::once(&signed_vote)))
+                    .is_empty();
+            if !past_merge_bound {
+                info!("[{}] dropping vote from faulty voter", self.id());
+                return Ok(VoteResponse::WaitingForMoreVotes);
+            }
         }
 
         self.process_signed_vote(signed_vote)
     }
 
-    fn process_signed_vote(&mut self, signed_vote: SignedVote<T>) -> Result<VoteResponse<T>> {
+    fn process_signed_vote(&mut self, signed_vote: SignedVote<T, S>) -> Result<VoteResponse<T, S>> {
         self.log_processed_signed_vote(&signed_vote);
 
-        if let Some(proposals) = signed_vote.vote_count().get_decision(&self.elders)? {
+        let gen = signed_vote.vote.gen;
+        let signed_vote_count = signed_vote.vote_count();
+        if let Some(proposals) = signed_vote_count.get_decision(&self.elders)? {
             // This case is here to handle situations where this node has recieved
             // a faulty vote previously that is preventing it from accepting a network
             // decision using the sm_over_sm logic below.
@@ -140,7 +414,20 @@ impl<T: Proposition> Consensus<T> {
                 proposals,
                 faults: signed_vote.vote.faults.clone(),
             };
-            self.decision = Some(decision);
+            // their vote is enough for others to reach the decision if we can't sign our own
+            let vote = if self.awaiting_secret_key {
+                signed_vote.clone()
+            } else {
+                let vote = self.build_super_majority_vote(
+                    decision.votes.clone(),
+                    decision.faults.clone(),
+                    gen,
+                )?;
+                self.persist_vote(&vote)?;
+                vote
+            };
+            self.certificate = signed_vote_count.get_certificate(gen, &self.elders)?;
+            self.decide(gen, decision, Some(vote));
             return Ok(VoteResponse::WaitingForMoreVotes);
         }
 
@@ -157,17 +444,38 @@ impl<T: Proposition> Consensus<T> {
                 proposals,
                 faults: self.faults(),
             };
+            let certificate = vote_count.get_certificate(gen, &self.elders)?;
+            if self.awaiting_secret_key {
+                // peers are sent the votes that led to the decision instead of our vote
+                self.certificate = certificate;
+                self.decide(gen, decision, None);
+                return Ok(VoteResponse::WaitingForMoreVotes);
+            }
+
             let vote = self.build_super_majority_vote(
                 decision.votes.clone(),
                 decision.faults.clone(),
                 signed_vote.vote.gen,
             )?;
-            self.decision = Some(decision);
+            self.persist_vote(&vote)?;
+            self.certificate = certificate;
+            self.decide(gen, decision, Some(vote.clone()));
             return Ok(VoteResponse::Broadcast(vote));
         }
 
+        if self.awaiting_secret_key {
+            info!("[{}] waiting for our secret key before voting", self.id());
+            return Ok(VoteResponse::WaitingForMoreVotes);
+        }
+
+        let finals = crate::vote::final_votes(self.votes.values());
+        if !finals.is_empty() {
+            return self.break_tie(gen, finals);
+        }
+
         if vote_count.is_split_vote(&self.elders, self.n_elders) {
             info!("[{}] Detected split vote", self.id());
+            self.observe(ConsensusEvent::SplitVote { gen });
             let merge_vote = Vote {
                 gen: signed_vote.vote.gen,
                 ballot: Ballot::Merge(self.votes.values().cloned().collect()).simplify(),
@@ -175,12 +483,17 @@ impl<T: Proposition> Consensus<T> {
             };
             let signed_merge_vote = self.sign_vote(merge_vote)?;
 
-            let resp = if vote_count != signed_merge_vote.vote_count() {
-                info!("[{}] broadcasting merge.", self.id());
-                VoteResponse::Broadcast(self.cast_vote(signed_merge_vote)?)
-            } else {
+            let resp = if vote_count == signed_merge_vote.vote_count() {
                 info!("[{}] merge does not change counts, waiting.", self.id());
                 VoteResponse::WaitingForMoreVotes
+            } else {
+                info!("[{}] broadcasting merge.", self.id());
+                self.merge_rounds += 1;
+                self.observe(ConsensusEvent::MergeCast {
+                    gen,
+                    round: self.merge_rounds,
+                });
+                VoteResponse::Broadcast(self.cast_vote(signed_merge_vote)?)
             };
 
             return Ok(resp);
@@ -205,6 +518,7 @@ impl<T: Proposition> Consensus<T> {
                 signed_vote.vote.gen,
             )?;
 
+            self.observe(ConsensusEvent::SuperMajorityCast { gen });
             return Ok(VoteResponse::Broadcast(self.cast_vote(signed_vote)?));
         }
 
@@ -229,7 +543,93 @@ impl<T: Proposition> Consensus<T> {
         }
     }
 
-    pub fn sign_vote(&self, vote: Vote<T>) -> Result<SignedVote<T>> {
+    fn decide(
+        &mut self,
+        gen: Generation,
+        decision: Decision<T, S>,
+        vote: Option<SignedVote<T, S>>,
+    ) {
+        let proposals = Vec::from_iter(decision.proposals.keys().cloned());
+        self.decision = Some(decision);
+        self.decision_vote = vote;
+        self.observe(ConsensusEvent::Decided { gen, proposals });
+
+        #[cfg(feature = "metrics")]
+        {
+            let labels = [("engine", "vote")];
+            crate::metrics::record_histogram(
+                "sn_consensus_merges_per_generation",
+                &labels,
+                self.merge_rounds as f64,
+            );
+            if let (Some(started), Some(last_tick)) = (self.started, self.last_tick) {
+                crate::metrics::record_histogram(
+                    "sn_consensus_decision_seconds",
+                    &labels,
+                    last_tick.saturating_sub(started).as_secs_f64(),
+                );
+            }
+        }
+    }
+
+    /// The number of merge votes we cast in this generation.
+    pub fn merge_rounds(&self) -> usize {
+        self.merge_rounds
+    }
+
+    /// Once a voter reached the merge bound, see `SignedVote::is_exhausted`, nobody merges
+    /// anymore. We cast our final vote, a merge of every vote we've seen, and once we hold
+    /// the final vote of every elder we cast a super majority over their tie-break, see
+    /// `vote::tie_break`. The tie-break is cast again if we learn of a final vote that
+    /// changes it, i.e. one that a faulty elder cast in place of the one we held.
+    fn break_tie(
+        &mut self,
+        gen: Generation,
+        finals: BTreeMap<NodeId, SignedVote<T, S>>,
+    ) -> Result<VoteResponse<T, S>> {
+        if !finals.contains_key(&self.id()) {
+            warn!(
+                "[{}] the merge bound was reached, casting our final vote",
+                self.id()
+            );
+            let final_vote = self.sign_vote(Vote {
+                gen,
+                ballot: Ballot::Merge(self.votes.values().cloned().collect()).simplify(),
+                faults: self.faults(),
+            })?;
+            self.merge_rounds += 1;
+            self.observe(ConsensusEvent::MergeCast {
+                gen,
+                round: self.merge_rounds,
+            });
+            return Ok(VoteResponse::Broadcast(self.cast_vote(final_vote)?));
+        }
+
+        if finals.len() < self.n_elders {
+            info!("[{}] waiting for the final vote of every elder", self.id());
+            return Ok(VoteResponse::WaitingForMoreVotes);
+        }
+
+        let candidate = crate::vote::tie_break(&finals);
+        let our_vote = self.votes.get(&self.id()).cloned();
+        if let Some(our_vote) = our_vote.as_ref() {
+            if our_vote.vote.is_tie_break() && our_vote.candidate() == candidate {
+                info!("[{}] We've already broken the tie, waiting", self.id());
+                return Ok(VoteResponse::WaitingForMoreVotes);
+            }
+        }
+
+        info!("[{}] breaking the tie for {candidate:?}", self.id());
+        let signed_vote = self.build_super_majority_vote(
+            finals.into_values().chain(our_vote).collect(),
+            self.faults(),
+            gen,
+        )?;
+        self.observe(ConsensusEvent::SuperMajorityCast { gen });
+        Ok(VoteResponse::Broadcast(self.cast_vote(signed_vote)?))
+    }
+
+    pub fn sign_vote(&self, vote: Vote<T, S>) -> Result<SignedVote<T, S>> {
         Ok(SignedVote {
             voter: self.id(),
             sig: self.sign(&vote)?,
@@ -237,19 +637,30 @@ impl<T: Proposition> Consensus<T> {
         })
     }
 
-    pub fn cast_vote(&mut self, signed_vote: SignedVote<T>) -> Result<SignedVote<T>> {
+    pub fn cast_vote(&mut self, signed_vote: SignedVote<T, S>) -> Result<SignedVote<T, S>> {
+        if self.awaiting_secret_key {
+            return Err(Error::MissingSecretKey);
+        }
         info!("[{}] casting vote {:?}", self.id(), signed_vote);
+        self.persist_vote(&signed_vote)?;
         match self.handle_signed_vote(signed_vote.clone())? {
             VoteResponse::WaitingForMoreVotes => Ok(signed_vote),
             VoteResponse::Broadcast(vote) => Ok(vote),
         }
     }
 
-    fn have_we_processed_vote(&self, signed_vote: &SignedVote<T>) -> bool {
+    fn persist_vote(&self, signed_vote: &SignedVote<T, S>) -> Result<()> {
+        match self.vote_log.as_ref() {
+            Some(vote_log) => vote_log.append(signed_vote),
+            None => Ok(()),
+        }
+    }
+
+    fn have_we_processed_vote(&self, signed_vote: &SignedVote<T, S>) -> bool {
         self.processed_votes_cache.contains(&signed_vote.sig)
     }
 
-    fn log_processed_signed_vote(&mut self, signed_vote: &SignedVote<T>) {
+    fn log_processed_signed_vote(&mut self, signed_vote: &SignedVote<T, S>) {
         for vote in signed_vote.unpack_votes() {
             if self.processed_votes_cache.insert(vote.sig.clone()) {
                 let existing_vote = self.votes.entry(vote.voter).or_insert_with(|| vote.clone());
@@ -264,14 +675,217 @@ impl<T: Proposition> Consensus<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use blsttc::SecretKeySet;
-    use rand::{prelude::StdRng, SeedableRng};
+    use crate::{Error, MemoryVoteLog, SecretKeySet};
+    use rand::{prelude::StdRng, Rng, SeedableRng};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails every append once `appends_left` reaches zero.
+    #[derive(Debug)]
+    struct FailingVoteLog {
+        appends_left: AtomicUsize,
+    }
+
+    impl FailingVoteLog {
+        fn new(appends_left: usize) -> Self {
+            Self {
+                appends_left: AtomicUsize::new(appends_left),
+            }
+        }
+    }
+
+    impl VoteLog<u8> for FailingVoteLog {
+        fn append(&self, _vote: &SignedVote<u8>) -> Result<()> {
+            self.appends_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .map_err(|_| Error::IO(std::io::ErrorKind::Other.into()))?;
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Vec<SignedVote<u8>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_vote_log_reloads_our_vote_after_restart() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let elders_sk = SecretKeySet::random(2, &mut rng);
+        let vote_log = MemoryVoteLog::default();
+        let mut proc = Consensus::<u8>::with_vote_log(
+            (1, elders_sk.secret_key_share(1usize)),
+            elders_sk.public_keys(),
+            4,
+            Arc::new(vote_log.clone()),
+        )
+        .unwrap();
+
+        let vote = proc
+            .sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(1),
+                faults: Default::default(),
+            })
+            .unwrap();
+        let vote = proc.cast_vote(vote).unwrap();
+        assert_eq!(vote_log.load().unwrap(), vec![vote.clone()]);
+
+        // restart with the same secret key
+        let proc = Consensus::<u8>::with_vote_log(
+            (1, elders_sk.secret_key_share(1usize)),
+            elders_sk.public_keys(),
+            4,
+            Arc::new(vote_log),
+        )
+        .unwrap();
+        assert_eq!(proc.votes.get(&1), Some(&vote));
+
+        // a different ballot would now be seen as us changing our vote
+        let conflicting_vote = proc
+            .sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(2),
+                faults: Default::default(),
+            })
+            .unwrap();
+        assert!(conflicting_vote
+            .detect_byzantine_faults(&proc.elders, &proc.votes, &proc.processed_votes_cache)
+            .is_err());
+    }
+
+    #[test]
+    fn test_vote_is_not_returned_if_vote_log_fails() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let elders_sk = SecretKeySet::random(0, &mut rng);
+        let mut proc = Consensus::<u8>::with_vote_log(
+            (1, elders_sk.secret_key_share(1usize)),
+            elders_sk.public_keys(),
+            1,
+            Arc::new(FailingVoteLog::new(0)),
+        )
+        .unwrap();
+
+        let vote = proc
+            .sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(1),
+                faults: Default::default(),
+            })
+            .unwrap();
+        assert!(matches!(proc.cast_vote(vote), Err(Error::IO(_))));
+        assert!(proc.votes.is_empty());
+    }
+
+    #[test]
+    fn test_no_decision_if_vote_log_fails() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let elders_sk = SecretKeySet::random(2, &mut rng);
+        let mut states = Vec::from_iter((1..=4).map(|id| {
+            Consensus::<u8>::from(
+                (id, elders_sk.secret_key_share(id as usize)),
+                elders_sk.public_keys(),
+                4,
+            )
+        }));
+        // our super majority is logged, our super majority over super majorities is not
+        states[0].vote_log = Some(Arc::new(FailingVoteLog::new(1)));
+
+        let proposals = BTreeSet::from_iter(states[1..].iter().map(|state| {
+            state
+                .sign_vote(Vote {
+                    gen: 0,
+                    ballot: Ballot::Propose(1),
+                    faults: Default::default(),
+                })
+                .unwrap()
+        }));
+        let super_majorities = Vec::from_iter(states[1..3].iter().map(|state| {
+            state
+                .build_super_majority_vote(proposals.clone(), Default::default(), 0)
+                .unwrap()
+        }));
+
+        let resp = states[0].handle_signed_vote(super_majorities[0].clone());
+        assert!(matches!(resp, Ok(VoteResponse::Broadcast(_))));
+
+        let resp = states[0].handle_signed_vote(super_majorities[1].clone());
+        assert!(matches!(resp, Err(Error::IO(_))));
+        assert!(states[0].decision.is_none());
+        assert!(states[0].certificate.is_none());
+    }
+
+    #[test]
+    fn test_split_vote_is_broken_once_the_merge_bound_is_reached() {
+        for seed in 0..8u8 {
+            let mut rng = StdRng::from_seed([seed; 32]);
+            let elders_sk = SecretKeySet::random(2, &mut rng);
+            let mut states = Vec::from_iter((1..=4).map(|id| {
+                Consensus::<u8>::from(
+                    (id, elders_sk.secret_key_share(id as usize)),
+                    elders_sk.public_keys(),
+                    4,
+                )
+            }));
+
+            let mut msgs = Vec::new();
+            for (state, p) in states.iter_mut().zip([3, 1, 2, 1]) {
+                let vote = state
+                    .sign_vote(Vote {
+                        gen: 0,
+                        ballot: Ballot::Propose(p),
+                        faults: Default::default(),
+                    })
+                    .unwrap();
+                msgs.push(state.cast_vote(vote).unwrap());
+            }
+
+            // the first elder merges twice before seeing anyone else, that's the bound
+            // for the single voter it counted
+            for _ in 0..2 {
+                let merge = states[0]
+                    .sign_vote(Vote {
+                        gen: 0,
+                        ballot: Ballot::Merge(BTreeSet::from_iter([states[0].votes[&1].clone()])),
+                        faults: Default::default(),
+                    })
+                    .unwrap();
+                msgs.push(states[0].cast_vote(merge).unwrap());
+            }
+            assert!(states[0].votes[&1].is_exhausted());
+
+            while !msgs.is_empty() {
+                let vote = msgs.remove(rng.gen_range(0..msgs.len()));
+                for state in states.iter_mut().filter(|s| s.id() != vote.voter) {
+                    if let VoteResponse::Broadcast(vote) =
+                        state.handle_signed_vote(vote.clone()).unwrap()
+                    {
+                        msgs.push(vote);
+                    }
+                }
+            }
+
+            let decided = |state: &Consensus<u8>| {
+                state
+                    .decision
+                    .as_ref()
+                    .map(|d| BTreeSet::from_iter(d.proposals.keys().copied()))
+            };
+            assert!(decided(&states[0]).is_some());
+            for state in states.iter() {
+                assert!(state.faults.is_empty());
+                assert_eq!(decided(state), decided(&states[0]));
+                assert_eq!(state.certificate, states[0].certificate);
+                assert!(state.decision_vote.as_ref().unwrap().vote.is_tie_break());
+            }
+        }
+    }
 
     #[test]
     fn test_have_we_seen_this_vote_before() {
         let mut rng = StdRng::from_seed([0u8; 32]);
         let elders_sk = SecretKeySet::random(10, &mut rng);
-        let mut states = Vec::from_iter((1..=10).into_iter().map(|id| {
+        let mut states = Vec::from_iter((1..=10).map(|id| {
             Consensus::from(
                 (id, elders_sk.secret_key_share(id as usize)),
                 elders_sk.public_keys(),
@@ -320,4 +934,55 @@ mod tests {
             .unwrap();
         assert!(!states[0].have_we_processed_vote(&new_vote));
     }
+
+    #[test]
+    fn test_tick_rebroadcasts_then_escalates_to_anti_entropy() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let elders_sk = SecretKeySet::random(2, &mut rng);
+        let mut procs = Vec::from_iter((0..2).map(|id| {
+            Consensus::<u8>::from(
+                (id, elders_sk.secret_key_share(id as usize)),
+                elders_sk.public_keys(),
+                4,
+            )
+        }));
+        let timeout = procs[0].timeout;
+        let start = Duration::from_secs(100);
+
+        // nothing is in progress, nothing can time out
+        assert_eq!(procs[0].tick(start), None);
+        assert_eq!(procs[0].tick(start + timeout), None);
+
+        let vote = procs[0]
+            .sign_vote(Vote {
+                gen: 1,
+                ballot: Ballot::Propose(1),
+                faults: Default::default(),
+            })
+            .unwrap();
+        let vote = procs[0].cast_vote(vote).unwrap();
+
+        assert_eq!(procs[0].tick(start), None);
+        assert_eq!(procs[0].tick(start + timeout / 2), None);
+        assert_eq!(
+            procs[0].tick(start + timeout),
+            Some(TimeoutResponse::Rebroadcast(vote.clone()))
+        );
+        assert_eq!(procs[0].tick(start + timeout * 3 / 2), None);
+        assert_eq!(
+            procs[0].tick(start + timeout * 2),
+            Some(TimeoutResponse::AntiEntropy(procs[0].digest(1)))
+        );
+
+        // progress resets the timer
+        procs[1].handle_signed_vote(vote).unwrap();
+        let their_vote = procs[1].votes[&1].clone();
+        procs[0].handle_signed_vote(their_vote).unwrap();
+        assert_eq!(procs[0].tick(start + timeout * 5 / 2), None);
+        assert_eq!(procs[0].tick(start + timeout * 3), None);
+        assert!(matches!(
+            procs[0].tick(start + timeout * 7 / 2),
+            Some(TimeoutResponse::Rebroadcast(_))
+        ));
+    }
 }
@@ -1,20 +1,42 @@
 use std::collections::{BTreeMap, BTreeSet};
 
-use blsttc::{PublicKeySet, Signature};
 use log::warn;
 use serde::{Deserialize, Serialize};
 
-use crate::{Error, Fault, Generation, NodeId, Proposition, Result, SignedVote, VoteCount};
+use crate::{
+    Error, Fault, Generation, NodeId, Proposition, PublicKeySet, Result, SignatureScheme,
+    SignedVote, VoteCount,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct Decision<T: Proposition> {
-    pub votes: BTreeSet<SignedVote<T>>,
-    pub proposals: BTreeMap<T, Signature>,
-    pub faults: BTreeSet<Fault<T>>,
+pub struct Decision<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub votes: BTreeSet<SignedVote<T, S>>,
+    pub proposals: BTreeMap<T, S::Signature>,
+    pub faults: BTreeSet<Fault<T, S>>,
 }
 
-impl<T: Proposition> Decision<T> {
-    pub fn validate(&self, voters: &PublicKeySet) -> Result<()> {
+/// A compact proof of a decision, it can be verified with the section public key
+/// alone, without replaying the votes that led to the decision.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DecisionCertificate<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub gen: Generation,
+    pub proposals: BTreeSet<T>,
+    pub signature: S::Signature,
+}
+
+impl<T: Proposition, S: SignatureScheme> DecisionCertificate<T, S> {
+    pub fn verify(&self, public_key: &S::PublicKey) -> Result<()> {
+        let msg = bincode::serialize(&(self.gen, &self.proposals))?;
+        if S::verify(public_key, &msg, &self.signature) {
+            Ok(())
+        } else {
+            Err(Error::InvalidDecisionCertificate)
+        }
+    }
+}
+
+impl<T: Proposition, S: SignatureScheme> Decision<T, S> {
+    pub fn validate(&self, voters: &S) -> Result<()> {
         let all_votes = self.votes_by_voter();
         let known_faulty_voters = self.faulty_ids();
         let expected_generation = self.generation()?;
@@ -56,7 +78,7 @@ impl<T: Proposition> Decision<T> {
         Ok(())
     }
 
-    pub fn votes_by_voter(&self) -> BTreeMap<NodeId, SignedVote<T>> {
+    pub fn votes_by_voter(&self) -> BTreeMap<NodeId, SignedVote<T, S>> {
         let mut all_votes = BTreeMap::new();
 
         for vote in self.votes.iter().flat_map(|v| v.unpack_votes()) {
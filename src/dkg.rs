@@ -0,0 +1,589 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use blsttc::group::Group;
+use blsttc::poly::{BivarCommitment, BivarPoly, Poly};
+use blsttc::{Ciphertext, Fr, G1Projective, PublicKey, SecretKey, SecretKeyShare};
+use core::fmt::Debug;
+use log::info;
+use rand::{CryptoRng, Rng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::consensus::{Consensus, VoteResponse};
+use crate::vote::{Ballot, SignedVote, Vote};
+use crate::{Error, NodeId, PublicKeySet, Result, SignatureScheme};
+
+/// The dealers whose parts are combined into the generated key.
+pub type DealerSet = BTreeSet<NodeId>;
+
+/// Our share of the generated key along with the key set of all participants,
+/// ready to be handed to `Membership::from` or `Handover::from`.
+pub type DkgOutcome = ((NodeId, SecretKeyShare), blsttc::PublicKeySet);
+
+type PartId = (NodeId, [u8; 32]);
+
+#[derive(Debug, Error)]
+pub enum DkgError {
+    #[error("{0} is not a participant of this DKG")]
+    UnknownParticipant(NodeId),
+    #[error("Part from dealer {dealer} commits to a polynomial of degree {degree}, expected {threshold}")]
+    InvalidPartDegree {
+        dealer: NodeId,
+        degree: usize,
+        threshold: usize,
+    },
+    #[error("A vote proposed {dealers} dealers, more than {threshold} are needed")]
+    NotEnoughDealers { dealers: usize, threshold: usize },
+    #[error("We don't have a complete part from dealer {0}")]
+    MissingPart(NodeId),
+    #[error("Not enough valid values to recover our row of dealer {0}'s part")]
+    NotEnoughValues(NodeId),
+}
+
+/// A dealer's contribution to the key: a commitment to a random bivariate polynomial `f`
+/// along with the row `f(i + 1, y)` of every participant `i`, encrypted to that participant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Part {
+    pub commitment: BivarCommitment,
+    pub rows: BTreeMap<NodeId, Ciphertext>,
+}
+
+/// Sent once a participant has verified its row of a part, carries the value of that
+/// row at every participant's index, encrypted to that participant.
+/// This way a participant can recover its row from the acks even if the dealer sent
+/// it a bad one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ack {
+    pub dealer: NodeId,
+    pub part: [u8; 32],
+    pub values: BTreeMap<NodeId, Ciphertext>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPart<S: SignatureScheme = PublicKeySet> {
+    pub part: Part,
+    pub voter: NodeId,
+    pub sig: S::SignatureShare,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAck<S: SignatureScheme = PublicKeySet> {
+    pub ack: Ack,
+    pub voter: NodeId,
+    pub sig: S::SignatureShare,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum DkgMessage<S: SignatureScheme = PublicKeySet> {
+    Part(SignedPart<S>),
+    Ack(SignedAck<S>),
+    Vote(SignedVote<DealerSet, S>),
+}
+
+/// Distributed generation of a blsttc key set among the participants.
+///
+/// Every participant deals a part, a part is complete once more than `2 * threshold`
+/// participants acked it. Participants then run consensus over the sets of complete
+/// parts they know of, the parts of every decided dealer are combined into the key.
+/// Any vote we broadcast is preceded by the parts and acks backing its proposals, so
+/// that a faulty dealer can't prevent others from validating our votes.
+///
+/// Messages are authenticated with `elders`, e.g. the keys of the current elders.
+/// Up to `threshold` faulty participants are tolerated as long as there are more than
+/// `3 * threshold` participants.
+#[derive(Debug)]
+pub struct Dkg<S: SignatureScheme = PublicKeySet> {
+    pub consensus: Consensus<DealerSet, S>,
+    pub threshold: usize,
+    pub encryption_keys: BTreeMap<NodeId, PublicKey>,
+    pub parts: BTreeMap<PartId, SignedPart<S>>,
+    pub acks: BTreeMap<PartId, BTreeMap<NodeId, SignedAck<S>>>,
+    decryption_key: SecretKey,
+    /// Votes we can't validate until the parts and acks backing them arrive, only the
+    /// latest vote of each voter is kept.
+    pending_votes: BTreeMap<NodeId, SignedVote<DealerSet, S>>,
+    forwarded: BTreeSet<NodeId>,
+}
+
+impl<S: SignatureScheme> Dkg<S> {
+    /// `encryption_keys` holds the key every participant's rows are encrypted to, we
+    /// decrypt ours with `decryption_key`.
+    pub fn from(
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
+        threshold: usize,
+        decryption_key: SecretKey,
+        encryption_keys: BTreeMap<NodeId, PublicKey>,
+    ) -> Self {
+        let n_participants = encryption_keys.len();
+        Dkg {
+            consensus: Consensus::from(secret_key, elders, n_participants),
+            threshold,
+            encryption_keys,
+            parts: Default::default(),
+            acks: Default::default(),
+            decryption_key,
+            pending_votes: Default::default(),
+            forwarded: Default::default(),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.consensus.id()
+    }
+
+    /// Deals our part, the returned messages are to be broadcast to every participant.
+    pub fn deal(&mut self, rng: &mut (impl Rng + CryptoRng)) -> Result<Vec<DkgMessage<S>>> {
+        let poly = BivarPoly::random(self.threshold, rng);
+        let rows = self
+            .encryption_keys
+            .iter()
+            .map(|(id, key)| {
+                let row = bincode::serialize(&poly.row(index(*id)))?;
+                Ok((*id, key.encrypt_with_rng(rng, row)))
+            })
+            .collect::<Result<_>>()?;
+        let part = Part {
+            commitment: poly.commitment(),
+            rows,
+        };
+        let signed_part = SignedPart {
+            sig: self.consensus.sign(&part)?,
+            voter: self.id(),
+            part,
+        };
+
+        let mut msgs = vec![DkgMessage::Part(signed_part.clone())];
+        msgs.extend(self.handle_part(signed_part)?);
+        Ok(msgs)
+    }
+
+    /// Handles a message from another participant, the returned messages are to be
+    /// broadcast to every participant.
+    pub fn handle_message(&mut self, msg: DkgMessage<S>) -> Result<Vec<DkgMessage<S>>> {
+        match msg {
+            DkgMessage::Part(signed_part) => self.handle_part(signed_part),
+            DkgMessage::Ack(signed_ack) => {
+                self.insert_ack(signed_ack)?;
+                self.progress()
+            }
+            DkgMessage::Vote(signed_vote) => self.handle_vote(signed_vote),
+        }
+    }
+
+    /// Our key share and the generated key set, once the participants agreed on the dealers.
+    pub fn outcome(&self) -> Result<Option<DkgOutcome>> {
+        let decision = match self.consensus.decision.as_ref() {
+            Some(decision) => decision,
+            None => return Ok(None),
+        };
+        let dealers = BTreeSet::from_iter(decision.proposals.keys().flatten().copied());
+
+        let mut commitment = Poly::zero().commitment();
+        let mut our_column = Poly::zero();
+        for dealer in dealers {
+            let part_id = self
+                .complete_part(dealer)
+                .ok_or(Error::Dkg(DkgError::MissingPart(dealer)))?;
+            let part = &self.parts[&part_id].part;
+
+            let values = Vec::from_iter(
+                self.acks[&part_id]
+                    .values()
+                    .filter_map(|signed_ack| self.verified_value(part, signed_ack))
+                    .take(self.threshold + 1),
+            );
+            if values.len() <= self.threshold {
+                return Err(Error::Dkg(DkgError::NotEnoughValues(dealer)));
+            }
+
+            commitment += part.commitment.row(0u64);
+            our_column += Poly::interpolate(values)?;
+        }
+
+        let secret_key_share = SecretKeyShare::from_mut(&mut our_column.evaluate(0u64));
+        Ok(Some((
+            (self.id(), secret_key_share),
+            blsttc::PublicKeySet::from(commitment),
+        )))
+    }
+
+    fn handle_part(&mut self, signed_part: SignedPart<S>) -> Result<Vec<DkgMessage<S>>> {
+        let dealer = signed_part.voter;
+        self.check_participant(dealer)?;
+        crate::verify_sig_share(
+            &signed_part.part,
+            &signed_part.sig,
+            dealer,
+            &self.consensus.elders,
+        )?;
+
+        let degree = signed_part.part.commitment.degree();
+        if degree != self.threshold {
+            return Err(Error::Dkg(DkgError::InvalidPartDegree {
+                dealer,
+                degree,
+                threshold: self.threshold,
+            }));
+        }
+
+        let part_id = (dealer, digest(&signed_part.part)?);
+        if self.parts.contains_key(&part_id) {
+            return Ok(vec![]);
+        }
+        // A dealer that equivocated may have a part other than the first one complete,
+        // we keep all of them but only ever ack the first one.
+        let first_part_from_dealer = !self.parts.keys().any(|(d, _)| *d == dealer);
+        self.parts.insert(part_id, signed_part.clone());
+
+        let mut msgs = Vec::new();
+        if first_part_from_dealer {
+            match self.verified_row(&signed_part.part) {
+                Some(row) => {
+                    let values = self
+                        .encryption_keys
+                        .iter()
+                        .map(|(id, key)| (*id, key.encrypt(row.evaluate(index(*id)).to_bytes_be())))
+                        .collect();
+                    let ack = Ack {
+                        dealer,
+                        part: part_id.1,
+                        values,
+                    };
+                    let signed_ack = SignedAck {
+                        sig: self.consensus.sign(&ack)?,
+                        voter: self.id(),
+                        ack,
+                    };
+                    self.insert_ack(signed_ack.clone())?;
+                    msgs.push(DkgMessage::Ack(signed_ack));
+                }
+                None => info!("[DKG {}] invalid row from dealer {dealer}", self.id()),
+            }
+        }
+
+        msgs.extend(self.progress()?);
+        Ok(msgs)
+    }
+
+    fn insert_ack(&mut self, signed_ack: SignedAck<S>) -> Result<()> {
+        self.check_participant(signed_ack.voter)?;
+        self.check_participant(signed_ack.ack.dealer)?;
+        crate::verify_sig_share(
+            &signed_ack.ack,
+            &signed_ack.sig,
+            signed_ack.voter,
+            &self.consensus.elders,
+        )?;
+
+        self.acks
+            .entry((signed_ack.ack.dealer, signed_ack.ack.part))
+            .or_default()
+            .entry(signed_ack.voter)
+            .or_insert(signed_ack);
+        Ok(())
+    }
+
+    fn handle_vote(&mut self, signed_vote: SignedVote<DealerSet, S>) -> Result<Vec<DkgMessage<S>>> {
+        if signed_vote.vote.gen != 0 {
+            return Err(Error::BadGeneration {
+                requested_gen: signed_vote.vote.gen,
+                gen: 0,
+            });
+        }
+        self.check_participant(signed_vote.voter)?;
+        signed_vote.validate_signature(&self.consensus.elders)?;
+
+        let complete_dealers = self.complete_dealers();
+        let mut verifiable = true;
+        for dealers in signed_vote.proposals() {
+            if dealers.len() <= self.threshold {
+                return Err(Error::Dkg(DkgError::NotEnoughDealers {
+                    dealers: dealers.len(),
+                    threshold: self.threshold,
+                }));
+            }
+            dealers
+                .iter()
+                .try_for_each(|dealer| self.check_participant(*dealer))?;
+            verifiable &= dealers.is_subset(&complete_dealers);
+        }
+
+        if !verifiable {
+            // The parts and acks backing this vote are still on their way.
+            let pending_vote = self
+                .pending_votes
+                .entry(signed_vote.voter)
+                .or_insert_with(|| signed_vote.clone());
+            if signed_vote.supersedes(pending_vote) {
+                *pending_vote = signed_vote;
+            }
+            return Ok(vec![]);
+        }
+
+        match self.consensus.handle_signed_vote(signed_vote)? {
+            VoteResponse::Broadcast(vote) => Ok(self.with_evidence(vote)),
+            VoteResponse::WaitingForMoreVotes => Ok(vec![]),
+        }
+    }
+
+    fn progress(&mut self) -> Result<Vec<DkgMessage<S>>> {
+        let mut msgs = Vec::new();
+        for signed_vote in std::mem::take(&mut self.pending_votes).into_values() {
+            msgs.extend(self.handle_vote(signed_vote)?);
+        }
+
+        let dealers = self.complete_dealers();
+        if dealers.len() > self.threshold
+            && self.consensus.decision.is_none()
+            && !self.consensus.votes.contains_key(&self.id())
+        {
+            info!("[DKG {}] proposing dealers {dealers:?}", self.id());
+            let signed_vote = self.consensus.sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(dealers),
+                faults: self.consensus.faults(),
+            })?;
+            let signed_vote = self.consensus.cast_vote(signed_vote)?;
+            msgs.extend(self.with_evidence(signed_vote));
+        }
+
+        Ok(msgs)
+    }
+
+    /// Precedes `signed_vote` with the parts and acks backing its proposals.
+    fn with_evidence(&mut self, signed_vote: SignedVote<DealerSet, S>) -> Vec<DkgMessage<S>> {
+        let mut msgs = Vec::new();
+        for dealer in signed_vote.proposals().into_iter().flatten() {
+            if !self.forwarded.insert(dealer) {
+                continue;
+            }
+            if let Some(part_id) = self.complete_part(dealer) {
+                msgs.push(DkgMessage::Part(self.parts[&part_id].clone()));
+                msgs.extend(self.acks[&part_id].values().cloned().map(DkgMessage::Ack));
+            }
+        }
+        msgs.push(DkgMessage::Vote(signed_vote));
+        msgs
+    }
+
+    fn complete_part(&self, dealer: NodeId) -> Option<PartId> {
+        self.parts
+            .keys()
+            .filter(|(d, _)| *d == dealer)
+            .find(|part_id| {
+                self.acks
+                    .get(part_id)
+                    .map(|acks| acks.len() > 2 * self.threshold)
+                    .unwrap_or(false)
+            })
+            .copied()
+    }
+
+    fn complete_dealers(&self) -> DealerSet {
+        BTreeSet::from_iter(
+            self.encryption_keys
+                .keys()
+                .copied()
+                .filter(|dealer| self.complete_part(*dealer).is_some()),
+        )
+    }
+
+    fn check_participant(&self, id: NodeId) -> Result<()> {
+        if self.encryption_keys.contains_key(&id) {
+            Ok(())
+        } else {
+            Err(Error::Dkg(DkgError::UnknownParticipant(id)))
+        }
+    }
+
+    /// Our row of `part`, if the dealer sent us one matching its commitment.
+    fn verified_row(&self, part: &Part) -> Option<Poly> {
+        let bytes = self.decryption_key.decrypt(part.rows.get(&self.id())?)?;
+        let row: Poly = bincode::deserialize(&bytes).ok()?;
+        (row.commitment() == part.commitment.row(index(self.id()))).then_some(row)
+    }
+
+    /// The value `f(acker + 1, id + 1)` of `part`'s polynomial carried by an ack, if it
+    /// matches the commitment.
+    fn verified_value(&self, part: &Part, signed_ack: &SignedAck<S>) -> Option<(u64, Fr)> {
+        let bytes = self
+            .decryption_key
+            .decrypt(signed_ack.ack.values.get(&self.id())?)?;
+        let value: Fr = Option::from(Fr::from_bytes_be(&bytes.try_into().ok()?))?;
+
+        let x = index(signed_ack.voter);
+        let expected = part.commitment.evaluate(x, index(self.id()));
+        (G1Projective::generator() * value == G1Projective::from(expected)).then_some((x, value))
+    }
+}
+
+/// Participant `id` is assigned the evaluation point `id + 1`, matching the share
+/// index blsttc's `PublicKeySet` uses for `id`.
+fn index(id: NodeId) -> u64 {
+    id as u64 + 1
+}
+
+fn digest(part: &Part) -> Result<[u8; 32]> {
+    use tiny_keccak::{Hasher, Sha3};
+
+    let mut sha3 = Sha3::v256();
+    let mut hash = [0; 32];
+    sha3.update(&bincode::serialize(part)?);
+    sha3.finalize(&mut hash);
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SecretKeySet;
+    use rand::{prelude::StdRng, SeedableRng};
+    use std::collections::VecDeque;
+
+    fn participants(n: NodeId, rng: &mut StdRng) -> Vec<Dkg> {
+        let elders_sk = SecretKeySet::random(2, &mut *rng);
+        let decryption_keys = BTreeMap::from_iter((1..=n).map(|id| (id, rng.gen::<SecretKey>())));
+        let encryption_keys = BTreeMap::from_iter(
+            decryption_keys
+                .iter()
+                .map(|(id, key)| (*id, key.public_key())),
+        );
+
+        Vec::from_iter(decryption_keys.into_iter().map(|(id, key)| {
+            Dkg::from(
+                (id, elders_sk.secret_key_share(id as u64)),
+                elders_sk.public_keys(),
+                1,
+                key,
+                encryption_keys.clone(),
+            )
+        }))
+    }
+
+    fn deliver(procs: &mut [Dkg], mut queue: VecDeque<(NodeId, DkgMessage)>) {
+        while let Some((source, msg)) = queue.pop_front() {
+            for proc in procs.iter_mut().filter(|p| p.id() != source) {
+                let id = proc.id();
+                for resp in proc.handle_message(msg.clone()).unwrap() {
+                    queue.push_back((id, resp));
+                }
+            }
+        }
+    }
+
+    fn assert_outcomes_form_a_key_set(procs: &[Dkg]) {
+        let outcomes = Vec::from_iter(procs.iter().map(|p| p.outcome().unwrap().unwrap()));
+        let public_keys = outcomes[0].1.clone();
+        let msg = b"signed by the generated key";
+
+        let mut shares = BTreeMap::new();
+        for ((id, secret_key_share), key_set) in outcomes {
+            assert_eq!(key_set, public_keys);
+            let sig = secret_key_share.sign(msg);
+            assert!(public_keys.verify_share(id, msg, &sig));
+            shares.insert(id, sig);
+        }
+
+        let sig = SignatureScheme::combine_signatures(&public_keys, &shares).unwrap();
+        assert!(public_keys.public_key().verify(&sig, msg));
+    }
+
+    #[test]
+    fn test_dkg_generates_a_key_set() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let mut procs = participants(4, &mut rng);
+
+        let mut queue = VecDeque::new();
+        for proc in procs.iter_mut() {
+            let id = proc.id();
+            queue.extend(proc.deal(&mut rng).unwrap().into_iter().map(|m| (id, m)));
+        }
+        deliver(&mut procs, queue);
+
+        assert_outcomes_form_a_key_set(&procs);
+    }
+
+    #[test]
+    fn test_dkg_tolerates_a_faulty_dealer() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let mut procs = participants(4, &mut rng);
+        let faulty = procs.pop().unwrap();
+
+        // the faulty dealer sends garbage rows and then goes silent
+        let poly = BivarPoly::random(faulty.threshold, &mut rng);
+        let part = Part {
+            commitment: poly.commitment(),
+            rows: BTreeMap::from_iter(faulty.encryption_keys.iter().map(|(id, key)| {
+                let garbage = bincode::serialize(&Poly::random(1, &mut rng)).unwrap();
+                (*id, key.encrypt(garbage))
+            })),
+        };
+        let bad_part = DkgMessage::Part(SignedPart {
+            sig: faulty.consensus.sign(&part).unwrap(),
+            voter: faulty.id(),
+            part,
+        });
+
+        let mut queue = VecDeque::from([(faulty.id(), bad_part)]);
+        for proc in procs.iter_mut() {
+            let id = proc.id();
+            queue.extend(proc.deal(&mut rng).unwrap().into_iter().map(|m| (id, m)));
+        }
+        deliver(&mut procs, queue);
+
+        for proc in procs.iter() {
+            let dealers = proc.consensus.decision.as_ref().unwrap().proposals.keys();
+            assert!(dealers.flatten().all(|dealer| *dealer != faulty.id()));
+        }
+        assert_outcomes_form_a_key_set(&procs);
+    }
+
+    #[test]
+    fn test_dkg_keeps_a_single_pending_vote_per_voter() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let mut procs = participants(4, &mut rng);
+
+        // no part is complete yet, every vote has to wait for its evidence
+        let votes = Vec::from_iter(
+            [[1, 2, 3], [2, 3, 4], [1, 3, 4]]
+                .into_iter()
+                .map(|dealers| {
+                    procs[1]
+                        .consensus
+                        .sign_vote(Vote {
+                            gen: 0,
+                            ballot: Ballot::Propose(BTreeSet::from(dealers)),
+                            faults: Default::default(),
+                        })
+                        .unwrap()
+                }),
+        );
+        for vote in votes.iter() {
+            assert!(procs[0]
+                .handle_message(DkgMessage::Vote(vote.clone()))
+                .unwrap()
+                .is_empty());
+        }
+        assert_eq!(
+            Vec::from_iter(procs[0].pending_votes.values()),
+            vec![&votes[0]]
+        );
+
+        // a vote naming a dealer that isn't a participant is rejected outright
+        let vote = procs[2]
+            .consensus
+            .sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(BTreeSet::from([1, 2, 9])),
+                faults: Default::default(),
+            })
+            .unwrap();
+        assert!(matches!(
+            procs[0].handle_message(DkgMessage::Vote(vote)),
+            Err(Error::Dkg(DkgError::UnknownParticipant(9)))
+        ));
+        assert_eq!(procs[0].pending_votes.len(), 1);
+    }
+}
@@ -1,11 +1,16 @@
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
 use rand::{CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
 use signature::{Signer, Verifier};
 
+use crate::voter_keys::{self, VoterKey};
+
 pub type Error = signature::Error;
 
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub struct PublicKey(ed25519::PublicKey);
+pub struct PublicKey(ed25519::VerifyingKey);
 
 impl PublicKey {
     pub fn random(rng: impl Rng + CryptoRng) -> Self {
@@ -30,16 +35,16 @@ impl core::fmt::Display for PublicKey {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SecretKey(ed25519::Keypair);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretKey(ed25519::SigningKey);
 
 impl SecretKey {
     pub fn random(mut rng: impl Rng + CryptoRng) -> Self {
-        Self(ed25519::Keypair::generate(&mut rng))
+        Self(ed25519::SigningKey::generate(&mut rng))
     }
 
     pub fn public_key(&self) -> PublicKey {
-        PublicKey(self.0.public)
+        PublicKey(self.0.verifying_key())
     }
 
     pub fn sign(&self, msg: &[u8]) -> Signature {
@@ -50,6 +55,36 @@ impl SecretKey {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Signature(ed25519::Signature);
 
+/// The elders of a section, each voter is identified by their own ed25519 public key.
+pub type PublicKeySet = voter_keys::PublicKeySet<PublicKey>;
+
+/// An ed25519 secret key for every possible `NodeId`, mirrors blsttc's `SecretKeySet` API.
+pub type SecretKeySet = voter_keys::SecretKeySet<PublicKey>;
+
+/// A super majority of ed25519 signatures, one per voter.
+pub type MultiSignature = voter_keys::MultiSignature<Signature>;
+
+impl VoterKey for PublicKey {
+    type SecretKey = SecretKey;
+    type Signature = Signature;
+
+    fn random_secret_key(rng: impl Rng + CryptoRng) -> SecretKey {
+        SecretKey::random(rng)
+    }
+
+    fn from_secret_key(secret_key: &SecretKey) -> Self {
+        secret_key.public_key()
+    }
+
+    fn sign(secret_key: &SecretKey, msg: &[u8]) -> Signature {
+        secret_key.sign(msg)
+    }
+
+    fn is_valid(&self, msg: &[u8], sig: &Signature) -> bool {
+        self.verify(msg, sig).is_ok()
+    }
+}
+
 impl PartialOrd for PublicKey {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
@@ -62,6 +97,12 @@ impl Ord for PublicKey {
     }
 }
 
+impl Hash for PublicKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bytes().hash(state)
+    }
+}
+
 impl PartialOrd for Signature {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
@@ -73,3 +114,57 @@ impl Ord for Signature {
         self.0.to_bytes().cmp(&other.0.to_bytes())
     }
 }
+
+impl Hash for Signature {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bytes().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ballot, Consensus, Vote, VoteResponse};
+    use rand::{prelude::StdRng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_ed25519_elders_reach_decision() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let secret_keys = BTreeMap::from_iter((1..=4).map(|id| (id, SecretKey::random(&mut rng))));
+        let elders = PublicKeySet::new(
+            2,
+            BTreeMap::from_iter(secret_keys.iter().map(|(id, sk)| (*id, sk.public_key()))),
+        );
+        let mut procs = Vec::from_iter(
+            secret_keys
+                .into_iter()
+                .map(|(id, sk)| Consensus::<u8, _>::from((id, sk), elders.clone(), 4)),
+        );
+
+        let vote = procs[0]
+            .sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Propose(42),
+                faults: Default::default(),
+            })
+            .unwrap();
+        let mut packets = vec![procs[0].cast_vote(vote).unwrap()];
+
+        while let Some(vote) = packets.pop() {
+            for proc in procs.iter_mut() {
+                if let VoteResponse::Broadcast(vote) =
+                    proc.handle_signed_vote(vote.clone()).unwrap()
+                {
+                    packets.push(vote);
+                }
+            }
+        }
+
+        for proc in procs {
+            let decision = proc.decision.unwrap();
+            assert!(decision.validate(&elders).is_ok());
+            assert_eq!(Vec::from_iter(decision.proposals.keys()), vec![&42]);
+        }
+    }
+}
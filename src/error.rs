@@ -11,8 +11,6 @@ pub enum Error {
     Fmt(#[from] std::fmt::Error),
     #[error("The operation requested assumes we have at least one member")]
     NoMembers,
-    #[error("We can not accept any new join requests, network member size is at capacity")]
-    MembersAtCapacity,
     #[error("An existing member can not request to join again")]
     JoinRequestForExistingMember,
     #[error("You must be a member to request to leave")]
@@ -31,30 +29,47 @@ pub enum Error {
     DecisionHasNoVotes,
     #[error("The voter is not an elder")]
     NotElder,
+    #[error("The elders were rotated, we can't vote until we're given our share of their key")]
+    MissingSecretKey,
+    #[error("{n_elders} elders can never form a super majority with a threshold of {threshold}")]
+    InvalidElders { n_elders: usize, threshold: usize },
     #[error("Voter changed their vote")]
     VoterChangedVote,
     #[error("Existing vote not compatible with new vote")]
     ExistingVoteIncompatibleWithNewVote,
-    #[error("The super majority ballot does not actually have supermajority")]
-    SuperMajorityBallotIsNotSuperMajority,
     #[error("Invalid generation {0}")]
     InvalidGeneration(Generation),
+    #[error("Generation {requested_gen} was pruned, history starts at the checkpoint at generation {checkpoint_gen}")]
+    PrunedGeneration {
+        requested_gen: Generation,
+        checkpoint_gen: Generation,
+    },
     #[error("History contains an invalid vote")]
     InvalidVoteInHistory,
     #[error("Invalid decision")]
     InvalidDecision,
+    #[error("Decision certificate is not signed by the section")]
+    InvalidDecisionCertificate,
     #[error("Failed to encode with bincode")]
     Encoding(#[from] bincode::Error),
     #[error("Elder signature is not valid")]
     InvalidElderSignature,
-    #[error("SuperMajority signed a different set of proposals than the proposals in the vote")]
-    SuperMajorityProposalsDoesNotMatchVoteProposals,
+    #[error("Not enough signature shares to form a super majority signature")]
+    NotEnoughSignatureShares,
     #[error("Blsttc Error {0}")]
     Blsttc(#[from] blsttc::error::Error),
     #[error("Client attempted a faulty proposal")]
     AttemptedFaultyProposal,
     #[error("Fault is not a valid fault: {0:?}")]
     FaultIsFaulty(crate::fault::FaultError),
+    #[error("Reconfig violates the membership policy: {0}")]
+    PolicyViolation(crate::policy::PolicyError),
+    #[error("Proposal was rejected by the validator: {0}")]
+    ProposalRejected(Box<dyn std::error::Error + Send + Sync>),
+    #[error("Distributed key generation failed: {0}")]
+    Dkg(crate::dkg::DkgError),
+    #[error("MVBA Error {0}")]
+    Mvba(crate::mvba::error::Error),
 
     #[cfg(feature = "ed25519")]
     #[error("Ed25519 Error {0}")]
@@ -1,8 +1,8 @@
-use blsttc::PublicKeySet;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::{NodeId, Proposition, SignedVote};
+use crate::vote::Ballot;
+use crate::{NodeId, Proposition, PublicKeySet, SignatureScheme, SignedVote, VoteCount};
 
 #[derive(Debug, Error)]
 pub enum FaultError {
@@ -14,23 +14,50 @@ pub enum FaultError {
     AccusedAnImproperlySignedVote,
     #[error("InvalidFaultProof was actually valid")]
     AccusedVoteOfInvalidFaultButAllFaultsAreValid,
+    #[error("The accused vote is not a SuperMajority ballot")]
+    AccusedVoteIsNotASuperMajorityBallot,
+    #[error("BogusSuperMajority fault is actually a super majority")]
+    BogusSuperMajorityIsActuallySuperMajority,
+    #[error("BogusSuperMajority fault is a tie-break cast once the merge bound was reached")]
+    BogusSuperMajorityIsATieBreak,
+    #[error("MismatchedSuperMajorityProposals fault is not a super majority to begin with")]
+    MismatchedSuperMajorityProposalsIsNotSuperMajority,
+    #[error("MismatchedSuperMajorityProposals fault signed the proposals of its votes")]
+    SuperMajorityProposalsActuallyMatch,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub enum Fault<T: Proposition> {
-    ChangedVote { a: SignedVote<T>, b: SignedVote<T> },
-    InvalidFault { signed_vote: SignedVote<T> },
+#[allow(clippy::large_enum_variant)]
+pub enum Fault<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    ChangedVote {
+        a: SignedVote<T, S>,
+        b: SignedVote<T, S>,
+    },
+    InvalidFault {
+        signed_vote: SignedVote<T, S>,
+    },
+    /// A SuperMajority ballot whose votes don't add up to a super majority, nor prove that
+    /// the merge bound was reached for it to break the tie.
+    BogusSuperMajority {
+        signed_vote: SignedVote<T, S>,
+    },
+    /// A SuperMajority ballot signing other proposals than the ones its votes agree on.
+    MismatchedSuperMajorityProposals {
+        signed_vote: SignedVote<T, S>,
+    },
 }
 
-impl<T: Proposition> Fault<T> {
+impl<T: Proposition, S: SignatureScheme> Fault<T, S> {
     pub fn voter_at_fault(&self) -> NodeId {
         match self {
             Fault::ChangedVote { a, .. } => a.voter,
-            Fault::InvalidFault { signed_vote } => signed_vote.voter,
+            Fault::InvalidFault { signed_vote }
+            | Fault::BogusSuperMajority { signed_vote }
+            | Fault::MismatchedSuperMajorityProposals { signed_vote } => signed_vote.voter,
         }
     }
 
-    pub fn validate(&self, voters: &PublicKeySet) -> std::result::Result<(), FaultError> {
+    pub fn validate(&self, voters: &S) -> std::result::Result<(), FaultError> {
         match self {
             Self::ChangedVote { a, b } => {
                 a.validate_signature(voters)
@@ -62,6 +89,45 @@ impl<T: Proposition> Fault<T> {
                     Ok(())
                 }
             }
+            Self::BogusSuperMajority { signed_vote } => {
+                let vote_count = Self::super_majority_count(signed_vote, voters)?;
+                if vote_count.do_we_have_supermajority(voters) {
+                    return Err(FaultError::BogusSuperMajorityIsActuallySuperMajority);
+                }
+                if signed_vote.vote.is_tie_break() {
+                    return Err(FaultError::BogusSuperMajorityIsATieBreak);
+                }
+                Ok(())
+            }
+            Self::MismatchedSuperMajorityProposals { signed_vote } => {
+                let vote_count = Self::super_majority_count(signed_vote, voters)?;
+                if !vote_count.do_we_have_supermajority(voters) && !signed_vote.vote.is_tie_break()
+                {
+                    return Err(FaultError::MismatchedSuperMajorityProposalsIsNotSuperMajority);
+                }
+                let candidate_proposals = signed_vote.candidate().proposals;
+                match &signed_vote.vote.ballot {
+                    Ballot::SuperMajority { proposals, .. }
+                        if !candidate_proposals.iter().eq(proposals.keys()) =>
+                    {
+                        Ok(())
+                    }
+                    _ => Err(FaultError::SuperMajorityProposalsActuallyMatch),
+                }
+            }
         }
     }
+
+    fn super_majority_count(
+        signed_vote: &SignedVote<T, S>,
+        voters: &S,
+    ) -> std::result::Result<VoteCount<T, S>, FaultError> {
+        signed_vote
+            .validate_signature(voters)
+            .map_err(|_| FaultError::AccusedAnImproperlySignedVote)?;
+        signed_vote
+            .vote
+            .super_majority_count()
+            .ok_or(FaultError::AccusedVoteIsNotASuperMajorityBallot)
+    }
 }
@@ -1,51 +1,91 @@
+// Implements `Debug` for a wrapper around a user provided function, the function
+// itself can't be printed.
+macro_rules! impl_fn_debug {
+    ($name:ident) => {
+        impl<F> core::fmt::Debug for $name<F> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str(stringify!($name))
+            }
+        }
+    };
+}
+
 pub mod consensus;
 pub mod decision;
+pub mod dkg;
 pub mod fault;
 pub mod mvba;
+pub mod mvba_adapter;
+pub mod observer;
+pub mod policy;
+pub mod resolver;
+pub mod shared;
+pub mod signature_scheme;
 pub mod sn_handover;
 pub mod sn_membership;
+pub mod store;
+pub mod validator;
 pub mod vote;
 pub mod vote_count;
 
 #[cfg(feature = "bad_crypto")]
 pub mod bad_crypto;
-// #[cfg(feature = "blsttc")]
-// pub mod blsttc;
+mod bls;
 #[cfg(feature = "ed25519")]
 pub mod ed25519;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(any(feature = "bad_crypto", feature = "ed25519"))]
+pub mod voter_keys;
 
-use blsttc::{PublicKeySet, SignatureShare};
 use serde::Serialize;
 
-pub use crate::consensus::{Consensus, VoteResponse};
-pub use crate::decision::Decision;
+pub use crate::consensus::{Consensus, TimeoutResponse, VoteDigest, VoteResponse};
+pub use crate::decision::{Decision, DecisionCertificate};
+pub use crate::dkg::{Dkg, DkgError, DkgMessage, DkgOutcome};
 pub use crate::fault::{Fault, FaultError};
-pub use crate::sn_handover::{Handover, UniqueSectionId};
-pub use crate::sn_membership::{Generation, Membership, Reconfig};
+pub use crate::mvba_adapter::{MvbaAdapter, MvbaApplication, MvbaDecision};
+pub use crate::observer::{ConsensusEvent, ConsensusObserver, MemoryEventLog};
+pub use crate::policy::{CapacityPolicy, MembershipPolicy, PolicyError};
+pub use crate::resolver::{
+    FnResolver, MaxResolver, MinResolver, MostSupportedResolver, Resolver, SeededRandomResolver,
+};
+pub use crate::shared::Shared;
+pub use crate::signature_scheme::{SignatureScheme, UniqueSignature};
+pub use crate::sn_handover::{Handover, HandoverChain, UniqueSectionId};
+pub use crate::sn_membership::{
+    Checkpoint, FaultLedger, FaultsByElder, Generation, Membership, Reconfig,
+};
+pub use crate::store::{
+    ConsensusState, FileStore, MembershipState, MemoryStore, MemoryVoteLog, Persisted, Record,
+    Store, VoteLog,
+};
+pub use crate::validator::{AcceptAll, FnValidator, ProposalValidator};
 pub use crate::vote::{Ballot, Proposition, SignedVote, Vote};
 pub use crate::vote_count::{Candidate, VoteCount};
 
-// #[cfg(feature = "bad_crypto")]
-// pub use crate::bad_crypto::{PublicKey, SecretKey, Signature};
-// #[cfg(feature = "blsttc")]
-// pub use crate::blsttc::{PublicKey, SecretKey, Signature};
-// #[cfg(feature = "ed25519")]
-// pub use crate::ed25519::{PublicKey, SecretKey, Signature};
+// blsttc is the crypto backend used by default throughout the vote pipeline, the
+// `bad_crypto` and `ed25519` features add the backends of the modules of the same name,
+// e.g. `Membership<T, ed25519::PublicKeySet>`.
+pub use blsttc::{PublicKeySet, SecretKeySet};
+
+pub type SecretKeyShare = <PublicKeySet as SignatureScheme>::SecretKeyShare;
+pub type SignatureShare = <PublicKeySet as SignatureScheme>::SignatureShare;
+pub type Signature = <PublicKeySet as SignatureScheme>::Signature;
 
 pub mod error;
 pub use crate::error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
 pub type NodeId = u8;
 
-pub fn verify_sig_share<M: Serialize>(
+pub fn verify_sig_share<M: Serialize, S: SignatureScheme>(
     msg: &M,
-    sig: &SignatureShare,
+    sig: &S::SignatureShare,
     voter: NodeId,
-    voters: &PublicKeySet,
+    voters: &S,
 ) -> Result<()> {
-    let public_key = voters.public_key_share(voter as u64);
     let msg_bytes = bincode::serialize(msg)?;
-    if public_key.verify(sig, msg_bytes) {
+    if voters.verify_share(voter, &msg_bytes, sig) {
         Ok(())
     } else {
         Err(Error::InvalidElderSignature)
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use core::fmt::Debug;
+
+use crate::observer::ConsensusEvent;
+use crate::shared::Shared;
+
+/// A metric label, e.g. `("engine", "mvba")`.
+pub type Label<'a> = (&'static str, &'a str);
+
+/// Receives the metrics of every consensus instance in the process, e.g. to expose
+/// them to Prometheus. Install one with `set_recorder`.
+pub trait Recorder: Debug + Send + Sync {
+    fn increment_counter(&self, name: &'static str, labels: &[Label], value: u64);
+    fn set_gauge(&self, name: &'static str, labels: &[Label], value: f64);
+    fn record_histogram(&self, name: &'static str, labels: &[Label], value: f64);
+}
+
+static RECORDER: RwLock<Option<Arc<dyn Recorder>>> = RwLock::new(None);
+
+/// Installs the recorder metrics are sent to from now on, replacing any previous one.
+pub fn set_recorder(recorder: impl Recorder + 'static) {
+    *RECORDER.write().unwrap() = Some(Arc::new(recorder));
+}
+
+/// Stops recording metrics.
+pub fn clear_recorder() {
+    *RECORDER.write().unwrap() = None;
+}
+
+fn with_recorder(f: impl FnOnce(&dyn Recorder)) {
+    if let Some(recorder) = RECORDER.read().unwrap().as_deref() {
+        f(recorder)
+    }
+}
+
+pub(crate) fn increment_counter(name: &'static str, labels: &[Label], value: u64) {
+    with_recorder(|r| r.increment_counter(name, labels, value))
+}
+
+pub(crate) fn set_gauge(name: &'static str, labels: &[Label], value: f64) {
+    with_recorder(|r| r.set_gauge(name, labels, value))
+}
+
+pub(crate) fn record_histogram(name: &'static str, labels: &[Label], value: f64) {
+    with_recorder(|r| r.record_histogram(name, labels, value))
+}
+
+/// Counts the state transitions reported to observers, `engine` tells the vote based
+/// engine apart from MVBA.
+pub(crate) fn record_event<T>(engine: &'static str, event: &ConsensusEvent<T>) {
+    let name = match event {
+        ConsensusEvent::VoteReceived { .. } => "sn_consensus_votes_received_total",
+        ConsensusEvent::FaultDetected { .. } => "sn_consensus_faults_detected_total",
+        ConsensusEvent::SplitVote { .. } => "sn_consensus_split_votes_total",
+        ConsensusEvent::MergeCast { .. } => "sn_consensus_merges_cast_total",
+        ConsensusEvent::SuperMajorityCast { .. } => "sn_consensus_super_majorities_cast_total",
+        ConsensusEvent::Decided { .. } => "sn_consensus_decisions_total",
+        ConsensusEvent::GenerationAdvanced { .. } => "sn_consensus_generations_advanced_total",
+    };
+    increment_counter(name, &[("engine", engine)], 1);
+}
+
+type Key = (&'static str, Vec<(&'static str, String)>);
+
+fn key(name: &'static str, labels: &[Label]) -> Key {
+    let labels = labels.iter().map(|(k, v)| (*k, v.to_string())).collect();
+    (name, labels)
+}
+
+/// The metrics kept by a `MemoryRecorder`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: BTreeMap<Key, u64>,
+    gauges: BTreeMap<Key, f64>,
+    histograms: BTreeMap<Key, Vec<f64>>,
+}
+
+/// A `Recorder` kept in memory.
+pub type MemoryRecorder = Shared<Metrics>;
+
+impl MemoryRecorder {
+    pub fn counter(&self, name: &'static str, labels: &[Label]) -> u64 {
+        let metrics = self.lock();
+        metrics
+            .counters
+            .get(&key(name, labels))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn gauge(&self, name: &'static str, labels: &[Label]) -> Option<f64> {
+        let metrics = self.lock();
+        metrics.gauges.get(&key(name, labels)).copied()
+    }
+
+    /// Every value recorded in the histogram, in the order they were recorded.
+    pub fn histogram(&self, name: &'static str, labels: &[Label]) -> Vec<f64> {
+        let metrics = self.lock();
+        metrics
+            .histograms
+            .get(&key(name, labels))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Recorder for MemoryRecorder {
+    fn increment_counter(&self, name: &'static str, labels: &[Label], value: u64) {
+        let mut metrics = self.lock();
+        *metrics.counters.entry(key(name, labels)).or_default() += value;
+    }
+
+    fn set_gauge(&self, name: &'static str, labels: &[Label], value: f64) {
+        let mut metrics = self.lock();
+        metrics.gauges.insert(key(name, labels), value);
+    }
+
+    fn record_histogram(&self, name: &'static str, labels: &[Label], value: f64) {
+        let mut metrics = self.lock();
+        metrics
+            .histograms
+            .entry(key(name, labels))
+            .or_default()
+            .push(value);
+    }
+}
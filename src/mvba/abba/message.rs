@@ -35,8 +35,9 @@ pub enum PreVoteJustification {
     WithValidity(Hash32, Signature),
     // In Round r > 1, justification is either hard,...
     Hard(Signature),
-    // ... or soft (refer to the spec)
-    Soft(Signature),
+    // ... or soft (refer to the spec). A soft justification comes with the coin of round r − 1
+    // that the value is taken from.
+    Soft(Signature, Signature),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -63,6 +64,12 @@ pub struct MainVoteAction {
     pub sig_share: SignatureShare,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CoinAction {
+    pub round: usize,
+    pub sig_share: SignatureShare,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct DecisionAction {
     pub round: usize,
@@ -74,6 +81,7 @@ pub struct DecisionAction {
 pub enum Action {
     PreVote(PreVoteAction),
     MainVote(MainVoteAction),
+    Coin(CoinAction),
     Decision(DecisionAction),
 }
 
@@ -88,6 +96,7 @@ impl Message {
         match self.action {
             Action::PreVote(_) => "pre-vote",
             Action::MainVote(_) => "main-vote",
+            Action::Coin(_) => "coin",
             Action::Decision(_) => "decision",
         }
     }
@@ -11,9 +11,10 @@ use blsttc::{PublicKeySet, SecretKeyShare, Signature, SignatureShare};
 
 use self::error::{Error, Result};
 use self::message::{
-    Action, DecisionAction, MainVoteAction, MainVoteValue, Message, PreVoteAction,
+    Action, CoinAction, DecisionAction, MainVoteAction, MainVoteValue, Message, PreVoteAction,
     PreVoteJustification, Value,
 };
+use super::fault::{Fault, Signed};
 use super::hash::Hash32;
 use super::tag::Tag;
 use super::NodeId;
@@ -22,6 +23,40 @@ use crate::mvba::broadcaster::Broadcaster;
 
 pub(crate) const MODULE_NAME: &str = "abba";
 
+// Coin shares are signed without any justification, we only keep the shares of the
+// rounds at most this far ahead of ours so a Byzantine party can't make us store
+// shares for every possible round.
+pub(crate) const MAX_COIN_ROUNDS_AHEAD: usize = 8;
+
+// pre_vote_bytes_to_sign generates bytes for Pre-Vote signature share.
+// pre_vote_bytes_to_sign is same as serialized of $(ID, pre-vote, r, b)$ in spec.
+pub fn pre_vote_bytes_to_sign(
+    tag: &Tag,
+    round: usize,
+    v: &Value,
+) -> std::result::Result<Vec<u8>, bincode::Error> {
+    bincode::serialize(&(tag, "pre-vote", round, v))
+}
+
+// main_vote_bytes_to_sign generates bytes for Main-Vote signature share.
+// main_vote_bytes_to_sign is same as serialized of $(ID, main-vote, r, v)$ in spec.
+pub fn main_vote_bytes_to_sign(
+    tag: &Tag,
+    round: usize,
+    v: &MainVoteValue,
+) -> std::result::Result<Vec<u8>, bincode::Error> {
+    bincode::serialize(&(tag, "main-vote", round, v))
+}
+
+// coin_value takes the value of a coin from the parity of its signature.
+fn coin_value(coin: &Signature) -> Value {
+    if coin.parity() {
+        Value::One
+    } else {
+        Value::Zero
+    }
+}
+
 /// The ABBA holds the information for Asynchronous Binary Byzantine Agreement protocol.
 pub(crate) struct Abba {
     tag: Tag,  // this is same as ID.j.s in the spec
@@ -35,6 +70,8 @@ pub(crate) struct Abba {
     broadcaster: Rc<RefCell<Broadcaster>>,
     round_pre_votes: Vec<HashMap<NodeId, PreVoteAction>>,
     round_main_votes: Vec<HashMap<NodeId, MainVoteAction>>,
+    coin_shares: HashMap<usize, HashMap<NodeId, SignatureShare>>,
+    faults: Vec<Fault>,
 }
 
 impl Abba {
@@ -57,6 +94,8 @@ impl Abba {
             broadcaster,
             round_pre_votes: Vec::new(),
             round_main_votes: Vec::new(),
+            coin_shares: HashMap::new(),
+            faults: Vec::new(),
         }
     }
 
@@ -140,142 +179,13 @@ impl Abba {
 
                 // if r > 1, ...
                 if self.r > 1 {
-                    // select n − t properly justified main-votes from round r − 1
-                    let main_votes = match self.get_main_votes_by_round(self.r - 1) {
-                        Some(v) => v,
-                        None => {
-                            log::debug!(
-                                "party {} has no main-votes for this round: {}",
-                                self.i,
-                                self.r
-                            );
-                            return Ok(());
-                        }
-                    };
-                    let mut zero_votes = main_votes
-                        .iter()
-                        .filter(|(_, a)| a.value == MainVoteValue::zero());
-                    let mut one_votes = main_votes
-                        .iter()
-                        .filter(|(_, a)| a.value == MainVoteValue::one());
-                    let abstain_votes = main_votes
-                        .iter()
-                        .filter(|(_, a)| a.value == MainVoteValue::Abstain);
-
-                    // 3. CHECK FOR DECISION. Collect n −t valid and properly justified main-votes of round r .
-                    if main_votes.len() >= self.threshold() {
-                        // If these are all main-votes for b ∈ {0, 1}, then decide the value b for ID
-                        if zero_votes.clone().count() >= self.threshold() {
-                            log::info!(
-                                "party {} decided for zero. tag={}, r={}",
-                                self.i,
-                                self.tag,
-                                self.r
-                            );
-                            let sig_share: HashMap<&NodeId, &SignatureShare> =
-                                zero_votes.map(|(n, a)| (n, &a.sig_share)).collect();
-                            let sig = self.pub_key_set.combine_signatures(sig_share)?;
-                            let decision = DecisionAction {
-                                round: action.round,
-                                value: Value::Zero,
-                                sig,
-                            };
-                            self.decided_value = Some(decision.clone());
-                            self.broadcast(Action::Decision(decision))?;
-                            return Ok(());
-                        }
-
-                        if one_votes.clone().count() >= self.threshold() {
-                            log::info!(
-                                "party {} decided for one. tag={}, r={}",
-                                self.i,
-                                self.tag,
-                                self.r
-                            );
-                            let sig_share: HashMap<&NodeId, &SignatureShare> =
-                                one_votes.map(|(n, a)| (n, &a.sig_share)).collect();
-                            let sig = self.pub_key_set.combine_signatures(sig_share)?;
-                            let decision = DecisionAction {
-                                round: action.round,
-                                value: Value::One,
-                                sig,
-                            };
-                            self.decided_value = Some(decision.clone());
-                            self.broadcast(Action::Decision(decision))?;
-                            return Ok(());
-                        }
-
-                        let (value, justification) = if let Some((digest, sig)) =
-                            &self.weak_validity
-                        {
-                            if let Some(v) = self.get_pre_votes_by_round(self.r) {
-                                if v.contains_key(&self.i) {
-                                    log::debug!("party {} obtained the corresponding validating data after voting for zero", self.i);
-                                    return Ok(());
-                                }
-                            };
-
-                            // if all honest parties start with 0, they may still
-                            // decide on 1 if they obtain the corresponding validating data
-                            //  for 1 during the agreement protocol
-
-                            (
-                                Value::One,
-                                PreVoteJustification::WithValidity(*digest, sig.clone()),
-                            )
-                        } else if let Some((_, zero_vote)) = zero_votes.next() {
-                            // if there is a main-vote for 0,
-                            let sig = match &zero_vote.justification {
-                                MainVoteJustification::NoAbstain(sig) => sig,
-                                _ => {
-                                    return Err(Error::Generic(
-                                        "protocol violated, invalid main-vote justification"
-                                            .to_string(),
-                                    ))
-                                }
-                            };
-                            // hard pre-vote for 0
-                            (Value::Zero, PreVoteJustification::Hard(sig.clone()))
-                        } else if let Some((_, one_vote)) = one_votes.next() {
-                            // if there is a main-vote for 1,
-                            let sig = match &one_vote.justification {
-                                MainVoteJustification::NoAbstain(sig) => sig,
-                                _ => {
-                                    return Err(Error::Generic(
-                                        "protocol violated, invalid main-vote justification"
-                                            .to_string(),
-                                    ))
-                                }
-                            };
-                            // hard pre-vote for 1
-                            (Value::One, PreVoteJustification::Hard(sig.clone()))
-                        } else if abstain_votes.clone().count() == main_votes.len() {
-                            // if all main-votes are abstain,
-                            let sig_share: HashMap<&NodeId, &SignatureShare> =
-                                abstain_votes.map(|(n, a)| (n, &a.sig_share)).collect();
-                            let sig = self.pub_key_set.combine_signatures(sig_share)?;
-                            // soft pre-vote for 1
-                            // Coin value bias to 1 in weaker validity mode.
-                            (Value::One, PreVoteJustification::Soft(sig))
-                        } else {
-                            return Err(Error::Generic(
-                                "protocol violated, no pre-vote majority".to_string(),
-                            ));
-                        };
-
-                        // Produce an S-signature share on the message `(ID, pre-vote, r, b)`
-                        let sign_bytes = self.pre_vote_bytes_to_sign(self.r, &value)?;
-                        let sig_share = self.sec_key_share.sign(sign_bytes);
-
-                        // Send to all parties the message `(ID, pre-vote, r, b, justification, signature share)`
-                        let action = Action::PreVote(PreVoteAction {
-                            round: self.r,
-                            value,
-                            justification,
-                            sig_share,
-                        });
-                        self.broadcast(action)?;
-                    }
+                    self.pre_vote_next_round()?;
+                }
+            }
+            Action::Coin(action) => {
+                // The coin of round r − 1 might be all we were waiting for to pre-vote in round r
+                if self.r > 1 && action.round + 1 == self.r {
+                    self.pre_vote_next_round()?;
                 }
             }
 
@@ -355,6 +265,9 @@ impl Abba {
                     });
                     self.broadcast(action)?;
                     self.r += 1;
+
+                    // The main-votes we already have might be all we need to move on
+                    self.pre_vote_next_round()?;
                 }
             }
         }
@@ -362,6 +275,177 @@ impl Abba {
         Ok(())
     }
 
+    // pre_vote_next_round runs the pre-vote step of round r > 1
+    // once we have the main-votes of round r − 1.
+    fn pre_vote_next_round(&mut self) -> Result<()> {
+        // select n − t properly justified main-votes from round r − 1
+        let main_votes = match self.get_main_votes_by_round(self.r - 1) {
+            Some(v) => v,
+            None => {
+                log::debug!(
+                    "party {} has no main-votes for this round: {}",
+                    self.i,
+                    self.r
+                );
+                return Ok(());
+            }
+        };
+        if main_votes.len() < self.threshold() {
+            return Ok(());
+        }
+
+        let mut zero_votes = main_votes
+            .iter()
+            .filter(|(_, a)| a.value == MainVoteValue::zero());
+        let mut one_votes = main_votes
+            .iter()
+            .filter(|(_, a)| a.value == MainVoteValue::one());
+        let abstain_votes = main_votes
+            .iter()
+            .filter(|(_, a)| a.value == MainVoteValue::Abstain);
+
+        // 3. CHECK FOR DECISION. Collect n −t valid and properly justified main-votes of round r .
+        // If these are all main-votes for b ∈ {0, 1}, then decide the value b for ID
+        if zero_votes.clone().count() >= self.threshold() {
+            log::info!(
+                "party {} decided for zero. tag={}, r={}",
+                self.i,
+                self.tag,
+                self.r
+            );
+            let sig_share: HashMap<&NodeId, &SignatureShare> =
+                zero_votes.map(|(n, a)| (n, &a.sig_share)).collect();
+            let sig = self.pub_key_set.combine_signatures(sig_share)?;
+            let decision = DecisionAction {
+                round: self.r - 1,
+                value: Value::Zero,
+                sig,
+            };
+            self.decided_value = Some(decision.clone());
+            return self.broadcast(Action::Decision(decision));
+        }
+
+        if one_votes.clone().count() >= self.threshold() {
+            log::info!(
+                "party {} decided for one. tag={}, r={}",
+                self.i,
+                self.tag,
+                self.r
+            );
+            let sig_share: HashMap<&NodeId, &SignatureShare> =
+                one_votes.map(|(n, a)| (n, &a.sig_share)).collect();
+            let sig = self.pub_key_set.combine_signatures(sig_share)?;
+            let decision = DecisionAction {
+                round: self.r - 1,
+                value: Value::One,
+                sig,
+            };
+            self.decided_value = Some(decision.clone());
+            return self.broadcast(Action::Decision(decision));
+        }
+
+        // Release our share of the coin for round r − 1, the coin can't be predicted
+        // before n − t parties have main-voted in that round.
+        // Receiving our own share brings us back here.
+        let coin_shares = self.coin_shares.get(&(self.r - 1));
+        if !coin_shares.is_some_and(|shares| shares.contains_key(&self.i)) {
+            let sign_bytes = self.coin_bytes_to_sign(self.r - 1)?;
+            let sig_share = self.sec_key_share.sign(sign_bytes);
+            return self.broadcast(Action::Coin(CoinAction {
+                round: self.r - 1,
+                sig_share,
+            }));
+        }
+
+        if let Some(v) = self.get_pre_votes_by_round(self.r) {
+            if v.contains_key(&self.i) {
+                log::trace!("party {} pre-voted before in round {}", self.i, self.r);
+                return Ok(());
+            }
+        }
+
+        let (value, justification) = if let Some((digest, sig)) = &self.weak_validity {
+            // if all honest parties start with 0, they may still
+            // decide on 1 if they obtain the corresponding validating data
+            //  for 1 during the agreement protocol
+
+            (
+                Value::One,
+                PreVoteJustification::WithValidity(*digest, sig.clone()),
+            )
+        } else if let Some((_, zero_vote)) = zero_votes.next() {
+            // if there is a main-vote for 0,
+            let sig = match &zero_vote.justification {
+                MainVoteJustification::NoAbstain(sig) => sig,
+                _ => {
+                    return Err(Error::Generic(
+                        "protocol violated, invalid main-vote justification".to_string(),
+                    ))
+                }
+            };
+            // hard pre-vote for 0
+            (Value::Zero, PreVoteJustification::Hard(sig.clone()))
+        } else if let Some((_, one_vote)) = one_votes.next() {
+            // if there is a main-vote for 1,
+            let sig = match &one_vote.justification {
+                MainVoteJustification::NoAbstain(sig) => sig,
+                _ => {
+                    return Err(Error::Generic(
+                        "protocol violated, invalid main-vote justification".to_string(),
+                    ))
+                }
+            };
+            // hard pre-vote for 1
+            (Value::One, PreVoteJustification::Hard(sig.clone()))
+        } else if abstain_votes.clone().count() == main_votes.len() {
+            // if all main-votes are abstain,
+            let coin = match self.coin(self.r - 1)? {
+                Some(coin) => coin,
+                None => {
+                    log::debug!(
+                        "party {} is waiting for the coin of round {}",
+                        self.i,
+                        self.r - 1
+                    );
+                    return Ok(());
+                }
+            };
+            let sig_share: HashMap<&NodeId, &SignatureShare> =
+                abstain_votes.map(|(n, a)| (n, &a.sig_share)).collect();
+            let sig = self.pub_key_set.combine_signatures(sig_share)?;
+            // soft pre-vote for the value of the coin
+            (coin_value(&coin), PreVoteJustification::Soft(sig, coin))
+        } else {
+            return Err(Error::Generic(
+                "protocol violated, no pre-vote majority".to_string(),
+            ));
+        };
+
+        // Produce an S-signature share on the message `(ID, pre-vote, r, b)`
+        let sign_bytes = self.pre_vote_bytes_to_sign(self.r, &value)?;
+        let sig_share = self.sec_key_share.sign(sign_bytes);
+
+        // Send to all parties the message `(ID, pre-vote, r, b, justification, signature share)`
+        let action = Action::PreVote(PreVoteAction {
+            round: self.r,
+            value,
+            justification,
+            sig_share,
+        });
+        self.broadcast(action)
+    }
+
+    // coin returns the common coin of the `round` once we have enough shares to combine.
+    // The coin is a signature so anyone can check the value we took from it.
+    fn coin(&self, round: usize) -> Result<Option<Signature>> {
+        match self.coin_shares.get(&round) {
+            Some(shares) if shares.len() >= self.threshold() => {
+                Ok(Some(self.pub_key_set.combine_signatures(shares.iter())?))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub fn decided_value(&self) -> Option<bool> {
         match &self.decided_value {
             Some(v) => match v.value {
@@ -372,12 +456,45 @@ impl Abba {
         }
     }
 
+    /// The decision along with the combined main-vote signature proving it.
+    pub fn decision(&self) -> Option<&DecisionAction> {
+        self.decided_value.as_ref()
+    }
+
+    /// The faults we have proof of.
+    pub fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
+    fn record_fault(&mut self, fault: Fault) {
+        log::warn!("party {} detected a fault: {fault:?}", self.i);
+        if !self.faults.contains(&fault) {
+            self.faults.push(fault);
+        }
+    }
+
     fn add_message(&mut self, initiator: &NodeId, msg: &Message) -> Result<bool> {
         match &msg.action {
             Action::PreVote(action) => {
                 let pre_votes = self.get_mut_pre_votes_by_round(action.round)?;
-                if let Some(exist) = pre_votes.get(initiator) {
-                    if exist != action {
+                if let Some(exist) = pre_votes.get(initiator).cloned() {
+                    if &exist != action {
+                        if exist.value != action.value {
+                            let fault = Fault::DoublePreVote {
+                                party: *initiator,
+                                tag: self.tag.clone(),
+                                round: action.round,
+                                a: Signed {
+                                    value: exist.value,
+                                    sig_share: exist.sig_share.clone(),
+                                },
+                                b: Signed {
+                                    value: action.value,
+                                    sig_share: action.sig_share.clone(),
+                                },
+                            };
+                            self.record_fault(fault);
+                        }
                         return Err(Error::InvalidMessage(format!(
                             "double pre-vote detected from {initiator:?}"
                         )));
@@ -389,8 +506,24 @@ impl Abba {
             }
             Action::MainVote(action) => {
                 let main_votes = self.get_mut_main_votes_by_round(action.round)?;
-                if let Some(exist) = main_votes.get(initiator) {
-                    if exist != action {
+                if let Some(exist) = main_votes.get(initiator).cloned() {
+                    if &exist != action {
+                        if exist.value != action.value {
+                            let fault = Fault::DoubleMainVote {
+                                party: *initiator,
+                                tag: self.tag.clone(),
+                                round: action.round,
+                                a: Signed {
+                                    value: exist.value,
+                                    sig_share: exist.sig_share.clone(),
+                                },
+                                b: Signed {
+                                    value: action.value,
+                                    sig_share: action.sig_share.clone(),
+                                },
+                            };
+                            self.record_fault(fault);
+                        }
                         return Err(Error::InvalidMessage(format!(
                             "double main-vote detected from {initiator:?}"
                         )));
@@ -400,6 +533,27 @@ impl Abba {
 
                 main_votes.insert(*initiator, action.clone());
             }
+            Action::Coin(action) => {
+                if action.round > self.r + MAX_COIN_ROUNDS_AHEAD {
+                    log::debug!(
+                        "party {} ignores the coin share of round {} from {initiator:?}",
+                        self.i,
+                        action.round
+                    );
+                    return Ok(false);
+                }
+                let coin_shares = self.coin_shares.entry(action.round).or_default();
+                if let Some(exist) = coin_shares.get(initiator) {
+                    if exist != &action.sig_share {
+                        return Err(Error::InvalidMessage(format!(
+                            "double coin share detected from {initiator:?}"
+                        )));
+                    }
+                    return Ok(false);
+                }
+
+                coin_shares.insert(*initiator, action.sig_share.clone());
+            }
             Action::Decision(_action) => (),
         }
         Ok(true)
@@ -425,60 +579,11 @@ impl Abba {
                     return Err(Error::InvalidMessage("invalid signature share".to_string()));
                 }
 
-                match &action.justification {
-                    PreVoteJustification::FirstRoundZero => {
-                        if action.round != 1 {
-                            return Err(Error::InvalidMessage(format!(
-                                "invalid round. expected 1, got {}",
-                                action.round
-                            )));
-                        }
-
-                        if action.value != Value::Zero {
-                            return Err(Error::InvalidMessage(
-                                "initial value should be zero".to_string(),
-                            ));
-                        }
-                    }
-                    PreVoteJustification::WithValidity(digest, sig) => {
-                        let sign_bytes =
-                            crate::mvba::vcbc::c_ready_bytes_to_sign(&self.tag, digest)?;
-
-                        if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
-                            return Err(Error::InvalidMessage(
-                                "invalid signature for the VCBC proposal".to_string(),
-                            ));
-                        }
-
-                        // A weaker validity: an honest party may only decide on a value
-                        // for which it has the accompanying validating data.
-                        if action.value != Value::One {
-                            return Err(Error::InvalidMessage(
-                                "initial value should be one".to_string(),
-                            ));
-                        }
-                    }
-                    PreVoteJustification::Hard(sig) => {
-                        // Hard pre-vote justification is the S-threshold signature for `(ID, pre-vote, r − 1, b)`
-                        let sign_bytes =
-                            self.pre_vote_bytes_to_sign(action.round - 1, &action.value)?;
-                        if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
-                            return Err(Error::InvalidMessage(
-                                "invalid hard-vote justification".to_string(),
-                            ));
-                        }
-                    }
-                    PreVoteJustification::Soft(sig) => {
-                        // Soft pre-vote justification is the S-threshold signature for `(ID, main-vote, r − 1, abstain)`
-                        let sign_bytes = self
-                            .main_vote_bytes_to_sign(action.round - 1, &MainVoteValue::Abstain)?;
-                        if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
-                            return Err(Error::InvalidMessage(
-                                "invalid soft-vote justification".to_string(),
-                            ));
-                        }
-                    }
-                }
+                self.check_pre_vote_justification(
+                    action.round,
+                    &action.value,
+                    &action.justification,
+                )?;
             }
             Action::MainVote(action) => {
                 // check the validity of the S-signature share
@@ -517,44 +622,28 @@ impl Abba {
                                 action.value
                             )));
                         }
-                        match just_0.as_ref() {
-                            PreVoteJustification::FirstRoundZero => {}
-                            PreVoteJustification::Hard(sig) => {
-                                let sign_bytes =
-                                    self.pre_vote_bytes_to_sign(action.round - 1, &Value::Zero)?;
-                                if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
-                                    return Err(Error::InvalidMessage(
-                                        "invalid abstain justification for value zero".to_string(),
-                                    ));
-                                }
-                            }
-                            _ => {
-                                return Err(Error::InvalidMessage(format!(
-                                    "invalid justification for value 0 in round 1: {just_0:?}"
-                                )));
-                            }
-                        }
-
-                        match just_1.as_ref() {
-                            PreVoteJustification::WithValidity(digest, sig) => {
-                                let sign_bytes =
-                                    crate::mvba::vcbc::c_ready_bytes_to_sign(&self.tag, digest)?;
-
-                                if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
-                                    return Err(Error::InvalidMessage(
-                                        "invalid signature for the VCBC proposal".to_string(),
-                                    ));
-                                }
-                            }
-                            _ => {
-                                return Err(Error::InvalidMessage(format!(
-                                    "invalid justification for value 1 in round 1: {just_1:?}"
-                                )));
-                            }
-                        }
+                        // the justifications of the two conflicting pre-votes in this round
+                        self.check_pre_vote_justification(action.round, &Value::Zero, just_0)?;
+                        self.check_pre_vote_justification(action.round, &Value::One, just_1)?;
                     }
                 }
             }
+            Action::Coin(action) => {
+                if action.round == 0 {
+                    return Err(Error::InvalidMessage(
+                        "invalid round. rounds start from 1".to_string(),
+                    ));
+                }
+                // check the validity of the coin signature share on message (ID, coin, r)
+                let sign_bytes = self.coin_bytes_to_sign(action.round)?;
+                if !self
+                    .pub_key_set
+                    .public_key_share(initiator)
+                    .verify(&action.sig_share, sign_bytes)
+                {
+                    return Err(Error::InvalidMessage("invalid signature share".to_string()));
+                }
+            }
             Action::Decision(action) => {
                 // check the validity of the S-signature share
                 let sign_bytes = self
@@ -572,6 +661,78 @@ impl Abba {
         Ok(())
     }
 
+    fn check_pre_vote_justification(
+        &self,
+        round: usize,
+        value: &Value,
+        justification: &PreVoteJustification,
+    ) -> Result<()> {
+        match justification {
+            PreVoteJustification::FirstRoundZero => {
+                if round != 1 {
+                    return Err(Error::InvalidMessage(format!(
+                        "invalid round. expected 1, got {round}"
+                    )));
+                }
+
+                if value != &Value::Zero {
+                    return Err(Error::InvalidMessage(
+                        "initial value should be zero".to_string(),
+                    ));
+                }
+            }
+            PreVoteJustification::WithValidity(digest, sig) => {
+                let sign_bytes = crate::mvba::vcbc::c_ready_bytes_to_sign(&self.tag, digest)?;
+
+                if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
+                    return Err(Error::InvalidMessage(
+                        "invalid signature for the VCBC proposal".to_string(),
+                    ));
+                }
+
+                // A weaker validity: an honest party may only decide on a value
+                // for which it has the accompanying validating data.
+                if value != &Value::One {
+                    return Err(Error::InvalidMessage(
+                        "initial value should be one".to_string(),
+                    ));
+                }
+            }
+            PreVoteJustification::Hard(sig) => {
+                // Hard pre-vote justification is the S-threshold signature for `(ID, pre-vote, r − 1, b)`
+                let sign_bytes = self.pre_vote_bytes_to_sign(round - 1, value)?;
+                if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
+                    return Err(Error::InvalidMessage(
+                        "invalid hard-vote justification".to_string(),
+                    ));
+                }
+            }
+            PreVoteJustification::Soft(sig, coin) => {
+                // Soft pre-vote justification is the S-threshold signature for `(ID, main-vote, r − 1, abstain)`
+                let sign_bytes =
+                    self.main_vote_bytes_to_sign(round - 1, &MainVoteValue::Abstain)?;
+                if !self.pub_key_set.public_key().verify(sig, sign_bytes) {
+                    return Err(Error::InvalidMessage(
+                        "invalid soft-vote justification".to_string(),
+                    ));
+                }
+
+                // ... and the value is taken from the coin of round r − 1
+                let sign_bytes = self.coin_bytes_to_sign(round - 1)?;
+                if !self.pub_key_set.public_key().verify(coin, sign_bytes) {
+                    return Err(Error::InvalidMessage("invalid coin signature".to_string()));
+                }
+                if &coin_value(coin) != value {
+                    return Err(Error::InvalidMessage(
+                        "soft-vote value does not match the coin".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     // broadcast sends the message `msg` to all other peers in the network.
     // It adds the message to our messages log.
     fn broadcast(&mut self, action: Action) -> Result<()> {
@@ -589,16 +750,18 @@ impl Abba {
         Ok(())
     }
 
-    // pre_vote_bytes_to_sign generates bytes for Pre-Vote signature share.
-    // pre_vote_bytes_to_sign is same as serialized of $(ID, pre-vote, r, b)$ in spec.
     fn pre_vote_bytes_to_sign(&self, round: usize, v: &Value) -> Result<Vec<u8>> {
-        Ok(bincode::serialize(&(&self.tag, "pre-vote", round, v))?)
+        Ok(pre_vote_bytes_to_sign(&self.tag, round, v)?)
     }
 
-    // main_vote_bytes_to_sign generates bytes for Main-Vote signature share.
-    // main_vote_bytes_to_sign is same as serialized of $(ID, main-vote, r, v)$ in spec.
     fn main_vote_bytes_to_sign(&self, round: usize, v: &MainVoteValue) -> Result<Vec<u8>> {
-        Ok(bincode::serialize(&(&self.tag, "main-vote", round, v))?)
+        Ok(main_vote_bytes_to_sign(&self.tag, round, v)?)
+    }
+
+    // coin_bytes_to_sign generates bytes for the coin signature share.
+    // coin_bytes_to_sign is same as serialized of $(ID, coin, r)$.
+    fn coin_bytes_to_sign(&self, round: usize) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(&self.tag, "coin", round))?)
     }
 
     // threshold return the threshold of the public key set.
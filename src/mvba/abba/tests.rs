@@ -2,16 +2,19 @@ use std::rc::Rc;
 use std::{cell::RefCell, collections::BTreeMap};
 
 use blsttc::{SecretKey, SecretKeySet, Signature};
+use quickcheck_macros::quickcheck;
 use rand::thread_rng;
 
 use super::{
+    coin_value,
     error::Error,
     message::{
-        Action, MainVoteAction, MainVoteJustification, MainVoteValue, Message, PreVoteAction,
-        PreVoteJustification, Value,
+        Action, CoinAction, MainVoteAction, MainVoteJustification, MainVoteValue, Message,
+        PreVoteAction, PreVoteJustification, Value,
     },
-    Abba,
+    Abba, MAX_COIN_ROUNDS_AHEAD,
 };
+use crate::mvba::fault::{Fault, Signed};
 use crate::mvba::hash::Hash32;
 use crate::mvba::tag::{Domain, Tag};
 use crate::mvba::{broadcaster::Broadcaster, bundle::Bundle, NodeId};
@@ -101,6 +104,21 @@ impl TestNet {
         }
     }
 
+    pub fn make_coin_msg(&self, round: usize, peer_id: &NodeId) -> Message {
+        let sign_bytes = self.abba.coin_bytes_to_sign(round).unwrap();
+        let sig_share = self.sec_key_set.secret_key_share(peer_id).sign(sign_bytes);
+        Message {
+            tag: self.abba.tag.clone(),
+            action: Action::Coin(CoinAction { round, sig_share }),
+        }
+    }
+
+    // coin is same as the combined coin signature for the `round`
+    pub fn coin(&self, round: usize) -> Signature {
+        let sign_bytes = self.abba.coin_bytes_to_sign(round).unwrap();
+        self.sec_key_set.secret_key().sign(sign_bytes)
+    }
+
     pub fn is_broadcasted(&self, msg: &Message) -> bool {
         self.broadcaster
             .borrow()
@@ -277,10 +295,75 @@ fn test_double_pre_vote() {
         .receive_message(TestNet::PARTY_B, pre_vote_1)
         .unwrap();
 
-    let result = t.abba.receive_message(TestNet::PARTY_B, pre_vote_2);
+    let result = t.abba.receive_message(TestNet::PARTY_B, pre_vote_2.clone());
     assert!(matches!(result, Err(Error::InvalidMessage(msg))
         if msg == format!(
             "double pre-vote detected from {:?}", &TestNet::PARTY_B)));
+
+    let sign_bytes = t.abba.pre_vote_bytes_to_sign(1, &Value::Zero).unwrap();
+    let sig_share = t
+        .sec_key_set
+        .secret_key_share(TestNet::PARTY_B)
+        .sign(sign_bytes);
+    let expected_fault = Fault::DoublePreVote {
+        party: TestNet::PARTY_B,
+        tag: t.abba.tag.clone(),
+        round: 1,
+        a: Signed {
+            value: Value::Zero,
+            sig_share,
+        },
+        b: Signed {
+            value: Value::One,
+            sig_share: match pre_vote_2.action {
+                Action::PreVote(action) => action.sig_share,
+                _ => unreachable!(),
+            },
+        },
+    };
+    assert_eq!(t.abba.faults(), std::slice::from_ref(&expected_fault));
+    assert!(expected_fault
+        .validate(&t.sec_key_set.public_keys())
+        .is_ok());
+}
+
+#[test]
+fn test_double_main_vote() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_X;
+    let mut t = TestNet::new(i, j);
+
+    let sign_bytes = t.abba.pre_vote_bytes_to_sign(1, &Value::Zero).unwrap();
+    let pre_vote_0_sig = t.sec_key_set.secret_key().sign(sign_bytes);
+    let just_0 = MainVoteJustification::NoAbstain(pre_vote_0_sig);
+    let main_vote_1 = t.make_main_vote_msg(1, MainVoteValue::zero(), &just_0, &TestNet::PARTY_B);
+
+    let just_abstain = MainVoteJustification::Abstain(
+        Box::new(PreVoteJustification::FirstRoundZero),
+        Box::new(PreVoteJustification::WithValidity(
+            t.proposal_digest,
+            t.proposal_sig.clone(),
+        )),
+    );
+    let main_vote_2 =
+        t.make_main_vote_msg(1, MainVoteValue::Abstain, &just_abstain, &TestNet::PARTY_B);
+
+    t.abba
+        .receive_message(TestNet::PARTY_B, main_vote_1)
+        .unwrap();
+    let result = t.abba.receive_message(TestNet::PARTY_B, main_vote_2);
+    assert!(matches!(result, Err(Error::InvalidMessage(msg))
+        if msg == format!(
+            "double main-vote detected from {:?}", &TestNet::PARTY_B)));
+
+    let faults = t.abba.faults();
+    assert_eq!(faults.len(), 1);
+    assert_eq!(faults[0].party_at_fault(), TestNet::PARTY_B);
+    assert!(faults[0].validate(&t.sec_key_set.public_keys()).is_ok());
+
+    // Faults are only checked against the keys of the parties
+    let other_keys = SecretKeySet::random(2, &mut thread_rng()).public_keys();
+    assert!(faults[0].validate(&other_keys).is_err());
 }
 
 #[test]
@@ -857,6 +940,116 @@ fn test_three_rounds() {
     assert_eq!(t.abba.decided_value.unwrap().value, Value::One);
 }
 
+#[test]
+fn test_soft_pre_vote_follows_the_coin() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_B;
+    let mut t = TestNet::new(i, j);
+
+    let sign_bytes = t
+        .abba
+        .main_vote_bytes_to_sign(1, &MainVoteValue::Abstain)
+        .unwrap();
+    let abstain_sig = t.sec_key_set.secret_key().sign(sign_bytes);
+    let coin = t.coin(1);
+    let value = coin_value(&coin);
+    let other_value = match value {
+        Value::One => Value::Zero,
+        Value::Zero => Value::One,
+    };
+
+    let just = PreVoteJustification::Soft(abstain_sig.clone(), coin);
+    let pre_vote_b = t.make_pre_vote_msg(2, value, &just, &TestNet::PARTY_B);
+    t.abba
+        .receive_message(TestNet::PARTY_B, pre_vote_b)
+        .unwrap();
+
+    let pre_vote_y = t.make_pre_vote_msg(2, other_value, &just, &TestNet::PARTY_Y);
+    let result = t.abba.receive_message(TestNet::PARTY_Y, pre_vote_y);
+    assert!(matches!(result, Err(Error::InvalidMessage(msg))
+        if msg == "soft-vote value does not match the coin"));
+
+    let sign_bytes = t.abba.coin_bytes_to_sign(1).unwrap();
+    let invalid_coin = SecretKey::random().sign(sign_bytes);
+    let just = PreVoteJustification::Soft(abstain_sig, invalid_coin);
+    let pre_vote_s = t.make_pre_vote_msg(2, value, &just, &TestNet::PARTY_S);
+    let result = t.abba.receive_message(TestNet::PARTY_S, pre_vote_s);
+    assert!(matches!(result, Err(Error::InvalidMessage(msg))
+        if msg == "invalid coin signature"));
+}
+
+#[test]
+fn test_all_abstain_main_votes_wait_for_the_coin() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_B;
+    let mut t = TestNet::new(i, j);
+
+    // We have main-voted abstain in round 1 without having the validating data ourselves.
+    t.abba.r = 2;
+
+    let just = MainVoteJustification::Abstain(
+        Box::new(PreVoteJustification::FirstRoundZero),
+        Box::new(PreVoteJustification::WithValidity(
+            t.proposal_digest,
+            t.proposal_sig.clone(),
+        )),
+    );
+    for peer in [TestNet::PARTY_X, TestNet::PARTY_Y, TestNet::PARTY_S] {
+        let main_vote = t.make_main_vote_msg(1, MainVoteValue::Abstain, &just, &peer);
+        t.abba.receive_message(peer, main_vote).unwrap();
+    }
+
+    // All main-votes are abstain, we release our share of the coin and wait for the others
+    assert!(t.is_broadcasted(&t.make_coin_msg(1, &TestNet::PARTY_X)));
+    assert!(t.abba.get_pre_votes_by_round(2).is_none());
+
+    for peer in [TestNet::PARTY_Y, TestNet::PARTY_S] {
+        let coin_msg = t.make_coin_msg(1, &peer);
+        t.abba.receive_message(peer, coin_msg).unwrap();
+    }
+
+    let sign_bytes = t
+        .abba
+        .main_vote_bytes_to_sign(1, &MainVoteValue::Abstain)
+        .unwrap();
+    let abstain_sig = t.sec_key_set.secret_key().sign(sign_bytes);
+    let coin = t.coin(1);
+    let round_2_pre_vote_x = t.make_pre_vote_msg(
+        2,
+        coin_value(&coin),
+        &PreVoteJustification::Soft(abstain_sig, coin),
+        &TestNet::PARTY_X,
+    );
+    assert!(t.is_broadcasted(&round_2_pre_vote_x));
+}
+
+#[test]
+fn test_coin_share_of_round_zero_is_rejected() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_B;
+    let mut t = TestNet::new(i, j);
+
+    let coin_msg = t.make_coin_msg(0, &TestNet::PARTY_B);
+    let result = t.abba.receive_message(TestNet::PARTY_B, coin_msg);
+    assert!(matches!(result, Err(Error::InvalidMessage(msg))
+        if msg == "invalid round. rounds start from 1"));
+    assert!(t.abba.coin_shares.is_empty());
+}
+
+#[test]
+fn test_coin_shares_far_ahead_are_ignored() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_B;
+    let mut t = TestNet::new(i, j);
+
+    let last_round = t.abba.r + MAX_COIN_ROUNDS_AHEAD;
+    for round in [last_round, last_round + 1, usize::MAX] {
+        let coin_msg = t.make_coin_msg(round, &TestNet::PARTY_B);
+        t.abba.receive_message(TestNet::PARTY_B, coin_msg).unwrap();
+    }
+    assert_eq!(Vec::from_iter(t.abba.coin_shares.keys()), vec![&last_round]);
+}
+
 struct Net {
     tag: Tag,
     secret_key_set: SecretKeySet,
@@ -877,7 +1070,7 @@ impl Net {
         let public_key_set = secret_key_set.public_keys();
         let tag = Tag::new(Domain::new("test-domain", 0), proposer);
 
-        let nodes = BTreeMap::from_iter((1..=n).into_iter().map(|node_id| {
+        let nodes = BTreeMap::from_iter((1..=n).map(|node_id| {
             let key_share = secret_key_set.secret_key_share(node_id);
             let broadcaster = Rc::new(RefCell::new(Broadcaster::new(node_id)));
             let vcbc = Abba::new(
@@ -944,7 +1137,6 @@ impl Net {
         }
     }
 
-    #[allow(unused)]
     fn deliver(&mut self, recipient: NodeId, index: usize) {
         if let Some(msgs) = self.queue.get_mut(&recipient) {
             if msgs.is_empty() {
@@ -991,3 +1183,41 @@ fn test_net_happy_path() {
         assert_eq!(node.decided_value.unwrap().value, Value::One);
     }
 }
+
+#[quickcheck]
+fn prop_net_terminates_under_randomized_msg_delivery(
+    n: usize,
+    votes: Vec<bool>,
+    msg_order: Vec<(NodeId, usize)>,
+) {
+    let n = n % 7 + 1;
+    let proposer = 1;
+    let mut net = Net::new(n, proposer);
+
+    let proposal_digest = Hash32::calculate("test-data".as_bytes());
+    let sign_bytes = crate::mvba::vcbc::c_ready_bytes_to_sign(&net.tag, &proposal_digest).unwrap();
+    let proposal_sig = net.secret_key_set.secret_key().sign(sign_bytes);
+
+    // Some nodes have the proposal and pre-vote one, the others pre-vote zero
+    for id in Vec::from_iter(net.nodes.keys().copied()) {
+        let node = net.node_mut(id);
+        if votes.get(id - 1).copied().unwrap_or_default() {
+            node.pre_vote_one(proposal_digest, proposal_sig.clone())
+                .expect("Failed to pre-vote");
+        } else {
+            node.pre_vote_zero().expect("Failed to pre-vote");
+        }
+
+        net.enqueue_bundles_from(id);
+    }
+
+    for (recipient, index) in msg_order {
+        net.deliver(recipient % n + 1, index);
+    }
+
+    net.drain_queue();
+
+    let decisions = Vec::from_iter(net.nodes.values().map(|node| node.decided_value()));
+    assert!(decisions[0].is_some());
+    assert!(decisions.iter().all(|d| d == &decisions[0]));
+}
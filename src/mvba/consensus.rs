@@ -1,37 +1,91 @@
 use super::{
-    abba::{self, Abba},
+    abba::{self, message::MainVoteValue, Abba},
     bundle::{Bundle, Outgoing},
     error::Error,
     error::Result,
+    fault::Fault,
     hash::Hash32,
     mvba::{self, Mvba},
     tag::{Domain, Tag},
     vcbc, Proposal,
 };
 use crate::mvba::{broadcaster::Broadcaster, vcbc::Vcbc, MessageValidity, NodeId};
-use blsttc::{PublicKeySet, SecretKeyShare};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::observer::{ConsensusEvent, ConsensusObserver};
+use crate::sn_membership::Generation;
+use blsttc::{PublicKeySet, SecretKeyShare, Signature};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+/// The proposal the parties agreed on, along with the proof of the agreement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision<P> {
+    /// The party whose proposal was agreed on.
+    pub proposer: NodeId,
+    pub proposal: P,
+    /// The `c-final` signature of the proposer's VCBC on the digest of the proposal.
+    pub proposal_sig: Signature,
+    /// The ABBA round in which the parties agreed on the proposer.
+    pub round: usize,
+    /// The combined ABBA main-vote signature for one in `round`.
+    pub agreement_sig: Signature,
+}
+
+impl<P: Proposal> Decision<P> {
+    /// Checks that the parties holding `pub_key_set` agreed on this decision in `domain`.
+    pub fn verify(&self, domain: &Domain, pub_key_set: &PublicKeySet) -> Result<()> {
+        let tag = Tag::new(domain.clone(), self.proposer);
+        let public_key = pub_key_set.public_key();
+
+        let digest = Hash32::calculate_serialized(&self.proposal)?;
+        let sign_bytes = vcbc::c_ready_bytes_to_sign(&tag, &digest)?;
+        if !public_key.verify(&self.proposal_sig, sign_bytes) {
+            return Err(Error::InvalidMessage(
+                "invalid c-final signature for the proposal".to_string(),
+            ));
+        }
+
+        let sign_bytes = abba::main_vote_bytes_to_sign(&tag, self.round, &MainVoteValue::one())?;
+        if !public_key.verify(&self.agreement_sig, sign_bytes) {
+            return Err(Error::InvalidMessage(
+                "invalid main-vote signature for the agreement".to_string(),
+            ));
+        }
 
-pub struct Consensus {
+        Ok(())
+    }
+}
+
+/// Called once the parties have agreed on a proposal.
+pub type DecisionCallback<P> = Box<dyn FnMut(&Decision<P>)>;
+
+pub struct Consensus<P: Proposal> {
     domain: Domain,
     self_id: NodeId,
     abba_map: HashMap<NodeId, Abba>,
-    vcbc_map: HashMap<NodeId, Vcbc>,
-    mvba: Mvba,
+    vcbc_map: HashMap<NodeId, Vcbc<P>>,
+    mvba: Mvba<P>,
     decided_proposer: Option<NodeId>,
-    decided_proposal: Option<Proposal>,
+    decision: Option<Decision<P>>,
+    on_decision: Option<DecisionCallback<P>>,
+    observer: Option<Box<dyn ConsensusObserver<P>>>,
+    #[cfg(feature = "metrics")]
+    started: std::time::Instant,
     broadcaster: Rc<RefCell<Broadcaster>>,
 }
 
-impl Consensus {
+impl<P: Proposal> Consensus<P> {
     pub fn init(
         domain: Domain,
         self_id: NodeId,
         sec_key_share: SecretKeyShare,
         pub_key_set: PublicKeySet,
         parties: Vec<NodeId>,
-        message_validity: MessageValidity,
-    ) -> Consensus {
+        message_validity: MessageValidity<P>,
+    ) -> Self {
         let broadcaster = Broadcaster::new(self_id);
         let broadcaster_rc = Rc::new(RefCell::new(broadcaster));
         let mut abba_map = HashMap::new();
@@ -44,7 +98,7 @@ impl Consensus {
                 self_id,
                 pub_key_set.clone(),
                 sec_key_share.clone(),
-                message_validity,
+                message_validity.clone(),
                 broadcaster_rc.clone(),
             );
             vcbc_map.insert(*party, vcbc);
@@ -75,13 +129,61 @@ impl Consensus {
             abba_map,
             mvba,
             decided_proposer: None,
-            decided_proposal: None,
+            decision: None,
+            on_decision: None,
+            observer: None,
+            #[cfg(feature = "metrics")]
+            started: std::time::Instant::now(),
             broadcaster: broadcaster_rc,
         }
     }
 
+    /// The decision, once we have both agreed on a proposer and received its proposal.
+    pub fn decision(&self) -> Option<&Decision<P>> {
+        self.decision.as_ref()
+    }
+
+    /// The faults we have proof of, by the party at fault.
+    ///
+    /// Faults carry the signatures of the party at fault, so they can be passed on and
+    /// validated by anyone.
+    pub fn faults(&self) -> BTreeMap<NodeId, Vec<Fault>> {
+        let vcbc_faults = self.vcbc_map.values().flat_map(|vcbc| vcbc.faults());
+        let abba_faults = self.abba_map.values().flat_map(|abba| abba.faults());
+
+        let mut faults: BTreeMap<NodeId, Vec<Fault>> = BTreeMap::new();
+        for fault in vcbc_faults.chain(abba_faults).chain(self.mvba.faults()) {
+            faults
+                .entry(fault.party_at_fault())
+                .or_default()
+                .push(fault.clone());
+        }
+        faults
+    }
+
+    /// Registers `callback` to be called when we reach the decision.
+    pub fn on_decision(&mut self, callback: impl FnMut(&Decision<P>) + 'static) {
+        self.on_decision = Some(Box::new(callback));
+    }
+
+    /// Notifies `observer` of the state transitions of the agreement from now on.
+    ///
+    /// The messages of the underlying VCBC, ABBA and MVBA instances are reported as
+    /// votes, the generation of the events is the sequence number of the domain.
+    pub fn set_observer(&mut self, observer: impl ConsensusObserver<P> + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    fn observe(&mut self, event: ConsensusEvent<P>) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_event("mvba", &event);
+        if let Some(observer) = self.observer.as_mut() {
+            observer.observe(&event);
+        }
+    }
+
     /// starts the consensus by proposing the `proposal`.
-    pub fn propose(&mut self, proposal: Proposal) -> Result<Vec<Outgoing>> {
+    pub fn propose(&mut self, proposal: P) -> Result<Vec<Outgoing>> {
         match self.vcbc_map.get_mut(&self.self_id) {
             Some(vcbc) => {
                 // verifiably authenticatedly c-broadcast message (v-echo, w, π) tagged with ID|vcbc.i.0
@@ -95,10 +197,20 @@ impl Consensus {
     }
 
     pub fn process_bundle(&mut self, bundle: &Bundle) -> Result<Vec<Outgoing>> {
-        if self.decided_proposal.is_some() {
+        if self.decision.is_some() {
             return Ok(vec![]);
         }
 
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_histogram(
+            "sn_consensus_bundle_payload_bytes",
+            &[("module", &bundle.module)],
+            bundle.payload.len() as f64,
+        );
+
+        let gen = self.domain.seq as Generation;
+        let known_faults = self.faults();
+
         match bundle.module.as_ref() {
             vcbc::MODULE_NAME => match bundle.target {
                 Some(target) => match self.vcbc_map.get_mut(&target) {
@@ -110,11 +222,10 @@ impl Consensus {
                             //    There might be a situation that we receive the agreement
                             //    before receiving the actual proposal.
 
-                            if self.decided_proposer.is_some() {
+                            if self.decided_proposer == Some(target) {
                                 // We re done! We have both proposal and agreement
-                                log::info!("halted. proposer: {target}");
-                                self.decided_proposal = Some(proposal);
-                            } else {
+                                self.decide(target, proposal, sig)?;
+                            } else if self.decided_proposer.is_none() {
                                 self.mvba.set_proposal(target, proposal, sig)?;
                             }
                         }
@@ -139,15 +250,14 @@ impl Consensus {
                                     .vcbc_map
                                     .get_mut(&target)
                                     .expect("vcbc_map is not initialized");
-                                if let Some((proposal, _)) = vcbc.read_delivered() {
+                                if let Some((proposal, sig)) = vcbc.read_delivered() {
                                     // We re done! We have both proposal and agreement
-                                    log::info!("halted. proposer: {target}");
-                                    self.decided_proposal = Some(proposal);
+                                    self.decide(target, proposal, sig)?;
                                 } else {
                                     // abba is finished but still we don't have the proposal
                                     // request it from the initiator
                                     let tag = Tag::new(self.domain.clone(), target);
-                                    let data = vcbc::make_c_request_message(tag)?;
+                                    let data = vcbc::make_c_request_message::<P>(tag)?;
 
                                     self.broadcaster.borrow_mut().broadcast(
                                         vcbc::MODULE_NAME,
@@ -181,6 +291,30 @@ impl Consensus {
             }
         };
 
+        self.observe(ConsensusEvent::VoteReceived {
+            gen,
+            voter: bundle.initiator as crate::NodeId,
+        });
+        for (party, faults) in self.faults() {
+            if faults.len() > known_faults.get(&party).map_or(0, Vec::len) {
+                self.observe(ConsensusEvent::FaultDetected {
+                    gen,
+                    voter: party as crate::NodeId,
+                });
+            }
+        }
+        if let Some(decision) = &self.decision {
+            let proposals = vec![decision.proposal.clone()];
+            self.observe(ConsensusEvent::Decided { gen, proposals });
+
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_histogram(
+                "sn_consensus_decision_seconds",
+                &[("engine", "mvba")],
+                self.started.elapsed().as_secs_f64(),
+            );
+        }
+
         if let Some(completed_vote) = self.mvba.completed_vote() {
             let abba = self
                 .abba_map
@@ -191,7 +325,7 @@ impl Consensus {
                 // The proposal is c-delivered and we have proof for that.
                 // Let's start binary agreement by voting 1
                 if let Some((proposal, sig)) = self.mvba.completed_vote_value()? {
-                    let digest = Hash32::calculate(proposal);
+                    let digest = Hash32::calculate_serialized(proposal)?;
                     abba.pre_vote_one(digest, sig.clone())?;
                 }
             } else {
@@ -203,6 +337,29 @@ impl Consensus {
 
         Ok(self.broadcaster.borrow_mut().take_outgoings())
     }
+
+    fn decide(&mut self, proposer: NodeId, proposal: P, proposal_sig: Signature) -> Result<()> {
+        log::info!("halted. proposer: {proposer}");
+
+        let agreement = self
+            .abba_map
+            .get(&proposer)
+            .and_then(|abba| abba.decision())
+            .ok_or_else(|| Error::Generic(format!("no agreement on proposer {proposer}")))?;
+
+        let decision = Decision {
+            proposer,
+            proposal,
+            proposal_sig,
+            round: agreement.round,
+            agreement_sig: agreement.sig.clone(),
+        };
+        if let Some(callback) = self.on_decision.as_mut() {
+            callback(&decision);
+        }
+        self.decision = Some(decision);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -211,17 +368,21 @@ mod tests {
 
     use super::Consensus;
     use crate::mvba::{bundle::Outgoing, tag::Domain, *};
+    use crate::observer::{ConsensusEvent, MemoryEventLog};
 
-    use blsttc::SecretKeySet;
+    use blsttc::{PublicKeySet, SecretKeySet};
     use quickcheck_macros::quickcheck;
     use rand::{thread_rng, Rng, SeedableRng};
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
-    fn valid_proposal(_id: NodeId, _: &Proposal) -> bool {
+    fn valid_proposal(_id: NodeId, _: &Vec<u8>) -> bool {
         true
     }
 
     struct TestNet {
-        cons: Vec<Consensus>,
+        pub_key_set: PublicKeySet,
+        cons: Vec<Consensus<Vec<u8>>>,
         buffer: Vec<Outgoing>,
     }
 
@@ -246,13 +407,14 @@ mod tests {
                     sec_key_set.secret_key_share(p),
                     sec_key_set.public_keys(),
                     parties.clone(),
-                    valid_proposal,
+                    Rc::new(valid_proposal),
                 );
 
                 cons.push(consensus);
             }
 
             Self {
+                pub_key_set: sec_key_set.public_keys(),
                 cons,
                 buffer: Vec::new(),
             }
@@ -267,8 +429,22 @@ mod tests {
         let mut rng = rand::rngs::StdRng::from_seed(seed_buf);
 
         let mut net = TestNet::new();
+        let decided = Rc::new(RefCell::new(HashMap::new()));
+        let mut event_logs = Vec::new();
 
         for c in &mut net.cons {
+            let event_log = MemoryEventLog::default();
+            c.set_observer(event_log.clone());
+            event_logs.push(event_log);
+
+            let (self_id, decided) = (c.self_id, decided.clone());
+            c.on_decision(move |decision| {
+                assert!(decided
+                    .borrow_mut()
+                    .insert(self_id, decision.clone())
+                    .is_none());
+            });
+
             let proposal = (0..4).map(|_| rng.gen_range(0..64)).collect();
             let mut msgs = c.propose(proposal).unwrap();
             net.buffer.append(&mut msgs);
@@ -297,21 +473,15 @@ mod tests {
         }
 
         let mut decisions = HashMap::new();
-        for c in &mut net.cons {
-            if c.decided_proposer.is_some() {
-                let value = c
-                    .abba_map
-                    .get(&c.decided_proposer.unwrap())
-                    .unwrap()
-                    .decided_value()
-                    .unwrap();
-
+        for c in &net.cons {
+            if let Some(decision) = c.decision() {
                 log::debug!(
-                    "test for consensus {} finished on proposal {} with {value}",
+                    "test for consensus {} finished on proposal {}",
                     c.self_id,
-                    c.decided_proposer.unwrap(),
+                    decision.proposer,
                 );
-                decisions.insert(c.self_id, (c.decided_proposer.unwrap(), value));
+                decision.verify(&c.domain, &net.pub_key_set).unwrap();
+                decisions.insert(c.self_id, decision.clone());
             }
         }
 
@@ -320,6 +490,33 @@ mod tests {
         // https://sts10.github.io/2019/06/06/is-all-equal-function.html
         let first = decisions.iter().next().unwrap().1;
         assert!(decisions.iter().all(|(_, item)| item == first));
+
+        // the callback was called once with the same decision
+        assert_eq!(*decided.borrow(), decisions);
+
+        // and so were the observers
+        for event_log in event_logs {
+            let decided_events = Vec::from_iter(
+                event_log
+                    .events()
+                    .into_iter()
+                    .filter(|e| matches!(e, ConsensusEvent::Decided { .. })),
+            );
+            assert_eq!(
+                decided_events,
+                vec![ConsensusEvent::Decided {
+                    gen: 0,
+                    proposals: vec![first.proposal.clone()]
+                }]
+            );
+        }
+
+        // the proof doesn't hold for any other proposal
+        let mut forged = first.clone();
+        forged.proposal.push(0);
+        assert!(forged
+            .verify(&net.cons[0].domain, &net.pub_key_set)
+            .is_err());
     }
 
     #[test]
@@ -365,21 +562,15 @@ mod tests {
         }
 
         let mut decisions = HashMap::new();
-        for c in &mut net.cons {
-            if c.decided_proposer.is_some() {
-                let value = c
-                    .abba_map
-                    .get(&c.decided_proposer.unwrap())
-                    .unwrap()
-                    .decided_value()
-                    .unwrap();
-
+        for c in &net.cons {
+            if let Some(decision) = c.decision() {
                 log::debug!(
-                    "test for consensus {} finished on proposal {} with {value}",
+                    "test for consensus {} finished on proposal {}",
                     c.self_id,
-                    c.decided_proposer.unwrap(),
+                    decision.proposer,
                 );
-                decisions.insert(c.self_id, (c.decided_proposer.unwrap(), value));
+                decision.verify(&c.domain, &net.pub_key_set).unwrap();
+                decisions.insert(c.self_id, decision.clone());
             }
         }
 
@@ -432,21 +623,15 @@ mod tests {
         }
 
         let mut decisions = HashMap::new();
-        for c in &mut net.cons {
-            if c.decided_proposer.is_some() {
-                let value = c
-                    .abba_map
-                    .get(&c.decided_proposer.unwrap())
-                    .unwrap()
-                    .decided_value()
-                    .unwrap();
-
+        for c in &net.cons {
+            if let Some(decision) = c.decision() {
                 log::debug!(
-                    "test for consensus {} finished on proposal {} with {value}",
+                    "test for consensus {} finished on proposal {}",
                     c.self_id,
-                    c.decided_proposer.unwrap(),
+                    decision.proposer,
                 );
-                decisions.insert(c.self_id, (c.decided_proposer.unwrap(), value));
+                decision.verify(&c.domain, &net.pub_key_set).unwrap();
+                decisions.insert(c.self_id, decision.clone());
             }
         }
 
@@ -455,5 +640,8 @@ mod tests {
         // https://sts10.github.io/2019/06/06/is-all-equal-function.html
         let first = decisions.iter().next().unwrap().1;
         assert!(decisions.iter().all(|(_, item)| item == first));
+
+        // honest parties have nothing to blame each other for
+        assert!(net.cons.iter().all(|c| c.faults().is_empty()));
     }
 }
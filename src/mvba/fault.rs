@@ -0,0 +1,144 @@
+use blsttc::{PublicKeySet, SignatureShare};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::abba;
+use super::hash::Hash32;
+use super::tag::Tag;
+use super::{vcbc, NodeId};
+
+pub use super::abba::message::{MainVoteValue, Value};
+pub use super::mvba::message::Vote;
+
+#[derive(Debug, Error)]
+pub enum FaultError {
+    #[error("the claimed fault is not made of conflicting messages")]
+    NotConflicting,
+    #[error("the claimed fault uses a message that was not signed by {0}")]
+    InvalidSignature(NodeId),
+    #[error("encoding/decoding error {0:?}")]
+    Encoding(#[from] bincode::Error),
+}
+
+/// A value along with the signature share of the party that signed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signed<V> {
+    pub value: V,
+    pub sig_share: SignatureShare,
+}
+
+/// Transferable evidence of a party signing two conflicting messages.
+/// Anyone holding the public key set of the parties can validate it.
+///
+/// A duplicated VCBC `c-final` is not a fault, it is not signed by the party sending it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum Fault {
+    /// Two VCBC `c-ready` messages for different digests of the same broadcast.
+    DoubleReady {
+        party: NodeId,
+        tag: Tag,
+        a: Signed<Hash32>,
+        b: Signed<Hash32>,
+    },
+    /// Two ABBA pre-votes for different values in the same round.
+    DoublePreVote {
+        party: NodeId,
+        tag: Tag,
+        round: usize,
+        a: Signed<Value>,
+        b: Signed<Value>,
+    },
+    /// Two ABBA main-votes for different values in the same round.
+    DoubleMainVote {
+        party: NodeId,
+        tag: Tag,
+        round: usize,
+        a: Signed<MainVoteValue>,
+        b: Signed<MainVoteValue>,
+    },
+    /// Two different MVBA votes for the same proposer.
+    DoubleVote {
+        party: NodeId,
+        a: Signed<Vote>,
+        b: Signed<Vote>,
+    },
+}
+
+impl Fault {
+    pub fn party_at_fault(&self) -> NodeId {
+        match self {
+            Fault::DoubleReady { party, .. }
+            | Fault::DoublePreVote { party, .. }
+            | Fault::DoubleMainVote { party, .. }
+            | Fault::DoubleVote { party, .. } => *party,
+        }
+    }
+
+    pub fn validate(&self, pub_key_set: &PublicKeySet) -> Result<(), FaultError> {
+        let (a, b) = match self {
+            Fault::DoubleReady { tag, a, b, .. } => {
+                if a.value == b.value {
+                    return Err(FaultError::NotConflicting);
+                }
+                (
+                    (vcbc::c_ready_bytes_to_sign(tag, &a.value)?, &a.sig_share),
+                    (vcbc::c_ready_bytes_to_sign(tag, &b.value)?, &b.sig_share),
+                )
+            }
+            Fault::DoublePreVote {
+                tag, round, a, b, ..
+            } => {
+                if a.value == b.value {
+                    return Err(FaultError::NotConflicting);
+                }
+                (
+                    (
+                        abba::pre_vote_bytes_to_sign(tag, *round, &a.value)?,
+                        &a.sig_share,
+                    ),
+                    (
+                        abba::pre_vote_bytes_to_sign(tag, *round, &b.value)?,
+                        &b.sig_share,
+                    ),
+                )
+            }
+            Fault::DoubleMainVote {
+                tag, round, a, b, ..
+            } => {
+                if a.value == b.value {
+                    return Err(FaultError::NotConflicting);
+                }
+                (
+                    (
+                        abba::main_vote_bytes_to_sign(tag, *round, &a.value)?,
+                        &a.sig_share,
+                    ),
+                    (
+                        abba::main_vote_bytes_to_sign(tag, *round, &b.value)?,
+                        &b.sig_share,
+                    ),
+                )
+            }
+            Fault::DoubleVote { a, b, .. } => {
+                if a.value.tag != b.value.tag || a.value == b.value {
+                    return Err(FaultError::NotConflicting);
+                }
+                (
+                    (bincode::serialize(&a.value)?, &a.sig_share),
+                    (bincode::serialize(&b.value)?, &b.sig_share),
+                )
+            }
+        };
+
+        let party = self.party_at_fault();
+        let public_key_share = pub_key_set.public_key_share(party);
+        for (sign_bytes, sig_share) in [a, b] {
+            if !public_key_share.verify(sig_share, sign_bytes) {
+                return Err(FaultError::InvalidSignature(party));
+            }
+        }
+
+        Ok(())
+    }
+}
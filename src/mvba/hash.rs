@@ -25,6 +25,11 @@ impl Hash32 {
         Hash32(hash)
     }
 
+    /// Calculates the hash of the serialized `value`, e.g. the $H(m)$ of a proposal.
+    pub fn calculate_serialized(value: &impl Serialize) -> Result<Self, bincode::Error> {
+        Ok(Self::calculate(bincode::serialize(value)?))
+    }
+
     pub fn from_bytes(data: &[u8]) -> Result<Self, InvalidLength> {
         let bytes: &[u8; HASH32_SIZE] = data.try_into().map_err(|_| InvalidLength {
             expected: HASH32_SIZE,
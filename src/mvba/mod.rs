@@ -1,11 +1,17 @@
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use serde::{de::DeserializeOwned, Serialize};
+
 pub mod consensus;
 pub mod error;
+pub mod fault;
 pub mod hash;
 pub mod tag;
 
 mod abba;
 mod broadcaster;
-mod bundle;
+pub mod bundle;
 // TODO: remove me
 #[allow(clippy::module_inception)]
 mod mvba;
@@ -14,9 +20,12 @@ mod vcbc;
 pub type NodeId = usize;
 
 /// A proposed data with the proof inside. It is the same as $(w, π)$ in the spec.
-pub type Proposal = Vec<u8>;
+///
+/// Proposals are hashed and sent to other parties in their serialized form.
+pub trait Proposal: Clone + Debug + Eq + Serialize + DeserializeOwned {}
+impl<P: Clone + Debug + Eq + Serialize + DeserializeOwned> Proposal for P {}
 
 /// MessageValidity is same as &Q_{ID}$ ins spec: a global polynomial-time computable
 /// predicate QID known to all parties, which is determined by an external application.
 /// Each party may propose a value v together with a proof π that should satisfy QID .
-pub type MessageValidity = fn(NodeId, &Proposal) -> bool;
+pub type MessageValidity<P> = Rc<dyn Fn(NodeId, &P) -> bool>;
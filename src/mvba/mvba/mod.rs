@@ -1,7 +1,8 @@
 pub(crate) mod error;
-mod message;
+pub mod message;
 
 use self::message::{Message, Vote};
+use super::fault::{Fault, Signed};
 
 use self::{error::Error, error::Result};
 use super::tag::Domain;
@@ -14,21 +15,22 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 pub(crate) const MODULE_NAME: &str = "mvba";
 
-pub struct Mvba {
+pub struct Mvba<P: Proposal> {
     domain: Domain,  // this is same as $ID.s$ in spec
     i: NodeId,       // this is same as $i$ in spec
     l: usize,        // this is same as $a$ in spec
     v: Option<bool>, // this is same as $v$ in spec
-    proposals: HashMap<NodeId, (Proposal, Signature)>,
-    votes_per_proposer: HashMap<NodeId, HashMap<NodeId, Vote>>,
+    proposals: HashMap<NodeId, (P, Signature)>,
+    votes_per_proposer: HashMap<NodeId, HashMap<NodeId, Message>>,
     voted: bool,
     pub_key_set: PublicKeySet,
     sec_key_share: SecretKeyShare,
     parties: Vec<NodeId>,
+    faults: Vec<Fault>,
     broadcaster: Rc<RefCell<Broadcaster>>,
 }
 
-impl Mvba {
+impl<P: Proposal> Mvba<P> {
     pub fn new(
         domain: Domain,
         self_id: NodeId,
@@ -48,6 +50,7 @@ impl Mvba {
             pub_key_set,
             sec_key_share,
             parties,
+            faults: Vec::new(),
             broadcaster,
         }
     }
@@ -55,12 +58,12 @@ impl Mvba {
     pub fn set_proposal(
         &mut self,
         proposer: NodeId,
-        proposal: Proposal,
+        proposal: P,
         signature: Signature,
     ) -> Result<()> {
         debug_assert!(self.parties.contains(&proposer));
         let tag = self.build_tag(proposer);
-        let digest = Hash32::calculate(&proposal);
+        let digest = Hash32::calculate_serialized(&proposal)?;
         let sign_bytes = vcbc::c_ready_bytes_to_sign(&tag, &digest)?;
         if !self.pub_key_set.public_key().verify(&signature, sign_bytes) {
             return Err(Error::InvalidMessage(
@@ -109,10 +112,15 @@ impl Mvba {
         self.v
     }
 
-    pub fn completed_vote_value(&self) -> Result<Option<&(Proposal, Signature)>> {
+    pub fn completed_vote_value(&self) -> Result<Option<&(P, Signature)>> {
         Ok(self.proposals.get(&self.current_proposer()?))
     }
 
+    /// The faults we have proof of.
+    pub fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
     fn check_message(&mut self, msg: &Message) -> Result<()> {
         if msg.vote.tag.domain != self.domain {
             return Err(Error::InvalidMessage(format!(
@@ -153,8 +161,23 @@ impl Mvba {
 
     pub fn add_vote(&mut self, msg: &Message) -> Result<bool> {
         let votes = self.proposer_votes_mut(&msg.vote.tag.proposer);
-        if let Some(exist) = votes.get(&msg.voter) {
-            if exist != &msg.vote {
+        if let Some(exist) = votes.get(&msg.voter).cloned() {
+            if exist.vote != msg.vote {
+                let fault = Fault::DoubleVote {
+                    party: msg.voter,
+                    a: Signed {
+                        value: exist.vote,
+                        sig_share: exist.signature,
+                    },
+                    b: Signed {
+                        value: msg.vote.clone(),
+                        sig_share: msg.signature.clone(),
+                    },
+                };
+                log::warn!("party {} detected a fault: {fault:?}", self.i);
+                if !self.faults.contains(&fault) {
+                    self.faults.push(fault);
+                }
                 return Err(Error::InvalidMessage(format!(
                     "double vote detected from {:?}",
                     msg.voter
@@ -163,7 +186,7 @@ impl Mvba {
             return Ok(false);
         }
 
-        votes.insert(msg.voter, msg.vote.clone());
+        votes.insert(msg.voter, msg.clone());
 
         if msg.vote.value && !self.proposals.contains_key(&msg.vote.tag.proposer) {
             // If a v-vote from Pj indicates 1 but Pi has not yet received Pa ’s proposal,
@@ -175,7 +198,7 @@ impl Mvba {
                 self.i,
                 msg.vote.tag.proposer,
             );
-            let data = vcbc::make_c_request_message(self.current_tag()?)?;
+            let data = vcbc::make_c_request_message::<P>(self.current_tag()?)?;
 
             self.broadcaster.borrow_mut().send_to(
                 vcbc::MODULE_NAME,
@@ -207,7 +230,7 @@ impl Mvba {
             // that VID|a (uj , ρj) holds
             let votes = self.proposer_votes_mut(&msg.vote.tag.proposer);
             if votes.len() >= threshold {
-                if votes.values().any(|v| v.value) {
+                if votes.values().any(|v| v.vote.value) {
                     log::debug!(
                         "party {} completed for proposer {}.",
                         self.i,
@@ -227,7 +250,7 @@ impl Mvba {
         Ok(())
     }
 
-    fn proposer_votes_mut(&mut self, proposer: &NodeId) -> &mut HashMap<NodeId, Vote> {
+    fn proposer_votes_mut(&mut self, proposer: &NodeId) -> &mut HashMap<NodeId, Message> {
         self.votes_per_proposer.entry(*proposer).or_default()
     }
 
@@ -250,7 +273,7 @@ impl Mvba {
                     // else
                     // let ρ be the message that completes the c-broadcast with tag ID|vcbc.a.0
                     // send the message (ID, v-vote, a, 1, ρ) to all parties
-                    let digest = Hash32::calculate(proposal);
+                    let digest = Hash32::calculate_serialized(proposal)?;
                     Vote {
                         tag,
                         value: true,
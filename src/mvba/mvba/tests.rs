@@ -2,9 +2,10 @@ use super::message::{Message, Vote};
 use super::NodeId;
 use super::{Error, Mvba};
 use crate::mvba::broadcaster::Broadcaster;
+use crate::mvba::fault::{Fault, Signed};
 use crate::mvba::hash::Hash32;
 use crate::mvba::tag::{Domain, Tag};
-use crate::mvba::{vcbc, Proposal};
+use crate::mvba::vcbc;
 use blsttc::{SecretKey, SecretKeySet, Signature, SignatureShare};
 use rand::{thread_rng, Rng};
 use std::cell::RefCell;
@@ -13,9 +14,9 @@ use std::rc::Rc;
 
 struct TestNet {
     sec_key_set: SecretKeySet,
-    mvba: Mvba,
+    mvba: Mvba<Vec<u8>>,
     broadcaster: Rc<RefCell<Broadcaster>>,
-    proposals: HashMap<NodeId, (Proposal, Signature)>,
+    proposals: HashMap<NodeId, (Vec<u8>, Signature)>,
 }
 
 impl TestNet {
@@ -38,7 +39,7 @@ impl TestNet {
 
         for p in &parties {
             let proposal = (0..100).map(|_| rng.gen_range(0..64)).collect();
-            let digest = Hash32::calculate(&proposal);
+            let digest = Hash32::calculate_serialized(&proposal).unwrap();
             let tag = Tag::new(domain.clone(), *p);
             let proposal_sign_bytes = vcbc::c_ready_bytes_to_sign(&tag, &digest).unwrap();
             let sig = sec_key_set.secret_key().sign(proposal_sign_bytes);
@@ -69,7 +70,7 @@ impl TestNet {
             None
         } else {
             let (proposal, signature) = self.proposals.get(&proposer).unwrap();
-            let digest = Hash32::calculate(proposal);
+            let digest = Hash32::calculate_serialized(proposal).unwrap();
             Some((digest, signature.clone()))
         };
 
@@ -195,10 +196,26 @@ fn test_double_vote() {
     let msg_1 = t.make_vote_msg(voter, proposer, true);
     let msg_2 = t.make_vote_msg(voter, proposer, false);
 
-    t.mvba.receive_message(msg_1).unwrap();
-    let result = t.mvba.receive_message(msg_2);
+    t.mvba.receive_message(msg_1.clone()).unwrap();
+    let result = t.mvba.receive_message(msg_2.clone());
     assert!(matches!(result, Err(Error::InvalidMessage(msg))
         if msg == format!("double vote detected from {voter:?}")));
+
+    let expected_fault = Fault::DoubleVote {
+        party: voter,
+        a: Signed {
+            value: msg_1.vote,
+            sig_share: msg_1.signature,
+        },
+        b: Signed {
+            value: msg_2.vote,
+            sig_share: msg_2.signature,
+        },
+    };
+    assert_eq!(t.mvba.faults(), std::slice::from_ref(&expected_fault));
+    assert!(expected_fault
+        .validate(&t.sec_key_set.public_keys())
+        .is_ok());
 }
 
 #[test]
@@ -289,7 +306,7 @@ fn test_request_proposal() {
     t.mvba.receive_message(msg_x).unwrap();
 
     let tag = Tag::new(t.mvba.domain.clone(), TestNet::PARTY_X);
-    let data = vcbc::make_c_request_message(tag).unwrap();
+    let data = vcbc::make_c_request_message::<Vec<u8>>(tag).unwrap();
 
     assert!(t
         .broadcaster
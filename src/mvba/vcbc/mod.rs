@@ -10,6 +10,7 @@ use blsttc::{PublicKeySet, SecretKeyShare, Signature, SignatureShare};
 
 use self::error::{Error, Result};
 use self::message::{Action, Message};
+use super::fault::{Fault, Signed};
 use super::hash::Hash32;
 use super::tag::Tag;
 use super::{MessageValidity, NodeId, Proposal};
@@ -19,8 +20,10 @@ pub(crate) const MODULE_NAME: &str = "vcbc";
 
 // make_c_request_message creates the payload message to request a proposal
 // from the the proposer
-pub fn make_c_request_message(tag: Tag) -> std::result::Result<Vec<u8>, bincode::Error> {
-    let msg = Message {
+pub fn make_c_request_message<P: Proposal>(
+    tag: Tag,
+) -> std::result::Result<Vec<u8>, bincode::Error> {
+    let msg = Message::<P> {
         tag,
         action: Action::Request,
     };
@@ -38,18 +41,19 @@ pub fn c_ready_bytes_to_sign(
 }
 
 // Protocol VCBC for verifiable and authenticated consistent broadcast.
-pub(crate) struct Vcbc {
+pub(crate) struct Vcbc<P: Proposal> {
     tag: Tag,                            // this is same as $Tag$ in spec
     i: NodeId,                           // this is same as $i$ in spec
-    m_bar: Option<Proposal>,             // this is same as $\bar{m}$ in spec
+    m_bar: Option<P>,                    // this is same as $\bar{m}$ in spec
     u_bar: Option<Signature>,            // this is same as $\bar{\mu}$ in spec
     wd: HashMap<NodeId, SignatureShare>, // this is same as $W_d$ in spec
     rd: usize,                           // this is same as $r_d$ in spec
     d: Option<Hash32>,                   // Memorizing the message digest
     pub_key_set: PublicKeySet,
     sec_key_share: SecretKeyShare,
-    final_messages: HashMap<NodeId, Message>,
-    message_validity: MessageValidity,
+    final_messages: HashMap<NodeId, Message<P>>,
+    faults: Vec<Fault>,
+    message_validity: MessageValidity<P>,
     broadcaster: Rc<RefCell<Broadcaster>>,
 }
 
@@ -58,7 +62,7 @@ pub(crate) struct Vcbc {
 /// If the map already had this key present, nothing is updated, and
 /// `DuplicatedMessage` error is returned.
 /// TODO: replace it with unstable try_insert function
-fn try_insert(map: &mut HashMap<NodeId, Message>, k: NodeId, v: Message) -> Result<()> {
+fn try_insert<P>(map: &mut HashMap<NodeId, Message<P>>, k: NodeId, v: Message<P>) -> Result<()> {
     if let std::collections::hash_map::Entry::Vacant(e) = map.entry(k) {
         e.insert(v);
         Ok(())
@@ -67,13 +71,13 @@ fn try_insert(map: &mut HashMap<NodeId, Message>, k: NodeId, v: Message) -> Resu
     }
 }
 
-impl Vcbc {
+impl<P: Proposal> Vcbc<P> {
     pub fn new(
         tag: Tag,
         self_id: NodeId,
         pub_key_set: PublicKeySet,
         sec_key_share: SecretKeyShare,
-        message_validity: MessageValidity,
+        message_validity: MessageValidity<P>,
         broadcaster: Rc<RefCell<Broadcaster>>,
     ) -> Self {
         debug_assert_eq!(self_id, broadcaster.borrow().self_id());
@@ -87,6 +91,7 @@ impl Vcbc {
             rd: 0,
             d: None,
             final_messages: HashMap::new(),
+            faults: Vec::new(),
             pub_key_set,
             sec_key_share,
             message_validity,
@@ -96,7 +101,7 @@ impl Vcbc {
 
     /// c_broadcast sends the messages `m` to all other parties.
     /// It also adds the message to message_log and process it.
-    pub fn c_broadcast(&mut self, m: Proposal) -> Result<()> {
+    pub fn c_broadcast(&mut self, m: P) -> Result<()> {
         debug_assert_eq!(self.i, self.tag.proposer);
 
         // Upon receiving message (ID.j.s, in, c-broadcast, m):
@@ -109,7 +114,7 @@ impl Vcbc {
     }
 
     /// receive_message process the received message 'msg` from `initiator`
-    pub fn receive_message(&mut self, initiator: NodeId, msg: Message) -> Result<()> {
+    pub fn receive_message(&mut self, initiator: NodeId, msg: Message<P>) -> Result<()> {
         log::trace!(
             "party {} received {} message: {:?} from {}",
             self.i,
@@ -136,7 +141,7 @@ impl Vcbc {
                     // m̄ ← m
                     self.m_bar = Some(m.clone());
 
-                    let d = Hash32::calculate(&m);
+                    let d = Hash32::calculate_serialized(&m)?;
                     self.d = Some(d);
 
                     // compute an S1-signature share ν on (ID.j.s, c-ready, H(m))
@@ -161,6 +166,32 @@ impl Vcbc {
 
                 if d != msg_d {
                     log::warn!("party {} received c-ready with unknown digest. expected {d:?}, got {msg_d:?}", self.i);
+                    // A party that already signed c-ready for our digest has signed two digests
+                    if let Some(exist) = self.wd.get(&initiator) {
+                        let msg_sign_bytes = c_ready_bytes_to_sign(&self.tag, &msg_d)?;
+                        if self
+                            .pub_key_set
+                            .public_key_share(initiator)
+                            .verify(&sig_share, msg_sign_bytes)
+                        {
+                            let fault = Fault::DoubleReady {
+                                party: initiator,
+                                tag: self.tag.clone(),
+                                a: Signed {
+                                    value: d,
+                                    sig_share: exist.clone(),
+                                },
+                                b: Signed {
+                                    value: msg_d,
+                                    sig_share,
+                                },
+                            };
+                            log::warn!("party {} detected a fault: {fault:?}", self.i);
+                            if !self.faults.contains(&fault) {
+                                self.faults.push(fault);
+                            }
+                        }
+                    }
                     return Err(Error::Generic("Invalid digest".to_string()));
                 }
 
@@ -261,7 +292,7 @@ impl Vcbc {
                 // Upon receiving message (ID.j.s, c-answer, m, µ) from Pl :
                 if self.u_bar.is_none() {
                     // if µ̄ = ⊥ and ...
-                    let d = Hash32::calculate(&m);
+                    let d = Hash32::calculate_serialized(&m)?;
                     let sign_bytes = c_ready_bytes_to_sign(&self.tag, &d)?;
                     if self.pub_key_set.public_key().verify(&u, sign_bytes) {
                         // ... µ is a valid S1 -signature on (ID.j.s, c-ready, H(m)) then
@@ -281,7 +312,12 @@ impl Vcbc {
         Ok(())
     }
 
-    pub fn read_delivered(&self) -> Option<(Proposal, Signature)> {
+    /// The faults we have proof of.
+    pub fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
+    pub fn read_delivered(&self) -> Option<(P, Signature)> {
         if let (Some(proposal), Some(sig)) = (self.m_bar.clone(), self.u_bar.clone()) {
             Some((proposal, sig))
         } else {
@@ -291,7 +327,7 @@ impl Vcbc {
 
     // send_to sends the message `msg` to the corresponding peer `to`.
     // If the `to` is us, it adds the  message to our messages log.
-    fn send_to(&mut self, msg: self::Message, to: NodeId) -> Result<()> {
+    fn send_to(&mut self, msg: self::Message<P>, to: NodeId) -> Result<()> {
         log::debug!("party {} sends {msg:?} to {}", self.i, to);
 
         let data = bincode::serialize(&msg)?;
@@ -307,7 +343,7 @@ impl Vcbc {
 
     // broadcast sends the message `msg` to all other peers in the network.
     // It adds the message to our messages log.
-    fn broadcast(&mut self, msg: self::Message) -> Result<()> {
+    fn broadcast(&mut self, msg: self::Message<P>) -> Result<()> {
         log::debug!("party {} broadcasts {msg:?}", self.i);
 
         let data = bincode::serialize(&msg)?;
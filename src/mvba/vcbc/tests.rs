@@ -3,28 +3,28 @@ use super::Error;
 use super::{NodeId, Vcbc};
 use crate::mvba::broadcaster::Broadcaster;
 use crate::mvba::bundle::Bundle;
+use crate::mvba::fault::{Fault, Signed};
 use crate::mvba::hash::Hash32;
 use crate::mvba::tag::{Domain, Tag};
 use crate::mvba::vcbc::c_ready_bytes_to_sign;
-use crate::mvba::Proposal;
 use blsttc::{SecretKeySet, Signature, SignatureShare};
 use quickcheck_macros::quickcheck;
 use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::rc::Rc;
 
-fn valid_proposal(_: NodeId, _: &Proposal) -> bool {
+fn valid_proposal(_: NodeId, _: &Vec<u8>) -> bool {
     true
 }
 
-fn invalid_proposal(_: NodeId, _: &Proposal) -> bool {
+fn invalid_proposal(_: NodeId, _: &Vec<u8>) -> bool {
     false
 }
 
 struct Net {
     domain: Domain,
     secret_key_set: SecretKeySet,
-    nodes: BTreeMap<NodeId, Vcbc>,
+    nodes: BTreeMap<NodeId, Vcbc<Vec<u8>>>,
     queue: BTreeMap<NodeId, Vec<Bundle>>,
 }
 
@@ -42,7 +42,7 @@ impl Net {
         let public_key_set = secret_key_set.public_keys();
         let domain = Domain::new("testing-vcbc", 0);
 
-        let nodes = BTreeMap::from_iter((1..=n).into_iter().map(|self_id| {
+        let nodes = BTreeMap::from_iter((1..=n).map(|self_id| {
             let key_share = secret_key_set.secret_key_share(self_id);
             let broadcaster = Rc::new(RefCell::new(Broadcaster::new(self_id)));
             let tag = Tag::new(domain.clone(), proposer);
@@ -51,7 +51,7 @@ impl Net {
                 self_id,
                 public_key_set.clone(),
                 key_share,
-                valid_proposal,
+                Rc::new(valid_proposal),
                 broadcaster,
             );
             (self_id, vcbc)
@@ -65,7 +65,7 @@ impl Net {
         }
     }
 
-    fn node_mut(&mut self, id: NodeId) -> &mut Vcbc {
+    fn node_mut(&mut self, id: NodeId) -> &mut Vcbc<Vec<u8>> {
         self.nodes.get_mut(&id).unwrap()
     }
 
@@ -97,7 +97,7 @@ impl Net {
                 let recipient_node = self.node_mut(recipient);
 
                 for bundle in queue {
-                    let msg: Message = bincode::deserialize(&bundle.payload)
+                    let msg: Message<Vec<u8>> = bincode::deserialize(&bundle.payload)
                         .expect("Failed to deserialize message");
 
                     recipient_node
@@ -118,7 +118,7 @@ impl Net {
             let index = index % msgs.len();
 
             let bundle = msgs.swap_remove(index);
-            let msg: Message =
+            let msg: Message<Vec<u8>> =
                 bincode::deserialize(&bundle.payload).expect("Failed to deserialize message");
 
             let recipient_node = self.node_mut(recipient);
@@ -151,9 +151,11 @@ fn test_vcbc_happy_path() {
 
     // And check that all nodes have delivered the expected value and signature
 
-    let expected_bytes_to_sign: Vec<u8> =
-        c_ready_bytes_to_sign(&tag, &Hash32::calculate("HAPPY-PATH-VALUE"))
-            .expect("Failed to serialize");
+    let expected_bytes_to_sign: Vec<u8> = c_ready_bytes_to_sign(
+        &tag,
+        &Hash32::calculate_serialized(&"HAPPY-PATH-VALUE".as_bytes().to_vec()).unwrap(),
+    )
+    .expect("Failed to serialize");
 
     let expected_sig = net.secret_key_set.secret_key().sign(expected_bytes_to_sign);
 
@@ -196,7 +198,8 @@ fn prop_vcbc_terminates_under_randomized_msg_delivery(
 
     let tag = Tag::new(net.domain.clone(), proposer);
     let expected_bytes_to_sign: Vec<u8> =
-        c_ready_bytes_to_sign(&tag, &Hash32::calculate(&proposal)).expect("Failed to serialize");
+        c_ready_bytes_to_sign(&tag, &Hash32::calculate_serialized(&proposal).unwrap())
+            .expect("Failed to serialize");
 
     let expected_sig = net.secret_key_set.secret_key().sign(expected_bytes_to_sign);
 
@@ -234,7 +237,7 @@ use rand::{thread_rng, Rng};
 
 struct TestNet {
     sec_key_set: SecretKeySet,
-    vcbc: Vcbc,
+    vcbc: Vcbc<Vec<u8>>,
     m: Vec<u8>,
     broadcaster: Rc<RefCell<Broadcaster>>,
 }
@@ -259,7 +262,7 @@ impl TestNet {
             i,
             sec_key_set.public_keys(),
             sec_key_share,
-            valid_proposal,
+            Rc::new(valid_proposal),
             broadcaster.clone(),
         );
 
@@ -274,14 +277,14 @@ impl TestNet {
         }
     }
 
-    pub fn make_send_msg(&self, m: &[u8]) -> Message {
+    pub fn make_send_msg(&self, m: &[u8]) -> Message<Vec<u8>> {
         Message {
             tag: self.vcbc.tag.clone(),
             action: Action::Send(m.to_vec()),
         }
     }
 
-    pub fn make_ready_msg(&self, d: &Hash32, peer_id: &NodeId) -> Message {
+    pub fn make_ready_msg(&self, d: &Hash32, peer_id: &NodeId) -> Message<Vec<u8>> {
         let sig_share = self.sig_share(d, peer_id);
         Message {
             tag: self.vcbc.tag.clone(),
@@ -289,20 +292,20 @@ impl TestNet {
         }
     }
 
-    pub fn make_final_msg(&self, d: &Hash32) -> Message {
+    pub fn make_final_msg(&self, d: &Hash32) -> Message<Vec<u8>> {
         Message {
             tag: self.vcbc.tag.clone(),
             action: Action::Final(*d, self.u()),
         }
     }
 
-    pub fn is_broadcasted(&self, msg: &Message) -> bool {
+    pub fn is_broadcasted(&self, msg: &Message<Vec<u8>>) -> bool {
         self.broadcaster
             .borrow()
             .has_gossip_message(&bincode::serialize(msg).unwrap())
     }
 
-    pub fn is_send_to(&self, to: &NodeId, msg: &Message) -> bool {
+    pub fn is_send_to(&self, to: &NodeId, msg: &Message<Vec<u8>>) -> bool {
         self.broadcaster
             .borrow()
             .has_direct_message(to, &bincode::serialize(msg).unwrap())
@@ -315,7 +318,7 @@ impl TestNet {
 
     // d is same as proposal's digest
     pub fn d(&self) -> Hash32 {
-        Hash32::calculate(&self.m)
+        Hash32::calculate_serialized(&self.m).unwrap()
     }
 
     // u is same as final signature
@@ -337,7 +340,7 @@ fn test_invalid_message() {
     let i = TestNet::PARTY_X;
     let j = TestNet::PARTY_B;
     let mut t = TestNet::new(i, j);
-    t.vcbc.message_validity = invalid_proposal;
+    t.vcbc.message_validity = Rc::new(invalid_proposal);
 
     let msg = t.make_send_msg(&t.m);
 
@@ -439,6 +442,51 @@ fn test_invalid_digest() {
         .is_err());
 }
 
+#[test]
+fn test_double_ready_is_a_fault() {
+    let i = TestNet::PARTY_X;
+    let j = TestNet::PARTY_X;
+    let mut t = TestNet::new(i, j);
+
+    t.vcbc.c_broadcast(t.m.clone()).unwrap();
+
+    let ready_msg_b = t.make_ready_msg(&t.d(), &TestNet::PARTY_B);
+    t.vcbc
+        .receive_message(TestNet::PARTY_B, ready_msg_b)
+        .unwrap();
+
+    let other_digest = Hash32::calculate("other-data");
+    let other_ready_msg_b = t.make_ready_msg(&other_digest, &TestNet::PARTY_B);
+    assert!(t
+        .vcbc
+        .receive_message(TestNet::PARTY_B, other_ready_msg_b)
+        .is_err());
+
+    let expected_fault = Fault::DoubleReady {
+        party: TestNet::PARTY_B,
+        tag: t.vcbc.tag.clone(),
+        a: Signed {
+            value: t.d(),
+            sig_share: t.sig_share(&t.d(), &TestNet::PARTY_B),
+        },
+        b: Signed {
+            value: other_digest,
+            sig_share: t.sig_share(&other_digest, &TestNet::PARTY_B),
+        },
+    };
+    assert_eq!(t.vcbc.faults(), std::slice::from_ref(&expected_fault));
+    assert!(expected_fault
+        .validate(&t.sec_key_set.public_keys())
+        .is_ok());
+
+    // the same party can't be blamed with the shares of another party
+    let mut forged_fault = expected_fault;
+    if let Fault::DoubleReady { party, .. } = &mut forged_fault {
+        *party = TestNet::PARTY_Y;
+    }
+    assert!(forged_fault.validate(&t.sec_key_set.public_keys()).is_err());
+}
+
 #[test]
 fn test_invalid_sig_share() {
     let i = TestNet::PARTY_X;
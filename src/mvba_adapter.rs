@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use blsttc::{PublicKeySet, Signature};
+use log::info;
+use serde::de::DeserializeOwned;
+
+use crate::consensus::{Consensus, VoteResponse};
+use crate::mvba::bundle::{Bundle, Outgoing};
+use crate::mvba::consensus::Consensus as MvbaConsensus;
+use crate::mvba::tag::Domain;
+use crate::mvba::MessageValidity;
+use crate::sn_membership::{Generation, Reconfig};
+use crate::{Error, Handover, HandoverChain, Membership, NodeId, Proposition, Result, SignedVote};
+
+/// An application agreeing on its proposals with the MVBA engine, i.e. the external
+/// application that determines the validity predicate `Q_ID` of the MVBA spec.
+///
+/// The elders of the application's consensus are the parties of the agreement, MVBA
+/// is built on blsttc so only applications using blsttc keys can be adapted.
+///
+/// The agreed proposal is certified by the vote based engine of the application, every
+/// elder proposes it once MVBA agrees on it. It's the only proposal of the honest elders
+/// so it's decided alone, with the votes and the certificate of any other decision.
+pub trait MvbaApplication {
+    type Proposal: Proposition + DeserializeOwned;
+
+    /// Keeps the MVBA instances of different applications apart.
+    const DOMAIN: &'static str;
+
+    /// The generation the proposals are agreed on for.
+    fn next_gen(&self) -> Generation;
+
+    fn consensus(&self) -> &Consensus<Self::Proposal, PublicKeySet>;
+
+    /// Validates a proposal for `gen`, `gen` may have been decided by the time the
+    /// proposal reaches us.
+    fn validate(&self, gen: Generation, proposal: &Self::Proposal) -> Result<()>;
+
+    /// Proposes the agreed proposal on the vote based engine.
+    fn propose(&mut self, proposal: Self::Proposal) -> Result<SignedVote<Self::Proposal>>;
+
+    fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<Self::Proposal>,
+    ) -> Result<VoteResponse<Self::Proposal>>;
+}
+
+impl<T: Proposition + DeserializeOwned> MvbaApplication for Membership<T, PublicKeySet> {
+    type Proposal = Reconfig<T, PublicKeySet>;
+
+    const DOMAIN: &'static str = "membership";
+
+    fn next_gen(&self) -> Generation {
+        self.gen + 1
+    }
+
+    fn consensus(&self) -> &Consensus<Self::Proposal, PublicKeySet> {
+        &self.consensus
+    }
+
+    fn validate(&self, gen: Generation, reconfig: &Self::Proposal) -> Result<()> {
+        self.validate_reconfig(reconfig.clone(), gen)
+    }
+
+    fn propose(&mut self, reconfig: Self::Proposal) -> Result<SignedVote<Self::Proposal>> {
+        Membership::propose(self, reconfig)
+    }
+
+    fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<Self::Proposal>,
+    ) -> Result<VoteResponse<Self::Proposal>> {
+        Membership::handle_signed_vote(self, signed_vote)
+    }
+}
+
+impl<T: Proposition + DeserializeOwned> MvbaApplication for Handover<T, PublicKeySet> {
+    type Proposal = T;
+
+    const DOMAIN: &'static str = "handover";
+
+    fn next_gen(&self) -> Generation {
+        self.gen
+    }
+
+    fn consensus(&self) -> &Consensus<Self::Proposal, PublicKeySet> {
+        &self.consensus
+    }
+
+    fn validate(&self, _gen: Generation, proposal: &Self::Proposal) -> Result<()> {
+        self.validate_proposal(proposal.clone())
+    }
+
+    fn propose(&mut self, proposal: Self::Proposal) -> Result<SignedVote<Self::Proposal>> {
+        Handover::propose(self, proposal)
+    }
+
+    fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<Self::Proposal>,
+    ) -> Result<VoteResponse<Self::Proposal>> {
+        Handover::handle_signed_vote(self, signed_vote)
+    }
+}
+
+impl<T: Proposition + DeserializeOwned> MvbaApplication for HandoverChain<T, PublicKeySet> {
+    type Proposal = T;
+
+    const DOMAIN: &'static str = "handover";
+
+    fn next_gen(&self) -> Generation {
+        self.gen()
+    }
+
+    fn consensus(&self) -> &Consensus<Self::Proposal, PublicKeySet> {
+        &self.handover.consensus
+    }
+
+    fn validate(&self, _gen: Generation, proposal: &Self::Proposal) -> Result<()> {
+        self.handover.validate_proposal(proposal.clone())
+    }
+
+    fn propose(&mut self, proposal: Self::Proposal) -> Result<SignedVote<Self::Proposal>> {
+        HandoverChain::propose(self, proposal)
+    }
+
+    fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<Self::Proposal>,
+    ) -> Result<VoteResponse<Self::Proposal>> {
+        HandoverChain::handle_signed_vote(self, signed_vote)
+    }
+}
+
+/// The outcome of an MVBA agreement, MVBA agrees on the proposal of a single elder
+/// rather than on a set of proposals like `Decision` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MvbaDecision<T> {
+    pub gen: Generation,
+    /// The elder whose proposal was agreed on.
+    pub proposer: NodeId,
+    pub proposal: T,
+    /// The `c-final` signature of the proposer's broadcast on the digest of the proposal.
+    pub proposal_sig: Signature,
+}
+
+/// Runs the agreement of a single generation of an application on the MVBA engine.
+///
+/// Every proposal we receive is validated by the application before we help
+/// broadcast it, once the agreement is reached we propose the agreed proposal on the
+/// vote based engine of the application to certify it.
+/// The votes of the generation must be handed to the adapter rather than to the
+/// application, they are held back until we've voted for the agreed proposal so that
+/// we can't be led to vote for anything else.
+/// A new adapter is needed for every generation.
+pub struct MvbaAdapter<A: MvbaApplication> {
+    pub app: Rc<RefCell<A>>,
+    pub gen: Generation,
+    consensus: MvbaConsensus<A::Proposal>,
+    decision: Option<MvbaDecision<A::Proposal>>,
+    pending_votes: Vec<SignedVote<A::Proposal>>,
+    vote: Option<SignedVote<A::Proposal>>,
+}
+
+impl<A: MvbaApplication + 'static> MvbaAdapter<A> {
+    pub fn new(app: Rc<RefCell<A>>, parties: impl IntoIterator<Item = NodeId>) -> Self {
+        let (gen, (id, secret_key_share), elders) = {
+            let app = app.borrow();
+            let consensus = app.consensus();
+            (
+                app.next_gen(),
+                consensus.secret_key.clone(),
+                consensus.elders.clone(),
+            )
+        };
+
+        let validity_app = app.clone();
+        let message_validity: MessageValidity<A::Proposal> = Rc::new(move |proposer, proposal| {
+            let valid = validity_app.borrow().validate(gen, proposal);
+            if let Err(err) = &valid {
+                info!("[MVBA] rejecting proposal from {proposer}: {err}");
+            }
+            valid.is_ok()
+        });
+
+        let consensus = MvbaConsensus::init(
+            Domain::new(A::DOMAIN, gen as usize),
+            id as usize,
+            secret_key_share,
+            elders,
+            Vec::from_iter(parties.into_iter().map(usize::from)),
+            message_validity,
+        );
+
+        Self {
+            app,
+            gen,
+            consensus,
+            decision: None,
+            pending_votes: Vec::new(),
+            vote: None,
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.app.borrow().consensus().id()
+    }
+
+    pub fn propose(&mut self, proposal: A::Proposal) -> Result<Vec<Outgoing>> {
+        self.app.borrow().validate(self.gen, &proposal)?;
+        self.consensus.propose(proposal).map_err(Error::Mvba)
+    }
+
+    pub fn handle_bundle(&mut self, bundle: &Bundle) -> Result<Vec<Outgoing>> {
+        let outgoing = self.consensus.process_bundle(bundle).map_err(Error::Mvba)?;
+
+        if self.decision.is_none() {
+            if let Some(decision) = self.consensus.decision() {
+                let decision = MvbaDecision {
+                    gen: self.gen,
+                    proposer: decision.proposer as NodeId,
+                    proposal: decision.proposal.clone(),
+                    proposal_sig: decision.proposal_sig.clone(),
+                };
+                self.certify(&decision)?;
+                self.decision = Some(decision);
+            }
+        }
+
+        Ok(outgoing)
+    }
+
+    /// Hands a vote of the vote based engine to the application, votes are held back
+    /// until we've voted for the agreed proposal.
+    pub fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<A::Proposal>,
+    ) -> Result<VoteResponse<A::Proposal>> {
+        if self.decision.is_none() {
+            self.pending_votes.push(signed_vote);
+            return Ok(VoteResponse::WaitingForMoreVotes);
+        }
+        self.app.borrow_mut().handle_signed_vote(signed_vote)
+    }
+
+    /// Our vote for the agreed proposal, or our latest vote in response to the votes
+    /// that were held back until then. It's to be broadcast to the other elders.
+    pub fn take_vote(&mut self) -> Option<SignedVote<A::Proposal>> {
+        self.vote.take()
+    }
+
+    pub fn decision(&self) -> Option<&MvbaDecision<A::Proposal>> {
+        self.decision.as_ref()
+    }
+
+    fn certify(&mut self, decision: &MvbaDecision<A::Proposal>) -> Result<()> {
+        let mut app = self.app.borrow_mut();
+        let id = app.consensus().id();
+
+        // we may have followed the others to the decision already
+        if app.next_gen() == self.gen && !app.consensus().votes.contains_key(&id) {
+            self.vote = Some(app.propose(decision.proposal.clone())?);
+        }
+
+        for signed_vote in std::mem::take(&mut self.pending_votes) {
+            match app.handle_signed_vote(signed_vote) {
+                Ok(VoteResponse::Broadcast(vote)) => self.vote = Some(vote),
+                Ok(VoteResponse::WaitingForMoreVotes) => (),
+                Err(err) => info!("[MVBA] {id} dropping held back vote: {err}"),
+            }
+        }
+        Ok(())
+    }
+}
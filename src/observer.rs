@@ -0,0 +1,48 @@
+use core::fmt::Debug;
+
+use crate::shared::Shared;
+use crate::sn_membership::Generation;
+use crate::NodeId;
+
+/// A state transition of a consensus instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusEvent<T> {
+    /// A valid vote from `voter` was processed.
+    VoteReceived { gen: Generation, voter: NodeId },
+    /// `voter` was proven faulty, either by us or by the evidence of another elder.
+    FaultDetected { gen: Generation, voter: NodeId },
+    /// The votes are split, no candidate can reach a super majority anymore.
+    SplitVote { gen: Generation },
+    /// We cast a merge vote, `round` counts the merges we cast in this generation.
+    MergeCast { gen: Generation, round: usize },
+    /// We cast a super majority vote.
+    SuperMajorityCast { gen: Generation },
+    /// We decided on `proposals`.
+    Decided { gen: Generation, proposals: Vec<T> },
+    /// We moved on to voting on `gen`.
+    GenerationAdvanced { gen: Generation },
+}
+
+/// Gets notified of the state transitions of a consensus instance, e.g. to drive
+/// metrics or a debugger.
+///
+/// Observers are called synchronously while a vote is being handled, they should
+/// return quickly.
+pub trait ConsensusObserver<T>: Debug {
+    fn observe(&self, event: &ConsensusEvent<T>);
+}
+
+/// Records events in memory.
+pub type MemoryEventLog<T> = Shared<Vec<ConsensusEvent<T>>>;
+
+impl<T: Clone> MemoryEventLog<T> {
+    pub fn events(&self) -> Vec<ConsensusEvent<T>> {
+        self.lock().clone()
+    }
+}
+
+impl<T: Clone + Debug> ConsensusObserver<T> for MemoryEventLog<T> {
+    fn observe(&self, event: &ConsensusEvent<T>) {
+        self.lock().push(event.clone());
+    }
+}
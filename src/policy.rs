@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+use core::fmt::Debug;
+use thiserror::Error;
+
+use crate::sn_membership::{Generation, Reconfig};
+use crate::{Proposition, PublicKeySet, SignatureScheme};
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("We can not accept any new join requests, network member size is at capacity ({max_members})")]
+    MembersAtCapacity { max_members: usize },
+    #[error("Reconfig was rejected by the membership policy: {0}")]
+    Rejected(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Admission rules applied to every `Reconfig` on top of the basic membership checks,
+/// i.e. that joining actors are not members yet and leaving actors are.
+pub trait MembershipPolicy<T: Proposition, S: SignatureScheme = PublicKeySet>: Debug {
+    /// Checks whether `reconfig` may be applied to `members`.
+    ///
+    /// When proposals are validated `members` is the member set at `gen - 1`, when a decision
+    /// is applied it also includes the reconfigs of that decision that were applied before.
+    fn validate(
+        &self,
+        reconfig: &Reconfig<T, S>,
+        members: &BTreeSet<T>,
+        gen: Generation,
+    ) -> Result<(), PolicyError>;
+}
+
+/// Caps the number of members in a section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityPolicy {
+    pub max_members: usize,
+}
+
+impl Default for CapacityPolicy {
+    fn default() -> Self {
+        Self { max_members: 7 }
+    }
+}
+
+impl<T: Proposition, S: SignatureScheme> MembershipPolicy<T, S> for CapacityPolicy {
+    fn validate(
+        &self,
+        reconfig: &Reconfig<T, S>,
+        members: &BTreeSet<T>,
+        _gen: Generation,
+    ) -> Result<(), PolicyError> {
+        match reconfig {
+            Reconfig::Join(_) if members.len() >= self.max_members => {
+                Err(PolicyError::MembersAtCapacity {
+                    max_members: self.max_members,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
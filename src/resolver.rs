@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use core::fmt::Debug;
+
+use crate::vote::Ballot;
+use crate::{Decision, NodeId, Proposition, PublicKeySet, SignatureScheme, UniqueSignature};
+
+/// Picks the single value a `Handover` settles on out of the decided proposals.
+///
+/// Every elder resolves the decision on its own, so a resolver must be deterministic
+/// and only depend on what all elders agree on, i.e. the decided proposals and votes.
+pub trait Resolver<T: Proposition, S: SignatureScheme = PublicKeySet>: Debug {
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T>;
+}
+
+/// Picks the largest proposal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaxResolver;
+
+impl<T: Proposition, S: SignatureScheme> Resolver<T, S> for MaxResolver {
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T> {
+        decision.proposals.keys().max().cloned()
+    }
+}
+
+/// Picks the smallest proposal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MinResolver;
+
+impl<T: Proposition, S: SignatureScheme> Resolver<T, S> for MinResolver {
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T> {
+        decision.proposals.keys().min().cloned()
+    }
+}
+
+/// Picks a proposal pseudo-randomly, seeded by the hash of the section signatures over
+/// the decided proposals. Unlike `MaxResolver`, an elder can't win by simply proposing
+/// the largest value, nor by grinding proposals since no one knows the signatures
+/// before the decision is reached.
+///
+/// Every elder must derive the same seed, so this resolver is only available for
+/// schemes whose section signatures are unique.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SeededRandomResolver;
+
+impl<T: Proposition, S: UniqueSignature> Resolver<T, S> for SeededRandomResolver {
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T> {
+        use tiny_keccak::{Hasher, Sha3};
+
+        if decision.proposals.is_empty() {
+            return None;
+        }
+
+        let proposals = Vec::from_iter(decision.proposals.keys());
+        let signatures = Vec::from_iter(decision.proposals.values());
+        let bytes = bincode::serialize(&signatures).ok()?;
+        let mut sha3 = Sha3::v256();
+        let mut hash = [0u8; 32];
+        sha3.update(&bytes);
+        sha3.finalize(&mut hash);
+
+        let seed = u64::from_le_bytes(hash[..8].try_into().ok()?);
+        let index = (seed % proposals.len() as u64) as usize;
+        Some(proposals[index].clone())
+    }
+}
+
+/// Picks the proposal that was proposed by the most elders, ties go to the largest proposal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MostSupportedResolver;
+
+impl<T: Proposition, S: SignatureScheme> Resolver<T, S> for MostSupportedResolver {
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T> {
+        let faulty = decision.faulty_ids();
+        let proposers: BTreeMap<NodeId, &T> = decision
+            .votes
+            .iter()
+            .flat_map(|vote| vote.unpack_votes())
+            .filter(|vote| !faulty.contains(&vote.voter))
+            .filter_map(|vote| match &vote.vote.ballot {
+                Ballot::Propose(proposal) => Some((vote.voter, proposal)),
+                _ => None,
+            })
+            .collect();
+
+        let mut support: BTreeMap<&T, usize> = BTreeMap::new();
+        for proposal in proposers.into_values() {
+            if decision.proposals.contains_key(proposal) {
+                *support.entry(proposal).or_default() += 1;
+            }
+        }
+
+        support
+            .into_iter()
+            .max_by_key(|(proposal, count)| (*count, *proposal))
+            .map(|(proposal, _)| proposal.clone())
+    }
+}
+
+/// Resolves decisions with a user provided function.
+#[derive(Clone, Copy)]
+pub struct FnResolver<F>(pub F);
+
+impl_fn_debug!(FnResolver);
+
+impl<T, S, F> Resolver<T, S> for FnResolver<F>
+where
+    T: Proposition,
+    S: SignatureScheme,
+    F: Fn(&Decision<T, S>) -> Option<T>,
+{
+    fn resolve(&self, decision: &Decision<T, S>) -> Option<T> {
+        (self.0)(decision)
+    }
+}
@@ -0,0 +1,18 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A value kept in memory behind a handle, clones share the same underlying value
+/// so a handle can outlive the consensus instance it was given to.
+#[derive(Debug, Default)]
+pub struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Shared<T> {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap()
+    }
+}
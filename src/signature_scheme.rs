@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use core::fmt::Debug;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{NodeId, Result};
+
+/// The signature scheme used by elders to sign and verify votes.
+///
+/// A scheme is implemented on the type describing the set of voters (e.g. blsttc's
+/// `PublicKeySet`), this way the scheme in use is inferred from the elders a
+/// `Consensus` is created with.
+pub trait SignatureScheme:
+    Debug + Clone + PartialEq + Eq + PartialOrd + Ord + Hash + Serialize
+{
+    /// The key combined signatures are verified with, i.e. the section key.
+    type PublicKey: Debug + Clone + PartialEq + Eq + Serialize + DeserializeOwned;
+
+    /// The secret key a single voter signs votes with.
+    type SecretKeyShare: Debug + Clone + PartialEq + Eq;
+
+    /// A signature produced by a single voter.
+    type SignatureShare: Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned;
+
+    /// A signature combined from the signature shares of a super majority of voters.
+    type Signature: Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + Hash
+        + Serialize
+        + DeserializeOwned;
+
+    fn public_key(&self) -> Self::PublicKey;
+
+    fn sign(secret_key: &Self::SecretKeyShare, msg: &[u8]) -> Self::SignatureShare;
+
+    fn verify_share(&self, voter: NodeId, msg: &[u8], sig: &Self::SignatureShare) -> bool;
+
+    /// A super majority is reached once strictly more than `threshold()` voters agree.
+    fn threshold(&self) -> usize;
+
+    fn combine_signatures(
+        &self,
+        shares: &BTreeMap<NodeId, Self::SignatureShare>,
+    ) -> Result<Self::Signature>;
+
+    fn verify(public_key: &Self::PublicKey, msg: &[u8], sig: &Self::Signature) -> bool;
+}
+
+/// A scheme whose combined signature over a message is the same no matter which
+/// voters' shares were combined, e.g. blsttc's threshold signatures.
+/// The multi-signatures of the `bad_crypto` and `ed25519` backends are not, they
+/// depend on which voters signed.
+pub trait UniqueSignature: SignatureScheme {}
@@ -1,35 +1,54 @@
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use blsttc::{PublicKeySet, SecretKeyShare, Signature};
 use core::fmt::Debug;
 use log::info;
 
-use crate::consensus::{Consensus, VoteResponse};
+use crate::consensus::{Consensus, TimeoutResponse, VoteDigest, VoteResponse};
+use crate::observer::{ConsensusEvent, ConsensusObserver};
+use crate::resolver::Resolver;
+use crate::validator::ProposalValidator;
 use crate::vote::{Ballot, Proposition, SignedVote, Vote};
-use crate::{Error, NodeId, Result};
+use crate::{Error, NodeId, PublicKeySet, Result, SignatureScheme};
 
 pub type UniqueSectionId = u64;
 
-#[derive(Debug)]
-pub struct Handover<T: Proposition> {
-    pub consensus: Consensus<T>,
+#[derive(Debug, Clone)]
+pub struct Handover<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub consensus: Consensus<T, S>,
     pub gen: UniqueSectionId,
+    /// Handles are shared by clones and ignored when comparing instances.
+    pub resolver: Arc<dyn Resolver<T, S> + Send + Sync>,
+    pub validator: Arc<dyn ProposalValidator<T> + Send + Sync>,
 }
 
-impl<T: Proposition> Handover<T> {
+impl<T: Proposition, S: SignatureScheme> PartialEq for Handover<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.consensus == other.consensus && self.gen == other.gen
+    }
+}
+
+impl<T: Proposition, S: SignatureScheme> Eq for Handover<T, S> {}
+
+impl<T: Proposition, S: SignatureScheme> Handover<T, S> {
     pub fn from(
-        secret_key: (NodeId, SecretKeyShare),
-        elders: PublicKeySet,
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
         n_elders: usize,
         gen: UniqueSectionId,
+        resolver: impl Resolver<T, S> + Send + Sync + 'static,
+        validator: impl ProposalValidator<T> + Send + Sync + 'static,
     ) -> Self {
-        Handover::<T> {
-            consensus: Consensus::<T>::from(secret_key, elders, n_elders),
+        Handover::<T, S> {
+            consensus: Consensus::<T, S>::from(secret_key, elders, n_elders),
             gen,
+            resolver: Arc::new(resolver),
+            validator: Arc::new(validator),
         }
     }
 
-    pub fn propose(&mut self, proposal: T) -> Result<SignedVote<T>> {
+    pub fn propose(&mut self, proposal: T) -> Result<SignedVote<T, S>> {
         let vote = Vote {
             gen: self.gen,
             ballot: Ballot::Propose(proposal),
@@ -48,46 +67,93 @@ impl<T: Proposition> Handover<T> {
     }
 
     // Get someone up to speed on our view of the current votes
-    pub fn anti_entropy(&self) -> Result<Vec<SignedVote<T>>> {
+    pub fn anti_entropy(&self) -> Result<Vec<SignedVote<T, S>>> {
         info!("[HDVR] anti-entropy from {:?}", self.id());
 
-        if let Some(decision) = self.consensus.decision.as_ref() {
-            let vote = self.consensus.build_super_majority_vote(
-                decision.votes.clone(),
-                decision.faults.clone(),
-                self.gen,
-            )?;
-            Ok(vec![vote])
+        if let Some(vote) = self.consensus.decision_vote.as_ref() {
+            Ok(vec![vote.clone()])
         } else {
             Ok(self.consensus.votes.values().cloned().collect())
         }
     }
 
-    pub fn resolve_votes<'a>(&self, proposals: &'a BTreeMap<T, Signature>) -> Option<&'a T> {
-        // we need to choose one deterministically
-        // proposals are comparable because they impl Ord so we arbitrarily pick the max
-        proposals.keys().max()
+    /// A digest of the votes we've seen, for peers to send us only what we're missing.
+    pub fn digest(&self) -> VoteDigest<S> {
+        self.consensus.digest(self.gen)
+    }
+
+    /// Get someone up to speed given their digest, only the votes they are missing are returned.
+    pub fn missing_votes(&self, digest: &VoteDigest<S>) -> Result<Vec<SignedVote<T, S>>> {
+        info!("[HDVR] anti-entropy for digest from {:?}", self.id());
+        if digest.gen != self.gen {
+            return Err(Error::BadGeneration {
+                requested_gen: digest.gen,
+                gen: self.gen,
+            });
+        }
+        self.consensus.missing_votes(digest)
+    }
+
+    /// Drives the timeout of our consensus, see `Consensus::tick`.
+    pub fn tick(&mut self, now: Duration) -> Option<TimeoutResponse<T, S>> {
+        self.consensus.tick(now)
+    }
+
+    pub fn on_timeout(&mut self) -> Option<TimeoutResponse<T, S>> {
+        self.consensus.on_timeout()
+    }
+
+    /// Resolves our decision to a single value, elders can audit a recorded
+    /// `resolution` by checking that it matches this.
+    pub fn resolve(&self) -> Option<T> {
+        self.consensus
+            .decision
+            .as_ref()
+            .and_then(|decision| self.resolver.resolve(decision))
+    }
+
+    /// The value our decision resolved to, recorded along with the decision.
+    pub fn resolution(&self) -> Option<&T> {
+        self.consensus.resolution.as_ref()
+    }
+
+    pub(crate) fn record_resolution(&mut self) {
+        if self.consensus.resolution.is_none() {
+            self.consensus.resolution = self.resolve();
+        }
     }
 
     pub fn id(&self) -> NodeId {
         self.consensus.id()
     }
 
-    pub fn handle_signed_vote(&mut self, signed_vote: SignedVote<T>) -> Result<VoteResponse<T>> {
+    /// Notifies `observer` of the state transitions of our consensus from now on.
+    pub fn set_observer(&mut self, observer: impl ConsensusObserver<T> + Send + Sync + 'static) {
+        self.consensus.set_observer(observer);
+    }
+
+    pub fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<T, S>,
+    ) -> Result<VoteResponse<T, S>> {
         self.validate_proposals(&signed_vote)?;
 
-        self.consensus.handle_signed_vote(signed_vote)
+        let vote_response = self.consensus.handle_signed_vote(signed_vote)?;
+        self.record_resolution();
+        Ok(vote_response)
     }
 
-    pub fn sign_vote(&self, vote: Vote<T>) -> Result<SignedVote<T>> {
+    pub fn sign_vote(&self, vote: Vote<T, S>) -> Result<SignedVote<T, S>> {
         self.consensus.sign_vote(vote)
     }
 
-    pub fn cast_vote(&mut self, signed_vote: SignedVote<T>) -> Result<SignedVote<T>> {
-        self.consensus.cast_vote(signed_vote)
+    pub fn cast_vote(&mut self, signed_vote: SignedVote<T, S>) -> Result<SignedVote<T, S>> {
+        let vote = self.consensus.cast_vote(signed_vote)?;
+        self.record_resolution();
+        Ok(vote)
     }
 
-    pub fn validate_proposals(&self, signed_vote: &SignedVote<T>) -> Result<()> {
+    pub fn validate_proposals(&self, signed_vote: &SignedVote<T, S>) -> Result<()> {
         if signed_vote.vote.gen != self.gen {
             return Err(Error::BadGeneration {
                 requested_gen: signed_vote.vote.gen,
@@ -101,8 +167,168 @@ impl<T: Proposition> Handover<T> {
             .try_for_each(|prop| self.validate_proposal(prop))
     }
 
-    // Placeholder for now, may be useful for sn_node
-    pub fn validate_proposal(&self, _proposal: T) -> Result<()> {
-        Ok(())
+    pub fn validate_proposal(&self, proposal: T) -> Result<()> {
+        self.validator
+            .validate(&proposal)
+            .map_err(Error::ProposalRejected)
+    }
+}
+
+/// Drives `Handover`s across generations, the next generation is started as soon as
+/// the current one decides and the decided consensus is kept in `history`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandoverChain<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    /// The handover of the generation currently being voted on.
+    pub handover: Handover<T, S>,
+    pub history: BTreeMap<UniqueSectionId, Consensus<T, S>>,
+}
+
+impl<T: Proposition, S: SignatureScheme> HandoverChain<T, S> {
+    pub fn from(
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
+        n_elders: usize,
+        gen: UniqueSectionId,
+        resolver: impl Resolver<T, S> + Send + Sync + 'static,
+        validator: impl ProposalValidator<T> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handover: Handover::from(secret_key, elders, n_elders, gen, resolver, validator),
+            history: BTreeMap::default(),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.handover.id()
+    }
+
+    /// Notifies `observer` of the state transitions of every generation from now on.
+    pub fn set_observer(&mut self, observer: impl ConsensusObserver<T> + Send + Sync + 'static) {
+        self.handover.set_observer(observer);
+    }
+
+    /// The generation currently being voted on.
+    pub fn gen(&self) -> UniqueSectionId {
+        self.handover.gen
+    }
+
+    /// The value the decision at `gen` resolved to.
+    pub fn resolution(&self, gen: UniqueSectionId) -> Option<&T> {
+        self.consensus_at_gen(gen).ok()?.resolution.as_ref()
+    }
+
+    pub fn consensus_at_gen(&self, gen: UniqueSectionId) -> Result<&Consensus<T, S>> {
+        if gen == self.gen() {
+            Ok(&self.handover.consensus)
+        } else {
+            self.history.get(&gen).ok_or(Error::BadGeneration {
+                requested_gen: gen,
+                gen: self.gen(),
+            })
+        }
+    }
+
+    pub fn propose(&mut self, proposal: T) -> Result<SignedVote<T, S>> {
+        let vote = self.handover.propose(proposal)?;
+        self.advance();
+        Ok(vote)
+    }
+
+    pub fn handle_signed_vote(
+        &mut self,
+        signed_vote: SignedVote<T, S>,
+    ) -> Result<VoteResponse<T, S>> {
+        let vote_gen = signed_vote.vote.gen;
+        if vote_gen == self.gen() {
+            let vote_response = self.handover.handle_signed_vote(signed_vote)?;
+            self.advance();
+            return Ok(vote_response);
+        }
+
+        // a vote for a generation we already decided, e.g. from an elder catching up
+        signed_vote
+            .proposals()
+            .into_iter()
+            .try_for_each(|prop| self.handover.validate_proposal(prop))?;
+        let gen = self.gen();
+        self.history
+            .get_mut(&vote_gen)
+            .ok_or(Error::BadGeneration {
+                requested_gen: vote_gen,
+                gen,
+            })?
+            .handle_signed_vote(signed_vote)
+    }
+
+    /// Get someone voting on `from_gen` up to speed, the decisions from `from_gen`
+    /// onwards are followed by our view of the votes of the current generation.
+    pub fn anti_entropy(&self, from_gen: UniqueSectionId) -> Result<Vec<SignedVote<T, S>>> {
+        info!("[HDVR] anti-entropy from gen {}", from_gen);
+
+        let mut msgs = Vec::from_iter(
+            self.history
+                .range(from_gen..)
+                .filter_map(|(_, c)| c.decision_vote.clone()),
+        );
+
+        msgs.extend(self.handover.anti_entropy()?);
+
+        Ok(msgs)
+    }
+
+    pub fn digest(&self) -> VoteDigest<S> {
+        self.handover.digest()
+    }
+
+    /// Get someone up to speed given their digest, the votes they are missing on the
+    /// generation they are voting on are followed by every decision since.
+    pub fn missing_votes(&self, digest: &VoteDigest<S>) -> Result<Vec<SignedVote<T, S>>> {
+        if digest.gen > self.gen() {
+            // they are ahead of us, there's nothing we can help them with
+            return Ok(Vec::new());
+        }
+
+        let mut msgs = self.consensus_at_gen(digest.gen)?.missing_votes(digest)?;
+        msgs.extend(
+            self.history
+                .range(digest.gen + 1..)
+                .filter_map(|(_, c)| c.decision_vote.clone()),
+        );
+        if digest.gen < self.gen() {
+            msgs.extend(self.handover.consensus.votes.values().cloned());
+        }
+
+        Ok(msgs)
+    }
+
+    /// Drives the timeout of the generation we're voting on, see `Consensus::tick`.
+    pub fn tick(&mut self, now: Duration) -> Option<TimeoutResponse<T, S>> {
+        self.handover.tick(now)
+    }
+
+    pub fn on_timeout(&mut self) -> Option<TimeoutResponse<T, S>> {
+        self.handover.on_timeout()
+    }
+
+    /// Moves on to the next generation once the current one has decided.
+    pub(crate) fn advance(&mut self) {
+        if self.handover.consensus.decision.is_none() {
+            return;
+        }
+
+        let gen = self.gen();
+        let mut next_consensus = Consensus::from(
+            self.handover.consensus.secret_key.clone(),
+            self.handover.consensus.elders.clone(),
+            self.handover.consensus.n_elders,
+        );
+        next_consensus.timeout = self.handover.consensus.timeout;
+        next_consensus.observer = self.handover.consensus.observer.take();
+        let decided_consensus = std::mem::replace(&mut self.handover.consensus, next_consensus);
+        self.history.insert(gen, decided_consensus);
+        self.handover.gen = gen + 1;
+        self.handover
+            .consensus
+            .observe(ConsensusEvent::GenerationAdvanced { gen: gen + 1 });
     }
 }
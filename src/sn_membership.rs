@@ -1,64 +1,282 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
 
-use blsttc::{PublicKeySet, SecretKeyShare};
 use core::fmt::Debug;
-use log::info;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
-use crate::consensus::{Consensus, VoteResponse};
+use crate::consensus::{Consensus, TimeoutResponse, VoteDigest, VoteResponse};
+use crate::observer::{ConsensusEvent, ConsensusObserver};
+use crate::policy::MembershipPolicy;
+use crate::store::{MembershipState, Record, Store, StoreVoteLog};
 use crate::vote::{Ballot, Proposition, SignedVote, Vote};
-use crate::{Error, NodeId, Result};
+use crate::{
+    Decision, DecisionCertificate, Error, Fault, NodeId, PublicKeySet, Result, SignatureScheme,
+};
 
-const SOFT_MAX_MEMBERS: usize = 7;
 pub type Generation = u64;
 
-#[derive(Debug)]
-pub struct Membership<T: Proposition> {
-    pub consensus: Consensus<Reconfig<T>>,
+/// The faults proven against elders, by the generation they were proven in.
+pub type FaultLedger<T, S = PublicKeySet> =
+    BTreeMap<Generation, BTreeMap<NodeId, Fault<Reconfig<T, S>, S>>>;
+
+/// The faults proven against each elder, by the generation they were proven in.
+pub type FaultsByElder<T, S = PublicKeySet> =
+    BTreeMap<NodeId, BTreeMap<Generation, Fault<Reconfig<T, S>, S>>>;
+
+#[derive(Debug, Clone)]
+pub struct Membership<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub consensus: Consensus<Reconfig<T, S>, S>,
+    pub gen: Generation,
+    pub forced_reconfigs: BTreeMap<Generation, BTreeSet<Reconfig<T, S>>>,
+    pub history: BTreeMap<Generation, Consensus<Reconfig<T, S>, S>>,
+    pub checkpoint: Option<Checkpoint<T, S>>,
+    /// Faults proven in decided generations, unlike `history` this is not pruned
+    /// by checkpoints.
+    pub fault_ledger: FaultLedger<T, S>,
+    /// Handles are shared by clones and ignored when comparing instances.
+    pub policy: Arc<dyn MembershipPolicy<T, S> + Send + Sync>,
+    pub store: Option<Arc<dyn Store<T, S> + Send + Sync>>,
+}
+
+impl<T: Proposition, S: SignatureScheme> PartialEq for Membership<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.consensus == other.consensus
+            && self.gen == other.gen
+            && self.forced_reconfigs == other.forced_reconfigs
+            && self.history == other.history
+            && self.checkpoint == other.checkpoint
+            && self.fault_ledger == other.fault_ledger
+    }
+}
+
+impl<T: Proposition, S: SignatureScheme> Eq for Membership<T, S> {}
+
+/// The member set at generation `gen` along with the decision that produced it,
+/// history before a checkpoint is no longer needed and is pruned.
+///
+/// `certificate` proves the decision to anyone holding the section key, the
+/// checkpoint does not rely on the word of the elder that took it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint<T: Proposition, S: SignatureScheme = PublicKeySet> {
     pub gen: Generation,
-    pub forced_reconfigs: BTreeMap<Generation, BTreeSet<Reconfig<T>>>,
-    pub history: BTreeMap<Generation, Consensus<Reconfig<T>>>,
+    pub members: BTreeSet<T>,
+    pub decision: Decision<Reconfig<T, S>, S>,
+    pub certificate: DecisionCertificate<Reconfig<T, S>, S>,
+}
+
+impl<T: Proposition, S: SignatureScheme> Checkpoint<T, S> {
+    pub fn validate(&self, voters: &S) -> Result<()> {
+        self.certificate.verify(&voters.public_key())?;
+        self.decision.validate(voters)?;
+
+        if self.decision.generation()? != self.gen || self.certificate.gen != self.gen {
+            return Err(Error::InvalidDecision);
+        }
+
+        if !self
+            .decision
+            .proposals
+            .keys()
+            .eq(self.certificate.proposals.iter())
+        {
+            return Err(Error::InvalidDecisionCertificate);
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-pub enum Reconfig<T: Proposition> {
+pub enum Reconfig<T: Proposition, S: SignatureScheme = PublicKeySet> {
     Join(T),
     Leave(T),
+    /// Hands over voting to a new set of elders, the generations following the
+    /// decision are voted on by `elders`.
+    Elders {
+        elders: S,
+        n_elders: usize,
+    },
 }
 
-impl<T: Proposition> Debug for Reconfig<T> {
+impl<T: Proposition, S: SignatureScheme> Debug for Reconfig<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Reconfig::Join(a) => write!(f, "J{a:?}"),
             Reconfig::Leave(a) => write!(f, "L{a:?}"),
+            Reconfig::Elders { n_elders, .. } => write!(f, "E{n_elders}"),
         }
     }
 }
 
-impl<T: Proposition> Reconfig<T> {
+impl<T: Proposition, S: SignatureScheme> Reconfig<T, S> {
     fn apply(&self, members: &mut BTreeSet<T>) {
         match self {
-            Reconfig::Join(p) => members.insert(p.clone()),
-            Reconfig::Leave(p) => members.remove(p),
-        };
+            Reconfig::Join(p) => {
+                members.insert(p.clone());
+            }
+            Reconfig::Leave(p) => {
+                members.remove(p);
+            }
+            Reconfig::Elders { .. } => (),
+        }
     }
 }
 
-impl<T: Proposition> Membership<T> {
+impl<T: Proposition, S: SignatureScheme> Membership<T, S> {
     pub fn from(
-        secret_key: (NodeId, SecretKeyShare),
-        elders: PublicKeySet,
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
         n_elders: usize,
+        policy: impl MembershipPolicy<T, S> + Send + Sync + 'static,
     ) -> Self {
         Membership {
             consensus: Consensus::from(secret_key, elders, n_elders),
             gen: 0,
             forced_reconfigs: Default::default(),
             history: BTreeMap::default(),
+            checkpoint: None,
+            fault_ledger: Default::default(),
+            policy: Arc::new(policy),
+            store: None,
+        }
+    }
+
+    /// Restores a `Membership` from the state persisted in `store`, the store is
+    /// then kept to persist any further progress.
+    /// Starts from an empty `Membership` if nothing has been persisted yet, otherwise
+    /// the persisted elders are used and `secret_key` must be our key for the latest ones.
+    pub fn restore(
+        secret_key: (NodeId, S::SecretKeyShare),
+        elders: S,
+        n_elders: usize,
+        policy: impl MembershipPolicy<T, S> + Send + Sync + 'static,
+        store: Arc<dyn Store<T, S> + Send + Sync>,
+    ) -> Result<Self>
+    where
+        T: 'static,
+        S: 'static,
+    {
+        let (state, records) = store.load()?;
+        let mut membership = Membership::from(secret_key.clone(), elders.clone(), n_elders, policy);
+        let snapshot_gen = state.as_ref().map(|state| state.gen);
+
+        if let Some(state) = state {
+            let restore_consensus =
+                |consensus_state| Consensus::restore(secret_key.clone(), consensus_state);
+            membership.gen = state.gen;
+            membership.forced_reconfigs = state.forced_reconfigs;
+            membership.history = state
+                .history
+                .into_iter()
+                .map(|(gen, consensus_state)| (gen, restore_consensus(consensus_state)))
+                .collect();
+            membership.consensus = restore_consensus(state.consensus);
+            membership.checkpoint = state.checkpoint;
+            membership.fault_ledger = state.fault_ledger;
+        }
+
+        // A crash between writing a snapshot and truncating the log leaves records the
+        // snapshot already covers, the votes of decided generations and the reconfigs
+        // forced before the snapshot are skipped. Replaying a reconfig forced in the
+        // generation of the snapshot is harmless, it's applied to the same generation.
+        for record in records {
+            let replayed = match record {
+                Record::Vote(signed_vote) if Some(signed_vote.vote.gen) <= snapshot_gen => continue,
+                Record::ForceJoin { gen, .. } | Record::ForceLeave { gen, .. }
+                    if Some(gen) < snapshot_gen =>
+                {
+                    continue
+                }
+                Record::Vote(signed_vote) => membership.handle_signed_vote(signed_vote).map(|_| ()),
+                Record::ForceJoin { gen, actor } => membership.force_join_at(gen, actor),
+                Record::ForceLeave { gen, actor } => membership.force_leave_at(gen, actor),
+            };
+            if let Err(err) = replayed {
+                warn!("[MBR] skipping a record that failed to replay: {err}");
+            }
+        }
+
+        // Compact the replayed log into a fresh snapshot.
+        store.snapshot(&membership.state())?;
+        membership.consensus.vote_log = Some(Arc::new(StoreVoteLog(store.clone())));
+        membership.store = Some(store);
+
+        Ok(membership)
+    }
+
+    pub fn state(&self) -> MembershipState<T, S> {
+        MembershipState {
+            gen: self.gen,
+            forced_reconfigs: self.forced_reconfigs.clone(),
+            history: self
+                .history
+                .iter()
+                .map(|(gen, consensus)| (*gen, consensus.state()))
+                .collect(),
+            consensus: self.consensus.state(),
+            checkpoint: self.checkpoint.clone(),
+            fault_ledger: self.fault_ledger.clone(),
         }
     }
 
-    pub fn consensus_at_gen(&self, gen: Generation) -> Result<&Consensus<Reconfig<T>>> {
+    fn persist(&self, record: Record<T, S>) -> Result<()> {
+        match self.store.as_ref() {
+            Some(store) => store.append(&record),
+            None => Ok(()),
+        }
+    }
+
+    fn snapshot(&self) -> Result<()> {
+        match self.store.as_ref() {
+            Some(store) => store.snapshot(&self.state()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checkpoints the member set at `gen`, the history from before `gen` is pruned.
+    /// Only past generations can be checkpointed, forced reconfigs may still be
+    /// applied to the current one.
+    pub fn checkpoint(&mut self, gen: Generation) -> Result<()> {
+        self.check_not_pruned(gen)?;
+        if gen >= self.gen {
+            return Err(Error::InvalidGeneration(gen));
+        }
+
+        let (decision, certificate) = self
+            .history
+            .get(&gen)
+            .and_then(|consensus| {
+                Some((consensus.decision.clone()?, consensus.certificate.clone()?))
+            })
+            .ok_or(Error::InvalidGeneration(gen))?;
+        let members = self.members(gen)?;
+
+        self.checkpoint = Some(Checkpoint {
+            gen,
+            members,
+            decision,
+            certificate,
+        });
+        self.history = self.history.split_off(&gen);
+        self.forced_reconfigs = self.forced_reconfigs.split_off(&(gen + 1));
+
+        self.snapshot()
+    }
+
+    fn check_not_pruned(&self, gen: Generation) -> Result<()> {
+        match &self.checkpoint {
+            Some(checkpoint) if gen < checkpoint.gen => Err(Error::PrunedGeneration {
+                requested_gen: gen,
+                checkpoint_gen: checkpoint.gen,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn consensus_at_gen(&self, gen: Generation) -> Result<&Consensus<Reconfig<T, S>, S>> {
+        self.check_not_pruned(gen)?;
         if gen == self.gen + 1 {
             Ok(&self.consensus)
         } else {
@@ -69,7 +287,11 @@ impl<T: Proposition> Membership<T> {
         }
     }
 
-    pub fn consensus_at_gen_mut(&mut self, gen: Generation) -> Result<&mut Consensus<Reconfig<T>>> {
+    pub fn consensus_at_gen_mut(
+        &mut self,
+        gen: Generation,
+    ) -> Result<&mut Consensus<Reconfig<T, S>, S>> {
+        self.check_not_pruned(gen)?;
         if gen == self.gen + 1 {
             Ok(&mut self.consensus)
         } else {
@@ -80,37 +302,64 @@ impl<T: Proposition> Membership<T> {
         }
     }
 
-    pub fn force_join(&mut self, actor: T) {
-        let forced_reconfigs = self.forced_reconfigs.entry(self.gen).or_default();
+    pub fn force_join(&mut self, actor: T) -> Result<()> {
+        self.force_join_at(self.gen, actor)
+    }
+
+    pub fn force_leave(&mut self, actor: T) -> Result<()> {
+        self.force_leave_at(self.gen, actor)
+    }
+
+    fn force_join_at(&mut self, gen: Generation, actor: T) -> Result<()> {
+        self.persist(Record::ForceJoin {
+            gen,
+            actor: actor.clone(),
+        })?;
+        let forced_reconfigs = self.forced_reconfigs.entry(gen).or_default();
 
         // remove any leave reconfigs for this actor
         forced_reconfigs.remove(&Reconfig::Leave(actor.clone()));
         forced_reconfigs.insert(Reconfig::Join(actor));
+        Ok(())
     }
 
-    pub fn force_leave(&mut self, actor: T) {
-        let forced_reconfigs = self.forced_reconfigs.entry(self.gen).or_default();
+    fn force_leave_at(&mut self, gen: Generation, actor: T) -> Result<()> {
+        self.persist(Record::ForceLeave {
+            gen,
+            actor: actor.clone(),
+        })?;
+        let forced_reconfigs = self.forced_reconfigs.entry(gen).or_default();
 
         // remove any leave reconfigs for this actor
         forced_reconfigs.remove(&Reconfig::Join(actor.clone()));
         forced_reconfigs.insert(Reconfig::Leave(actor));
+        Ok(())
     }
 
     pub fn members(&self, gen: Generation) -> Result<BTreeSet<T>> {
-        let mut members = BTreeSet::new();
+        self.check_not_pruned(gen)?;
 
-        self.forced_reconfigs
-            .get(&0) // forced reconfigs at generation 0
-            .cloned()
-            .unwrap_or_default()
-            .into_iter()
-            .for_each(|r| r.apply(&mut members));
+        let (mut members, start_gen) = match &self.checkpoint {
+            Some(checkpoint) => (checkpoint.members.clone(), checkpoint.gen),
+            None => {
+                let mut members = BTreeSet::new();
 
-        if gen == 0 {
+                self.forced_reconfigs
+                    .get(&0) // forced reconfigs at generation 0
+                    .cloned()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .for_each(|r| r.apply(&mut members));
+
+                (members, 0)
+            }
+        };
+
+        if gen == start_gen {
             return Ok(members);
         }
 
-        for (history_gen, consensus) in self.history.iter() {
+        for (history_gen, consensus) in self.history.range(start_gen + 1..) {
             self.forced_reconfigs
                 .get(history_gen)
                 .cloned()
@@ -124,7 +373,7 @@ impl<T: Proposition> Membership<T> {
                 panic!("historical consensus entry without decision {history_gen}: {consensus:?}");
             };
 
-            for (reconfig, _sig) in decision.proposals.iter() {
+            for reconfig in self.trim_reconfigs(&members, decision.proposals.keys(), *history_gen) {
                 reconfig.apply(&mut members);
             }
 
@@ -136,7 +385,65 @@ impl<T: Proposition> Membership<T> {
         Err(Error::InvalidGeneration(gen))
     }
 
-    pub fn propose(&mut self, reconfig: Reconfig<T>) -> Result<SignedVote<Reconfig<T>>> {
+    /// The reconfigs that were applied to the members at `gen`.
+    ///
+    /// This may be a subset of the decided proposals, see `trim_reconfigs`.
+    pub fn decided_reconfigs(&self, gen: Generation) -> Result<BTreeSet<Reconfig<T, S>>> {
+        if gen == 0 {
+            return Err(Error::InvalidGeneration(gen));
+        }
+        let mut members = self.members(gen - 1)?;
+        let decision = self
+            .consensus_at_gen(gen)?
+            .decision
+            .as_ref()
+            .ok_or(Error::InvalidGeneration(gen))?;
+
+        self.forced_reconfigs
+            .get(&gen)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .for_each(|r| r.apply(&mut members));
+
+        Ok(self.trim_reconfigs(&members, decision.proposals.keys(), gen))
+    }
+
+    /// Each proposal of a decision is validated against the members at `gen - 1` on its own,
+    /// so the batch as a whole may not satisfy the policy, e.g. too many joins to stay within
+    /// capacity. Reconfigs are applied in order and those that are no longer valid given the
+    /// reconfigs before them are dropped. Every elder trims the same way, so they all agree
+    /// on the resulting members.
+    fn trim_reconfigs<'a>(
+        &self,
+        members: &BTreeSet<T>,
+        reconfigs: impl IntoIterator<Item = &'a Reconfig<T, S>>,
+        gen: Generation,
+    ) -> BTreeSet<Reconfig<T, S>>
+    where
+        T: 'a,
+        S: 'a,
+    {
+        let mut members = members.clone();
+        let mut applied = BTreeSet::new();
+        let mut elders_rotated = false;
+        for reconfig in reconfigs {
+            if let Reconfig::Elders { .. } = reconfig {
+                // only one elder rotation may take effect per generation
+                if elders_rotated {
+                    continue;
+                }
+                elders_rotated = true;
+            }
+            if self.check_reconfig(reconfig, &members, gen).is_ok() {
+                reconfig.apply(&mut members);
+                applied.insert(reconfig.clone());
+            }
+        }
+        applied
+    }
+
+    pub fn propose(&mut self, reconfig: Reconfig<T, S>) -> Result<SignedVote<Reconfig<T, S>, S>> {
         info!("[{}] proposing {:?}", self.id(), reconfig);
         let vote = Vote {
             gen: self.gen + 1,
@@ -155,18 +462,75 @@ impl<T: Proposition> Membership<T> {
         self.cast_vote(signed_vote)
     }
 
-    pub fn anti_entropy(&self, from_gen: Generation) -> Result<Vec<SignedVote<Reconfig<T>>>> {
+    /// The faults proven since `gen`, including those of the generation we're voting on,
+    /// by the elder at fault.
+    ///
+    /// Elder ids are those of the elders of the generation a fault was proven in, an id
+    /// may belong to someone else once the elders are rotated.
+    pub fn faults_since(&self, gen: Generation) -> FaultsByElder<T, S> {
+        let current = (self.gen + 1, &self.consensus.faults);
+        let ledger = self
+            .fault_ledger
+            .iter()
+            .map(|(fault_gen, faults)| (*fault_gen, faults));
+
+        let mut faults_by_elder = FaultsByElder::new();
+        for (fault_gen, faults) in ledger.chain([current]) {
+            if fault_gen < gen {
+                continue;
+            }
+            for (elder, fault) in faults {
+                faults_by_elder
+                    .entry(*elder)
+                    .or_default()
+                    .insert(fault_gen, fault.clone());
+            }
+        }
+        faults_by_elder
+    }
+
+    /// Proposes that a repeat offender leaves, i.e. a member whose elder was proven
+    /// faulty in at least `min_offences` generations since `gen`.
+    ///
+    /// `member_of` maps elder ids to members, offenders that are no longer members are
+    /// skipped. Returns `None` if there is no one to propose a leave for.
+    pub fn propose_leave_for_repeat_offender(
+        &mut self,
+        gen: Generation,
+        min_offences: usize,
+        member_of: impl Fn(NodeId) -> Option<T>,
+    ) -> Result<Option<SignedVote<Reconfig<T, S>, S>>> {
+        let members = self.members(self.gen)?;
+        let offender = self
+            .faults_since(gen)
+            .into_iter()
+            .filter(|(_, faults)| faults.len() >= min_offences)
+            .filter_map(|(elder, _)| member_of(elder))
+            .find(|member| members.contains(member));
+
+        match offender {
+            Some(member) => {
+                info!(
+                    "[{}] proposing repeat offender {:?} leaves",
+                    self.id(),
+                    member
+                );
+                self.propose(Reconfig::Leave(member)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn anti_entropy(&self, from_gen: Generation) -> Result<Vec<SignedVote<Reconfig<T, S>, S>>> {
         info!("[MBR] anti-entropy from gen {}", from_gen);
+        self.check_not_pruned(from_gen + 1)?;
 
-        let mut msgs = self
-            .history
-            .iter() // history is a BTreeSet, .iter() is ordered by generation
-            .filter(|(gen, _)| **gen > from_gen)
-            .filter_map(|(gen, c)| c.decision.clone().map(|d| (gen, c, d)))
-            .map(|(gen, c, decision)| {
-                c.build_super_majority_vote(decision.votes, decision.faults, *gen)
-            })
-            .collect::<Result<Vec<_>>>()?;
+        let mut msgs = Vec::from_iter(
+            self.history
+                .iter() // history is a BTreeSet, .iter() is ordered by generation
+                .filter(|(gen, _)| **gen > from_gen)
+                .filter_map(|(_, c)| c.decision_vote.clone()),
+        );
 
         // include the current in-progres votes as well.
         msgs.extend(self.consensus.votes.values().cloned());
@@ -174,48 +538,193 @@ impl<T: Proposition> Membership<T> {
         Ok(msgs)
     }
 
+    /// A digest of the votes we've seen in the generation we're voting on.
+    pub fn digest(&self) -> VoteDigest<S> {
+        self.consensus.digest(self.gen + 1)
+    }
+
+    /// Get someone up to speed given their digest, only the votes they are missing are returned.
+    pub fn missing_votes(
+        &self,
+        digest: &VoteDigest<S>,
+    ) -> Result<Vec<SignedVote<Reconfig<T, S>, S>>> {
+        info!("[MBR] anti-entropy for digest at gen {}", digest.gen);
+        if digest.gen > self.gen + 1 {
+            // they are ahead of us, there's nothing we can help them with
+            return Ok(Vec::new());
+        }
+        self.check_not_pruned(digest.gen)?;
+
+        let mut msgs = self.consensus_at_gen(digest.gen)?.missing_votes(digest)?;
+
+        // they are caught up on `digest.gen`, send them every decision that followed
+        msgs.extend(
+            self.history
+                .range(digest.gen + 1..)
+                .filter_map(|(_, c)| c.decision_vote.clone()),
+        );
+        if digest.gen <= self.gen {
+            msgs.extend(self.consensus.votes.values().cloned());
+        }
+
+        Ok(msgs)
+    }
+
+    /// Drives the timeout of the generation we're voting on, see `Consensus::tick`.
+    pub fn tick(&mut self, now: Duration) -> Option<TimeoutResponse<Reconfig<T, S>, S>> {
+        self.consensus.tick(now)
+    }
+
+    pub fn on_timeout(&mut self) -> Option<TimeoutResponse<Reconfig<T, S>, S>> {
+        self.consensus.on_timeout()
+    }
+
     pub fn id(&self) -> NodeId {
         self.consensus.id()
     }
 
+    /// Notifies `observer` of the state transitions of every generation from now on.
+    pub fn set_observer(
+        &mut self,
+        observer: impl ConsensusObserver<Reconfig<T, S>> + Send + Sync + 'static,
+    ) {
+        self.consensus.set_observer(observer);
+    }
+
     pub fn handle_signed_vote(
         &mut self,
-        signed_vote: SignedVote<Reconfig<T>>,
-    ) -> Result<VoteResponse<Reconfig<T>>> {
+        signed_vote: SignedVote<Reconfig<T, S>, S>,
+    ) -> Result<VoteResponse<Reconfig<T, S>, S>> {
+        self.check_elder(&signed_vote)?;
         self.validate_proposals(&signed_vote)?;
 
         let vote_gen = signed_vote.vote.gen;
+        // The vote is persisted before consensus acts on it, any vote we cast in response
+        // is persisted by the vote log of the consensus before it's handed back to us.
+        if self.store.is_some() {
+            self.persist(Record::Vote(signed_vote.clone()))?;
+        }
 
         let consensus = self.consensus_at_gen_mut(vote_gen)?;
         let vote_response = consensus.handle_signed_vote(signed_vote)?;
 
         if consensus.decision.is_some() && vote_gen == self.gen + 1 {
-            let next_consensus = Consensus::from(
-                self.consensus.secret_key.clone(),
-                self.consensus.elders.clone(),
-                self.consensus.n_elders,
-            );
-
-            let decided_consensus = std::mem::replace(&mut self.consensus, next_consensus);
-            self.history.insert(vote_gen, decided_consensus);
-            self.gen = vote_gen
+            self.advance(vote_gen)?;
         }
 
         Ok(vote_response)
     }
 
-    pub fn sign_vote(&self, vote: Vote<Reconfig<T>>) -> Result<SignedVote<Reconfig<T>>> {
+    /// Moves on to the generation following `gen` once `gen` has decided, the decided
+    /// consensus is kept in `history`.
+    pub(crate) fn advance(&mut self, gen: Generation) -> Result<()> {
+        let (elders, n_elders) = self.elders_after(gen)?;
+        let mut next_consensus =
+            Consensus::from(self.consensus.secret_key.clone(), elders, n_elders);
+        // we refuse to vote with the key of the previous elders until `set_secret_key`
+        // gives us our share of the key of the new ones
+        next_consensus.awaiting_secret_key =
+            self.consensus.awaiting_secret_key || next_consensus.elders != self.consensus.elders;
+        next_consensus.timeout = self.consensus.timeout;
+        next_consensus.vote_log = self.consensus.vote_log.clone();
+        next_consensus.observer = self.consensus.observer.take();
+
+        let decided_consensus = std::mem::replace(&mut self.consensus, next_consensus);
+        if !decided_consensus.faults.is_empty() {
+            self.fault_ledger
+                .insert(gen, decided_consensus.faults.clone());
+        }
+        self.history.insert(gen, decided_consensus);
+        self.gen = gen;
+        self.consensus
+            .observe(ConsensusEvent::GenerationAdvanced { gen: gen + 1 });
+
+        #[cfg(feature = "metrics")]
+        {
+            crate::metrics::set_gauge("sn_consensus_membership_generation", &[], gen as f64);
+            if let Ok(members) = self.members(gen) {
+                crate::metrics::set_gauge(
+                    "sn_consensus_membership_members",
+                    &[],
+                    members.len() as f64,
+                );
+            }
+        }
+
+        self.snapshot()
+    }
+
+    /// Votes must be signed by the elders of their generation, a vote signed by
+    /// elders that have since been rotated out is rejected with `Error::NotElder`.
+    fn check_elder(&self, signed_vote: &SignedVote<Reconfig<T, S>, S>) -> Result<()> {
+        let gen = signed_vote.vote.gen;
+        let elders = &self.consensus_at_gen(gen)?.elders;
+        if signed_vote.validate_signature(elders).is_ok() {
+            return Ok(());
+        }
+
+        let signed_by_previous_elders = self
+            .history
+            .range(..gen)
+            .map(|(_, consensus)| &consensus.elders)
+            .filter(|previous_elders| *previous_elders != elders)
+            .any(|previous_elders| signed_vote.validate_signature(previous_elders).is_ok());
+
+        if signed_by_previous_elders {
+            Err(Error::NotElder)
+        } else {
+            // Leave it to consensus to reject the invalid signature.
+            Ok(())
+        }
+    }
+
+    /// The elders voting on the generation following the decision at `gen`.
+    fn elders_after(&self, gen: Generation) -> Result<(S, usize)> {
+        let rotation =
+            self.decided_reconfigs(gen)?
+                .into_iter()
+                .find_map(|reconfig| match reconfig {
+                    Reconfig::Elders { elders, n_elders } => Some((elders, n_elders)),
+                    _ => None,
+                });
+
+        let consensus = self.consensus_at_gen(gen)?;
+        Ok(rotation.unwrap_or_else(|| (consensus.elders.clone(), consensus.n_elders)))
+    }
+
+    /// Replaces the key we vote with in the current generation, e.g. with our share of
+    /// the key of the elders we were rotated to.
+    pub fn set_secret_key(&mut self, secret_key: (NodeId, S::SecretKeyShare)) {
+        self.consensus.secret_key = secret_key;
+        self.consensus.awaiting_secret_key = false;
+    }
+
+    fn check_secret_key(&self) -> Result<()> {
+        if self.consensus.awaiting_secret_key {
+            Err(Error::MissingSecretKey)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn sign_vote(
+        &self,
+        vote: Vote<Reconfig<T, S>, S>,
+    ) -> Result<SignedVote<Reconfig<T, S>, S>> {
+        self.check_secret_key()?;
         self.consensus.sign_vote(vote)
     }
 
     pub fn cast_vote(
         &mut self,
-        signed_vote: SignedVote<Reconfig<T>>,
-    ) -> Result<SignedVote<Reconfig<T>>> {
+        signed_vote: SignedVote<Reconfig<T, S>, S>,
+    ) -> Result<SignedVote<Reconfig<T, S>, S>> {
+        self.check_secret_key()?;
+        // persisted by the vote log of the consensus before it is handled
         self.consensus.cast_vote(signed_vote)
     }
 
-    pub fn validate_proposals(&self, signed_vote: &SignedVote<Reconfig<T>>) -> Result<()> {
+    pub fn validate_proposals(&self, signed_vote: &SignedVote<Reconfig<T, S>, S>) -> Result<()> {
         // ensure we have a consensus instance for this votes generations
         let _ = self.consensus_at_gen(signed_vote.vote.gen)?;
 
@@ -225,26 +734,36 @@ impl<T: Proposition> Membership<T> {
             .try_for_each(|reconfig| self.validate_reconfig(reconfig, signed_vote.vote.gen))
     }
 
-    pub fn validate_reconfig(&self, reconfig: Reconfig<T>, gen: Generation) -> Result<()> {
+    pub fn validate_reconfig(&self, reconfig: Reconfig<T, S>, gen: Generation) -> Result<()> {
         assert!(gen > 0);
         let members = self.members(gen - 1)?;
+        self.check_reconfig(&reconfig, &members, gen)
+    }
+
+    fn check_reconfig(
+        &self,
+        reconfig: &Reconfig<T, S>,
+        members: &BTreeSet<T>,
+        gen: Generation,
+    ) -> Result<()> {
         match reconfig {
-            Reconfig::Join(actor) => {
-                if members.contains(&actor) {
-                    Err(Error::JoinRequestForExistingMember)
-                } else if members.len() >= SOFT_MAX_MEMBERS {
-                    Err(Error::MembersAtCapacity)
-                } else {
-                    Ok(())
-                }
+            Reconfig::Join(actor) if members.contains(actor) => {
+                return Err(Error::JoinRequestForExistingMember)
             }
-            Reconfig::Leave(actor) => {
-                if !members.contains(&actor) {
-                    Err(Error::LeaveRequestForNonMember)
-                } else {
-                    Ok(())
-                }
+            Reconfig::Leave(actor) if !members.contains(actor) => {
+                return Err(Error::LeaveRequestForNonMember)
             }
+            Reconfig::Elders { elders, n_elders } if *n_elders <= elders.threshold() => {
+                return Err(Error::InvalidElders {
+                    n_elders: *n_elders,
+                    threshold: elders.threshold(),
+                })
+            }
+            _ => (),
         }
+
+        self.policy
+            .validate(reconfig, members, gen)
+            .map_err(Error::PolicyViolation)
     }
 }
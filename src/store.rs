@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use core::fmt::Debug;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::shared::Shared;
+use crate::sn_membership::{Checkpoint, FaultLedger, Generation, Reconfig};
+use crate::{
+    Decision, DecisionCertificate, Fault, NodeId, Proposition, PublicKeySet, Result,
+    SignatureScheme, SignedVote,
+};
+
+/// Persistent storage for a `Membership`.
+///
+/// Records are appended to a log as the `Membership` makes progress, every record
+/// must be durable by the time `append` returns since records are appended before
+/// the `Membership` acts on them, our own votes are only broadcast once persisted.
+/// Snapshots replace the log with the full state so it doesn't grow unbounded.
+pub trait Store<T: Proposition, S: SignatureScheme = PublicKeySet>: Debug {
+    fn append(&self, record: &Record<T, S>) -> Result<()>;
+
+    /// Replaces the persisted state with `state`, discarding the log.
+    fn snapshot(&self, state: &MembershipState<T, S>) -> Result<()>;
+
+    /// Returns the last snapshot along with every record appended since.
+    fn load(&self) -> Result<Persisted<T, S>>;
+}
+
+/// Write-ahead log of the votes we cast.
+///
+/// `Consensus` appends each of our votes before handing it back for broadcasting,
+/// so `append` must not return until the vote is durable. A log is expected to
+/// hold the votes of a single `Consensus` instance, i.e. of a single generation.
+pub trait VoteLog<T: Proposition, S: SignatureScheme = PublicKeySet>: Debug {
+    fn append(&self, vote: &SignedVote<T, S>) -> Result<()>;
+
+    /// Returns every vote appended to this log.
+    fn load(&self) -> Result<Vec<SignedVote<T, S>>>;
+}
+
+/// The last snapshot along with the records appended since.
+pub type Persisted<T, S = PublicKeySet> = (Option<MembershipState<T, S>>, Vec<Record<T, S>>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum Record<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    Vote(SignedVote<Reconfig<T, S>, S>),
+    /// A join forced at generation `gen`.
+    ForceJoin {
+        gen: Generation,
+        actor: T,
+    },
+    /// A leave forced at generation `gen`.
+    ForceLeave {
+        gen: Generation,
+        actor: T,
+    },
+}
+
+/// Everything a `Consensus` needs to resume, except for the secret key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsensusState<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub elders: S,
+    pub n_elders: usize,
+    pub votes: BTreeMap<NodeId, SignedVote<T, S>>,
+    pub faults: BTreeMap<NodeId, Fault<T, S>>,
+    pub decision: Option<Decision<T, S>>,
+    pub certificate: Option<DecisionCertificate<T, S>>,
+    pub decision_vote: Option<SignedVote<T, S>>,
+}
+
+/// Everything a `Membership` needs to resume, except for the secret key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MembershipState<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub gen: Generation,
+    pub forced_reconfigs: BTreeMap<Generation, BTreeSet<Reconfig<T, S>>>,
+    pub history: BTreeMap<Generation, ConsensusState<Reconfig<T, S>, S>>,
+    pub consensus: ConsensusState<Reconfig<T, S>, S>,
+    pub checkpoint: Option<Checkpoint<T, S>>,
+    pub fault_ledger: FaultLedger<T, S>,
+}
+
+/// A `Store` kept in memory.
+pub type MemoryStore<T, S = PublicKeySet> = Shared<Persisted<T, S>>;
+
+impl<T: Proposition, S: SignatureScheme> Store<T, S> for MemoryStore<T, S> {
+    fn append(&self, record: &Record<T, S>) -> Result<()> {
+        self.lock().1.push(record.clone());
+        Ok(())
+    }
+
+    fn snapshot(&self, state: &MembershipState<T, S>) -> Result<()> {
+        *self.lock() = (Some(state.clone()), Vec::new());
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Persisted<T, S>> {
+        Ok(self.lock().clone())
+    }
+}
+
+/// A `VoteLog` kept in memory.
+pub type MemoryVoteLog<T, S = PublicKeySet> = Shared<Vec<SignedVote<T, S>>>;
+
+impl<T: Proposition, S: SignatureScheme> VoteLog<T, S> for MemoryVoteLog<T, S> {
+    fn append(&self, vote: &SignedVote<T, S>) -> Result<()> {
+        self.lock().push(vote.clone());
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<SignedVote<T, S>>> {
+        Ok(self.lock().clone())
+    }
+}
+
+/// Logs the votes a `Membership` casts to its `Store`, the votes are persisted as
+/// records and replayed by `Membership::restore` rather than loaded from here.
+#[derive(Debug)]
+pub(crate) struct StoreVoteLog<T: Proposition, S: SignatureScheme>(
+    pub(crate) Arc<dyn Store<T, S> + Send + Sync>,
+);
+
+impl<T: Proposition, S: SignatureScheme> VoteLog<Reconfig<T, S>, S> for StoreVoteLog<T, S> {
+    fn append(&self, vote: &SignedVote<Reconfig<T, S>, S>) -> Result<()> {
+        self.0.append(&Record::Vote(vote.clone()))
+    }
+
+    fn load(&self) -> Result<Vec<SignedVote<Reconfig<T, S>, S>>> {
+        Ok(Vec::new())
+    }
+}
+
+/// A `Store` kept on disk as a snapshot file and an append-only log of records.
+///
+/// Each record in the log is prefixed by its length, a record that was only
+/// partially written before a crash is ignored on load.
+#[derive(Debug)]
+pub struct FileStore<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    dir: PathBuf,
+    _state: PhantomData<fn() -> MembershipState<T, S>>,
+}
+
+impl<T: Proposition, S: SignatureScheme> FileStore<T, S> {
+    const SNAPSHOT: &'static str = "snapshot";
+    const LOG: &'static str = "log";
+
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            _state: PhantomData,
+        })
+    }
+
+    fn sync_dir(&self) -> Result<()> {
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+impl<T, S> Store<T, S> for FileStore<T, S>
+where
+    T: Proposition + DeserializeOwned,
+    S: SignatureScheme + DeserializeOwned,
+{
+    fn append(&self, record: &Record<T, S>) -> Result<()> {
+        let bytes = bincode::serialize(record)?;
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(Self::LOG))?;
+        log.write_all(&(bytes.len() as u64).to_le_bytes())?;
+        log.write_all(&bytes)?;
+        log.sync_data()?;
+        Ok(())
+    }
+
+    fn snapshot(&self, state: &MembershipState<T, S>) -> Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", Self::SNAPSHOT));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bincode::serialize(state)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, self.dir.join(Self::SNAPSHOT))?;
+        self.sync_dir()?;
+
+        // The snapshot is durable, the records it covers can be dropped.
+        File::create(self.dir.join(Self::LOG))?.sync_all()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Persisted<T, S>> {
+        let state = match fs::read(self.dir.join(Self::SNAPSHOT)) {
+            Ok(bytes) => Some(bincode::deserialize(&bytes)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let log = match fs::read(self.dir.join(Self::LOG)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut records = Vec::new();
+        let mut reader = log.as_slice();
+        loop {
+            let mut len = [0u8; 8];
+            if reader.read_exact(&mut len).is_err() {
+                break;
+            }
+            let len = u64::from_le_bytes(len) as usize;
+            if reader.len() < len {
+                break;
+            }
+            let (record, rest) = reader.split_at(len);
+            records.push(bincode::deserialize(record)?);
+            reader = rest;
+        }
+
+        Ok((state, records))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ballot, Consensus, SecretKeySet, Vote};
+    use rand::{prelude::StdRng, SeedableRng};
+
+    #[test]
+    fn test_file_store_ignores_partially_written_record() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let elders_sk = SecretKeySet::random(0, &mut rng);
+        let consensus = Consensus::<Reconfig<u8>>::from(
+            (0, elders_sk.secret_key_share(0)),
+            elders_sk.public_keys(),
+            1,
+        );
+        let vote = consensus
+            .sign_vote(Vote {
+                gen: 1,
+                ballot: Ballot::Propose(Reconfig::Join(1)),
+                faults: Default::default(),
+            })
+            .unwrap();
+
+        let dir =
+            std::env::temp_dir().join(format!("sn_consensus_file_store_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = FileStore::<u8>::open(&dir).unwrap();
+        let force_join = Record::ForceJoin { gen: 0, actor: 0 };
+        store.append(&force_join).unwrap();
+        store.append(&Record::Vote(vote.clone())).unwrap();
+
+        // simulate a crash in the middle of appending a record
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(dir.join("log"))
+            .unwrap();
+        log.write_all(&1024u64.to_le_bytes()).unwrap();
+        log.write_all(&[0u8; 10]).unwrap();
+
+        let (state, records) = store.load().unwrap();
+        assert_eq!(state, None);
+        assert_eq!(records, vec![force_join, Record::Vote(vote)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,35 @@
+use core::fmt::Debug;
+
+use crate::Proposition;
+
+/// Checks `Handover` proposals against application state before we vote on them,
+/// e.g. that a candidate elder set is made of known members.
+pub trait ProposalValidator<T: Proposition>: Debug {
+    fn validate(&self, proposal: &T) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Accepts every proposal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptAll;
+
+impl<T: Proposition> ProposalValidator<T> for AcceptAll {
+    fn validate(&self, _proposal: &T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+/// Validates proposals with a user provided function.
+#[derive(Clone, Copy)]
+pub struct FnValidator<F>(pub F);
+
+impl_fn_debug!(FnValidator);
+
+impl<T, F> ProposalValidator<T> for FnValidator<F>
+where
+    T: Proposition,
+    F: Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>>,
+{
+    fn validate(&self, proposal: &T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (self.0)(proposal)
+    }
+}
@@ -1,31 +1,35 @@
 use std::collections::{BTreeMap, BTreeSet};
 
-use blsttc::{PublicKeySet, SignatureShare};
 use core::fmt::Debug;
 use serde::{Deserialize, Serialize};
 
 use crate::sn_membership::Generation;
-use crate::{Candidate, Error, Fault, NodeId, Result, VoteCount};
+use crate::{Candidate, Error, Fault, NodeId, PublicKeySet, Result, SignatureScheme, VoteCount};
 
 pub trait Proposition: Ord + Clone + Debug + Serialize {}
 impl<T: Ord + Clone + Debug + Serialize> Proposition for T {}
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub enum Ballot<T: Proposition> {
+pub enum Ballot<T: Proposition, S: SignatureScheme = PublicKeySet> {
     Propose(T),
-    Merge(BTreeSet<SignedVote<T>>),
+    Merge(BTreeSet<SignedVote<T, S>>),
     SuperMajority {
-        votes: BTreeSet<SignedVote<T>>,
-        proposals: BTreeMap<T, (NodeId, SignatureShare)>,
+        votes: BTreeSet<SignedVote<T, S>>,
+        proposals: BTreeMap<T, (NodeId, S::SignatureShare)>,
+        /// Signature share over `(gen, proposals)`, a super majority of these
+        /// shares are combined into a `DecisionCertificate`.
+        certificate_share: (NodeId, S::SignatureShare),
     },
 }
 
-impl<T: Proposition> Debug for Ballot<T> {
+impl<T: Proposition, S: SignatureScheme> Debug for Ballot<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Ballot::Propose(r) => write!(f, "P({r:?})"),
             Ballot::Merge(votes) => write!(f, "M{votes:?}"),
-            Ballot::SuperMajority { votes, proposals } => write!(
+            Ballot::SuperMajority {
+                votes, proposals, ..
+            } => write!(
                 f,
                 "SM{:?}-{:?}",
                 votes,
@@ -35,9 +39,9 @@ impl<T: Proposition> Debug for Ballot<T> {
     }
 }
 
-pub fn simplify_votes<T: Proposition>(
-    signed_votes: &BTreeSet<SignedVote<T>>,
-) -> BTreeSet<SignedVote<T>> {
+pub fn simplify_votes<T: Proposition, S: SignatureScheme>(
+    signed_votes: &BTreeSet<SignedVote<T, S>>,
+) -> BTreeSet<SignedVote<T, S>> {
     let mut simpler_votes = BTreeSet::new();
     for v in signed_votes.iter() {
         let this_vote_is_superseded = signed_votes
@@ -52,8 +56,8 @@ pub fn simplify_votes<T: Proposition>(
     simpler_votes
 }
 
-pub fn proposals<T: Proposition>(
-    votes: &BTreeSet<SignedVote<T>>,
+pub fn proposals<T: Proposition, S: SignatureScheme>(
+    votes: &BTreeSet<SignedVote<T, S>>,
     known_faulty: &BTreeSet<NodeId>,
 ) -> BTreeSet<T> {
     BTreeSet::from_iter(
@@ -66,7 +70,95 @@ pub fn proposals<T: Proposition>(
     )
 }
 
-impl<T: Proposition> Ballot<T> {
+/// The final vote of each voter found in `votes`, i.e. the first vote it cast once it,
+/// or a voter it has seen a vote from, reached the merge bound.
+///
+/// A voter may only have a single final vote, one that casts conflicting final votes is
+/// faulty and the smallest of its earliest final votes is kept, so that anyone holding
+/// the same votes picks the same final votes.
+pub fn final_votes<'a, T: Proposition + 'a, S: SignatureScheme + 'a>(
+    votes: impl IntoIterator<Item = &'a SignedVote<T, S>>,
+) -> BTreeMap<NodeId, SignedVote<T, S>> {
+    let mut is_final = BTreeMap::new();
+    let mut finals_by_voter: BTreeMap<NodeId, BTreeSet<&SignedVote<T, S>>> = BTreeMap::new();
+    for vote in votes {
+        for v in vote.unpack_votes() {
+            if v.is_final_memo(&mut is_final) {
+                finals_by_voter.entry(v.voter).or_default().insert(v);
+            }
+        }
+    }
+
+    finals_by_voter
+        .into_iter()
+        .filter_map(|(voter, finals)| {
+            finals
+                .iter()
+                .find(|v| {
+                    !finals
+                        .iter()
+                        .any(|other| other != *v && v.supersedes(other))
+                })
+                .map(|v| (voter, (*v).clone()))
+        })
+        .collect()
+}
+
+/// Breaks the tie between the final votes of the elders, see `final_votes`.
+///
+/// Super majorities take precedence, the tie goes to the candidate of the most super
+/// majority ballots the final votes hold, counting the latest one of each voter. Without
+/// any, it goes to the candidate with the most final votes. Only the faults proven by the
+/// final votes are considered so that the outcome depends on the final votes alone.
+pub fn tie_break<T: Proposition, S: SignatureScheme>(
+    finals: &BTreeMap<NodeId, SignedVote<T, S>>,
+) -> Candidate<T> {
+    let faulty = BTreeSet::from_iter(finals.values().flat_map(|v| v.vote.faulty_ids()));
+
+    let mut super_majorities: BTreeMap<NodeId, &SignedVote<T, S>> = BTreeMap::new();
+    for v in finals.values().flat_map(SignedVote::unpack_votes) {
+        if v.vote.is_super_majority_ballot() && !faulty.contains(&v.voter) {
+            let latest = super_majorities.entry(v.voter).or_insert(v);
+            if v.supersedes(latest) {
+                *latest = v;
+            }
+        }
+    }
+
+    if super_majorities.is_empty() {
+        return VoteCount::count(finals.values(), &faulty)
+            .candidate_with_most_votes()
+            .map(|(candidate, _)| candidate.clone())
+            .unwrap_or_default();
+    }
+
+    let mut candidates: BTreeMap<Candidate<T>, usize> = BTreeMap::new();
+    for v in super_majorities.values() {
+        *candidates.entry(v.candidate()).or_default() += 1;
+    }
+    crate::vote_count::most_voted(candidates.iter().map(|(candidate, c)| (candidate, *c)))
+        .map(|(candidate, _)| candidate.clone())
+        .unwrap_or_default()
+}
+
+/// The candidate a SuperMajority ballot over `votes` signs: the candidate with the most
+/// votes, or the tie-break of the final votes once the merge bound was reached.
+pub fn super_majority_candidate<T: Proposition, S: SignatureScheme>(
+    votes: &BTreeSet<SignedVote<T, S>>,
+    faulty: &BTreeSet<NodeId>,
+) -> Candidate<T> {
+    let finals = final_votes(votes);
+    if finals.is_empty() {
+        VoteCount::count(votes, faulty)
+            .candidate_with_most_votes()
+            .map(|(candidate, _)| candidate.clone())
+            .unwrap_or_default()
+    } else {
+        tie_break(&finals)
+    }
+}
+
+impl<T: Proposition, S: SignatureScheme> Ballot<T, S> {
     pub fn as_proposal(&self) -> Option<&T> {
         match &self {
             Ballot::Propose(p) => Some(p),
@@ -79,22 +171,27 @@ impl<T: Proposition> Ballot<T> {
         match &self {
             Ballot::Propose(_) => self.clone(), // already in simplest form
             Ballot::Merge(votes) => Ballot::Merge(simplify_votes(votes)),
-            Ballot::SuperMajority { votes, proposals } => Ballot::SuperMajority {
+            Ballot::SuperMajority {
+                votes,
+                proposals,
+                certificate_share,
+            } => Ballot::SuperMajority {
                 votes: simplify_votes(votes),
                 proposals: proposals.clone(),
+                certificate_share: certificate_share.clone(),
             },
         }
     }
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct Vote<T: Proposition> {
+pub struct Vote<T: Proposition, S: SignatureScheme = PublicKeySet> {
     pub gen: Generation,
-    pub ballot: Ballot<T>,
-    pub faults: BTreeSet<Fault<T>>,
+    pub ballot: Ballot<T, S>,
+    pub faults: BTreeSet<Fault<T, S>>,
 }
 
-impl<T: Proposition> Debug for Vote<T> {
+impl<T: Proposition, S: SignatureScheme> Debug for Vote<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "G{}-{:?}", self.gen, self.ballot)?;
 
@@ -105,13 +202,13 @@ impl<T: Proposition> Debug for Vote<T> {
     }
 }
 
-impl<T: Proposition> Vote<T> {
+impl<T: Proposition, S: SignatureScheme> Vote<T, S> {
     pub fn validate(
         &self,
-        voters: &PublicKeySet,
-        valid_votes_memo: &BTreeSet<SignatureShare>,
+        voters: &S,
+        valid_votes_memo: &BTreeSet<S::SignatureShare>,
     ) -> Result<()> {
-        let validate_child_votes = |child_votes: &BTreeSet<SignedVote<T>>| {
+        let validate_child_votes = |child_votes: &BTreeSet<SignedVote<T, S>>| {
             for child_vote in child_votes {
                 let child_gen = child_vote.vote.gen;
                 let merge_gen = self.gen;
@@ -132,30 +229,36 @@ impl<T: Proposition> Vote<T> {
         match &self.ballot {
             Ballot::Propose(_) => Ok(()),
             Ballot::Merge(votes) => validate_child_votes(votes),
-            Ballot::SuperMajority { votes, proposals } => {
-                let vote_count = VoteCount::count(votes, &self.faulty_ids());
-
-                let candidate_proposals = vote_count
-                    .candidate_with_most_votes()
-                    .map(|(c, _)| c.proposals.clone())
-                    .unwrap_or_default();
-
-                if !vote_count.do_we_have_supermajority(voters) {
-                    // TODO: this should be moved to fault detection
-                    Err(Error::SuperMajorityBallotIsNotSuperMajority)
-                } else if !candidate_proposals.iter().eq(proposals.keys()) {
-                    // TODO: this should be moved to fault detection
-                    Err(Error::SuperMajorityProposalsDoesNotMatchVoteProposals)
-                } else if proposals
+            Ballot::SuperMajority {
+                votes,
+                proposals,
+                certificate_share: (certificate_voter, certificate_sig),
+            } => {
+                // Whether the ballot is a super majority of its votes, and signs their
+                // candidate, is left to fault detection so that the voter is held accountable
+                // for it, but the signatures it carries must hold either way.
+                proposals
                     .iter()
-                    .try_for_each(|(p, (id, sig))| crate::verify_sig_share(&p, sig, *id, voters))
-                    .is_err()
-                {
-                    Err(Error::InvalidElderSignature)
-                } else {
-                    validate_child_votes(votes)
-                }
+                    .try_for_each(|(p, (id, sig))| crate::verify_sig_share(&p, sig, *id, voters))?;
+                let signed_proposals = BTreeSet::from_iter(proposals.keys().cloned());
+                crate::verify_sig_share(
+                    &(self.gen, &signed_proposals),
+                    certificate_sig,
+                    *certificate_voter,
+                    voters,
+                )?;
+                validate_child_votes(votes)
+            }
+        }
+    }
+
+    /// Counts the votes of a SuperMajority ballot, `None` for any other ballot.
+    pub fn super_majority_count(&self) -> Option<VoteCount<T, S>> {
+        match &self.ballot {
+            Ballot::SuperMajority { votes, .. } => {
+                Some(VoteCount::count(votes, &self.faulty_ids()))
             }
+            _ => None,
         }
     }
 
@@ -163,6 +266,15 @@ impl<T: Proposition> Vote<T> {
         matches!(self.ballot, Ballot::SuperMajority { .. })
     }
 
+    /// A SuperMajority ballot cast once the merge bound was reached, it signs the
+    /// tie-break of the final votes it holds rather than a super majority.
+    pub fn is_tie_break(&self) -> bool {
+        match &self.ballot {
+            Ballot::SuperMajority { votes, .. } => votes.iter().any(SignedVote::is_final),
+            _ => false,
+        }
+    }
+
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
         Ok(bincode::serialize(&self)?)
     }
@@ -187,25 +299,24 @@ impl<T: Proposition> Vote<T> {
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct SignedVote<T: Proposition> {
-    pub vote: Vote<T>,
+pub struct SignedVote<T: Proposition, S: SignatureScheme = PublicKeySet> {
+    pub vote: Vote<T, S>,
     pub voter: NodeId,
-    pub sig: SignatureShare,
+    pub sig: S::SignatureShare,
 }
 
-impl<T: Proposition> Debug for SignedVote<T> {
+impl<T: Proposition, S: SignatureScheme> Debug for SignedVote<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}@{}", self.vote, self.voter)
     }
 }
 
-impl<T: Proposition> SignedVote<T> {
+impl<T: Proposition, S: SignatureScheme> SignedVote<T, S> {
     pub fn candidate(&self) -> Candidate<T> {
         match &self.vote.ballot {
-            Ballot::SuperMajority { votes, .. } => VoteCount::count(votes, &self.vote.faulty_ids())
-                .candidate_with_most_votes()
-                .map(|(candidate, _)| candidate.clone())
-                .unwrap_or_default(),
+            Ballot::SuperMajority { votes, .. } => {
+                super_majority_candidate(votes, &self.vote.faulty_ids())
+            }
             _ => Candidate {
                 proposals: self.proposals(),
                 faulty: self.vote.faulty_ids(),
@@ -213,7 +324,45 @@ impl<T: Proposition> SignedVote<T> {
         }
     }
 
-    pub fn validate_signature(&self, voters: &PublicKeySet) -> Result<()> {
+    /// Whether the voter reached the merge bound with this merge: it merged twice for
+    /// each voter it counted, once when it learnt of its proposal and once when it
+    /// learnt that it is faulty.
+    pub fn is_exhausted(&self) -> bool {
+        if !matches!(self.vote.ballot, Ballot::Merge(_)) {
+            return false;
+        }
+        let mut voters = self.vote.faulty_ids();
+        let mut merges = BTreeSet::new();
+        for v in self.unpack_votes() {
+            voters.insert(v.voter);
+            if v.voter == self.voter && matches!(v.vote.ballot, Ballot::Merge(_)) {
+                merges.insert(&v.sig);
+            }
+        }
+        merges.len() >= 2 * voters.len()
+    }
+
+    /// Whether this vote was cast once the merge bound was reached, by its voter or by a
+    /// voter it has seen a vote from. Once the bound is reached nobody merges anymore.
+    pub fn is_final(&self) -> bool {
+        self.is_final_memo(&mut BTreeMap::new())
+    }
+
+    fn is_final_memo<'a>(&'a self, memo: &mut BTreeMap<&'a S::SignatureShare, bool>) -> bool {
+        if let Some(is_final) = memo.get(&self.sig) {
+            return *is_final;
+        }
+        let is_final = match &self.vote.ballot {
+            Ballot::Propose(_) => false,
+            Ballot::Merge(votes) | Ballot::SuperMajority { votes, .. } => {
+                votes.iter().any(|v| v.is_final_memo(memo)) || self.is_exhausted()
+            }
+        };
+        memo.insert(&self.sig, is_final);
+        is_final
+    }
+
+    pub fn validate_signature(&self, voters: &S) -> Result<()> {
         crate::verify_sig_share(&self.vote, &self.sig, self.voter, voters)
     }
 
@@ -221,8 +370,8 @@ impl<T: Proposition> SignedVote<T> {
     /// Assumes those propositions are correct, they MUST be checked beforehand by the caller
     pub fn validate(
         &self,
-        voters: &PublicKeySet,
-        valid_votes_cache: &BTreeSet<SignatureShare>,
+        voters: &S,
+        valid_votes_cache: &BTreeSet<S::SignatureShare>,
     ) -> Result<()> {
         self.validate_signature(voters)?;
         self.vote.validate(voters, valid_votes_cache)?;
@@ -232,10 +381,10 @@ impl<T: Proposition> SignedVote<T> {
 
     pub fn detect_byzantine_faults(
         &self,
-        voters: &PublicKeySet,
-        existing_votes: &BTreeMap<NodeId, SignedVote<T>>,
-        valid_votes_cache: &BTreeSet<SignatureShare>,
-    ) -> std::result::Result<(), BTreeMap<NodeId, Fault<T>>> {
+        voters: &S,
+        existing_votes: &BTreeMap<NodeId, SignedVote<T, S>>,
+        valid_votes_cache: &BTreeSet<S::SignatureShare>,
+    ) -> std::result::Result<(), BTreeMap<NodeId, Fault<T, S>>> {
         let mut faults = BTreeMap::new();
         for vote in self.unpack_votes() {
             if valid_votes_cache.contains(&vote.sig) {
@@ -261,6 +410,21 @@ impl<T: Proposition> SignedVote<T> {
                     faults.insert(vote.voter, fault);
                 }
             }
+
+            if vote.vote.is_super_majority_ballot() {
+                for fault in [
+                    Fault::BogusSuperMajority {
+                        signed_vote: vote.clone(),
+                    },
+                    Fault::MismatchedSuperMajorityProposals {
+                        signed_vote: vote.clone(),
+                    },
+                ] {
+                    if let Ok(()) = fault.validate(voters) {
+                        faults.insert(vote.voter, fault);
+                    }
+                }
+            }
         }
 
         if faults.is_empty() {
@@ -302,7 +466,7 @@ impl<T: Proposition> SignedVote<T> {
         }
     }
 
-    pub fn vote_count(&self) -> VoteCount<T> {
+    pub fn vote_count(&self) -> VoteCount<T, S> {
         VoteCount::count([self], &self.vote.faulty_ids())
     }
 }
@@ -1,11 +1,13 @@
 use std::{
     borrow::Borrow,
+    cmp::Reverse,
     collections::{BTreeMap, BTreeSet},
 };
 
-use blsttc::{PublicKeySet, Signature, SignatureShare};
-
-use crate::{Ballot, NodeId, Proposition, Result, SignedVote};
+use crate::{
+    Ballot, DecisionCertificate, Generation, NodeId, Proposition, PublicKeySet, Result,
+    SignatureScheme, SignedVote,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Candidate<T> {
@@ -23,28 +25,30 @@ impl<T> Default for Candidate<T> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SuperMajorityCount<T> {
+pub struct SuperMajorityCount<T, S: SignatureScheme = PublicKeySet> {
     pub count: usize,
-    pub proposals: BTreeMap<T, BTreeMap<u64, SignatureShare>>,
+    pub proposals: BTreeMap<T, BTreeMap<NodeId, S::SignatureShare>>,
+    pub certificate_shares: BTreeMap<NodeId, S::SignatureShare>,
 }
 
-impl<T> Default for SuperMajorityCount<T> {
+impl<T, S: SignatureScheme> Default for SuperMajorityCount<T, S> {
     fn default() -> Self {
         Self {
             count: 0,
             proposals: Default::default(),
+            certificate_shares: Default::default(),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VoteCount<T> {
+pub struct VoteCount<T, S: SignatureScheme = PublicKeySet> {
     pub candidates: BTreeMap<Candidate<T>, usize>,
-    pub super_majorities: BTreeMap<Candidate<T>, SuperMajorityCount<T>>,
+    pub super_majorities: BTreeMap<Candidate<T>, SuperMajorityCount<T, S>>,
     pub voters: BTreeSet<NodeId>,
 }
 
-impl<T> Default for VoteCount<T> {
+impl<T, S: SignatureScheme> Default for VoteCount<T, S> {
     fn default() -> Self {
         Self {
             candidates: Default::default(),
@@ -54,14 +58,14 @@ impl<T> Default for VoteCount<T> {
     }
 }
 
-impl<T: Proposition> VoteCount<T> {
-    pub fn count<V: Borrow<SignedVote<T>>>(
+impl<T: Proposition, S: SignatureScheme> VoteCount<T, S> {
+    pub fn count<V: Borrow<SignedVote<T, S>>>(
         votes: impl IntoIterator<Item = V>,
         faulty: &BTreeSet<NodeId>,
     ) -> Self {
-        let mut count: VoteCount<T> = VoteCount::default();
+        let mut count: VoteCount<T, S> = VoteCount::default();
 
-        let mut votes_by_honest_voter: BTreeMap<NodeId, SignedVote<T>> = Default::default();
+        let mut votes_by_honest_voter: BTreeMap<NodeId, SignedVote<T, S>> = Default::default();
 
         for vote in votes.into_iter() {
             for unpacked_vote in vote.borrow().unpack_votes() {
@@ -86,17 +90,25 @@ impl<T: Proposition> VoteCount<T> {
         for vote in votes_by_honest_voter.into_values() {
             let candidate = vote.candidate();
 
-            if let Ballot::SuperMajority { proposals, .. } = &vote.vote.ballot {
+            if let Ballot::SuperMajority {
+                proposals,
+                certificate_share: (cert_id, cert_sig),
+                ..
+            } = &vote.vote.ballot
+            {
                 let sm_count = count.super_majorities.entry(candidate.clone()).or_default();
 
                 sm_count.count += 1;
+                sm_count
+                    .certificate_shares
+                    .insert(*cert_id, cert_sig.clone());
 
                 for (t, (id, sig)) in proposals {
                     sm_count
                         .proposals
                         .entry(t.clone())
                         .or_default()
-                        .insert(*id as u64, sig.clone());
+                        .insert(*id, sig.clone());
                 }
             }
 
@@ -107,26 +119,28 @@ impl<T: Proposition> VoteCount<T> {
         count
     }
 
+    /// See `most_voted` for how ties are broken.
     pub fn candidate_with_most_votes(&self) -> Option<(&Candidate<T>, usize)> {
-        self.candidates
-            .iter()
-            .map(|(candidates, c)| (candidates, *c))
-            .chain(
-                self.super_majority_with_most_votes()
-                    .map(|(candidates, sm_count)| (candidates, sm_count.count)),
-            )
-            .max_by_key(|(_, c)| *c)
+        most_voted(
+            self.candidates
+                .iter()
+                .map(|(candidates, c)| (candidates, *c))
+                .chain(
+                    self.super_majority_with_most_votes()
+                        .map(|(candidates, sm_count)| (candidates, sm_count.count)),
+                ),
+        )
     }
 
     pub fn super_majority_with_most_votes(
         &self,
-    ) -> Option<(&Candidate<T>, &SuperMajorityCount<T>)> {
+    ) -> Option<(&Candidate<T>, &SuperMajorityCount<T, S>)> {
         self.super_majorities
             .iter()
             .max_by_key(|(_, sm_count)| sm_count.count)
     }
 
-    pub fn is_split_vote(&self, voters: &PublicKeySet, n_voters: usize) -> bool {
+    pub fn is_split_vote(&self, voters: &S, n_voters: usize) -> bool {
         let most_votes = self
             .candidate_with_most_votes()
             .map(|(_, c)| c)
@@ -142,7 +156,7 @@ impl<T: Proposition> VoteCount<T> {
         self.voters.len() > voters.threshold() && predicted_votes <= voters.threshold()
     }
 
-    pub fn do_we_have_supermajority(&self, voters: &PublicKeySet) -> bool {
+    pub fn do_we_have_supermajority(&self, voters: &S) -> bool {
         let most_votes = self
             .candidate_with_most_votes()
             .map(|(_, c)| c)
@@ -151,7 +165,7 @@ impl<T: Proposition> VoteCount<T> {
         most_votes > voters.threshold()
     }
 
-    pub fn get_decision(&self, voters: &PublicKeySet) -> Result<Option<BTreeMap<T, Signature>>> {
+    pub fn get_decision(&self, voters: &S) -> Result<Option<BTreeMap<T, S::Signature>>> {
         if let Some((_candidate, sm_count)) = self.super_majority_with_most_votes() {
             if sm_count.count > voters.threshold() {
                 let proposals = sm_count
@@ -165,4 +179,32 @@ impl<T: Proposition> VoteCount<T> {
 
         Ok(None)
     }
+
+    pub fn get_certificate(
+        &self,
+        gen: Generation,
+        voters: &S,
+    ) -> Result<Option<DecisionCertificate<T, S>>> {
+        if let Some((candidate, sm_count)) = self.super_majority_with_most_votes() {
+            if sm_count.count > voters.threshold() {
+                return Ok(Some(DecisionCertificate {
+                    gen,
+                    proposals: candidate.proposals.clone(),
+                    signature: voters.combine_signatures(&sm_count.certificate_shares)?,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Ties go to the candidate with the fewest proposals, then to the smallest one, so
+/// that every elder breaks a tie the same way.
+pub(crate) fn most_voted<'a, T: Ord + 'a>(
+    candidates: impl IntoIterator<Item = (&'a Candidate<T>, usize)>,
+) -> Option<(&'a Candidate<T>, usize)> {
+    candidates
+        .into_iter()
+        .max_by_key(|(candidate, c)| (*c, Reverse(candidate.proposals.len()), Reverse(*candidate)))
 }
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use rand::{CryptoRng, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{NodeId, SignatureScheme};
+
+/// The public key of a voter in a scheme where every voter signs with their own key pair,
+/// a super majority of signatures is then kept as is rather than combined.
+pub trait VoterKey: Debug + Copy + Ord + Hash + Serialize + DeserializeOwned {
+    type SecretKey: Debug + Clone + PartialEq + Eq;
+    type Signature: Debug + Copy + Ord + Hash + Serialize + DeserializeOwned;
+
+    fn random_secret_key(rng: impl Rng + CryptoRng) -> Self::SecretKey;
+
+    fn from_secret_key(secret_key: &Self::SecretKey) -> Self;
+
+    fn sign(secret_key: &Self::SecretKey, msg: &[u8]) -> Self::Signature;
+
+    fn is_valid(&self, msg: &[u8], sig: &Self::Signature) -> bool;
+}
+
+/// The elders of a section, each voter is identified by their own public key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PublicKeySet<K> {
+    threshold: usize,
+    public_keys: BTreeMap<NodeId, K>,
+}
+
+impl<K: VoterKey> PublicKeySet<K> {
+    pub fn new(threshold: usize, public_keys: BTreeMap<NodeId, K>) -> Self {
+        Self {
+            threshold,
+            public_keys,
+        }
+    }
+
+    pub fn public_key(&self, voter: NodeId) -> Option<&K> {
+        self.public_keys.get(&voter)
+    }
+}
+
+/// A secret key for every possible `NodeId`, mirrors blsttc's `SecretKeySet` API.
+#[derive(Debug, Clone)]
+pub struct SecretKeySet<K: VoterKey> {
+    threshold: usize,
+    secret_keys: BTreeMap<NodeId, K::SecretKey>,
+}
+
+impl<K: VoterKey> SecretKeySet<K> {
+    pub fn random(threshold: usize, mut rng: impl Rng + CryptoRng) -> Self {
+        let secret_keys = BTreeMap::from_iter(
+            (NodeId::MIN..=NodeId::MAX).map(|id| (id, K::random_secret_key(&mut rng))),
+        );
+        Self {
+            threshold,
+            secret_keys,
+        }
+    }
+
+    pub fn public_keys(&self) -> PublicKeySet<K> {
+        PublicKeySet::new(
+            self.threshold,
+            BTreeMap::from_iter(
+                self.secret_keys
+                    .iter()
+                    .map(|(id, sk)| (*id, K::from_secret_key(sk))),
+            ),
+        )
+    }
+
+    /// Panics if `i` is not a valid `NodeId`.
+    pub fn secret_key_share<I: TryInto<NodeId>>(&self, i: I) -> K::SecretKey
+    where
+        I::Error: Debug,
+    {
+        let id = i.try_into().expect("Index is not a valid NodeId");
+        self.secret_keys[&id].clone()
+    }
+}
+
+/// A super majority of signatures, one per voter.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MultiSignature<S>(pub BTreeMap<NodeId, S>);
+
+impl<K: VoterKey> SignatureScheme for PublicKeySet<K> {
+    type PublicKey = PublicKeySet<K>;
+    type SecretKeyShare = K::SecretKey;
+    type SignatureShare = K::Signature;
+    type Signature = MultiSignature<K::Signature>;
+
+    fn public_key(&self) -> PublicKeySet<K> {
+        self.clone()
+    }
+
+    fn sign(secret_key: &K::SecretKey, msg: &[u8]) -> K::Signature {
+        K::sign(secret_key, msg)
+    }
+
+    fn verify_share(&self, voter: NodeId, msg: &[u8], sig: &K::Signature) -> bool {
+        self.public_key(voter)
+            .map(|public_key| public_key.is_valid(msg, sig))
+            .unwrap_or(false)
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    fn combine_signatures(
+        &self,
+        shares: &BTreeMap<NodeId, K::Signature>,
+    ) -> crate::Result<Self::Signature> {
+        let sigs = BTreeMap::from_iter(
+            shares
+                .iter()
+                .filter(|(voter, _)| self.public_keys.contains_key(voter))
+                .map(|(voter, sig)| (*voter, *sig)),
+        );
+
+        if sigs.len() > self.threshold {
+            Ok(MultiSignature(sigs))
+        } else {
+            Err(crate::Error::NotEnoughSignatureShares)
+        }
+    }
+
+    fn verify(public_key: &PublicKeySet<K>, msg: &[u8], sig: &Self::Signature) -> bool {
+        sig.0.len() > public_key.threshold
+            && sig
+                .0
+                .iter()
+                .all(|(voter, sig)| public_key.verify_share(*voter, msg, sig))
+    }
+}
@@ -4,12 +4,15 @@ use std::fs::File;
 use std::io::Write as IoWrite;
 use std::iter;
 
-use blsttc::{SecretKeySet, SignatureShare};
 use log::info;
 use rand::prelude::{IteratorRandom, StdRng};
 use rand::Rng;
 
-use sn_consensus::{Ballot, Error, Handover, NodeId, Result, SignedVote, Vote, VoteResponse};
+use sn_consensus::{AcceptAll, Error, MaxResolver, NodeId, Result, VoteResponse};
+
+#[path = "scheme.rs"]
+pub mod scheme;
+use scheme::{Ballot, Handover, SecretKeySet, SignatureShare, SignedVote, Vote};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Packet {
@@ -21,21 +24,22 @@ pub struct Packet {
 #[derive(Default, Debug)]
 pub struct Net {
     pub procs: Vec<Handover<u8>>,
-    pub proposals: BTreeSet<u8>,
     pub packets: BTreeMap<NodeId, VecDeque<Packet>>,
     pub delivered_packets: Vec<Packet>,
 }
 
 impl Net {
-    pub fn with_procs(threshold: usize, n: usize, mut rng: &mut StdRng) -> Self {
-        let elders_sk = SecretKeySet::random(threshold, &mut rng);
+    pub fn with_procs(threshold: usize, n: usize, rng: &mut StdRng) -> Self {
+        let elders_sk = SecretKeySet::random(threshold, rng);
 
-        let procs = Vec::from_iter((1..=n).into_iter().map(|i| {
+        let procs = Vec::from_iter((1..=n).map(|i| {
             Handover::from(
                 (i as u8, elders_sk.secret_key_share(i)),
                 elders_sk.public_keys(),
                 n,
                 0,
+                MaxResolver,
+                AcceptAll,
             )
         }));
         Self {
@@ -50,11 +54,9 @@ impl Net {
     }
 
     pub fn consensus_value(&self, proc: usize) -> Option<u8> {
-        self.procs[proc]
-            .consensus
-            .decision
-            .as_ref()
-            .and_then(|decision| self.procs[proc].resolve_votes(&decision.proposals).cloned())
+        let resolution = self.procs[proc].resolution().copied();
+        assert_eq!(resolution, self.procs[proc].resolve());
+        resolution
     }
 
     /// Pick a random public key from the set of procs
@@ -98,7 +100,21 @@ impl Net {
                             .take(n_proposals)
                             .collect::<Result<_>>()
                             .unwrap();
-                        Ballot::SuperMajority { votes, proposals }
+                        let certificate_share = (
+                            self.pick_id(rng),
+                            self.procs
+                                .iter()
+                                .choose(rng)
+                                .unwrap()
+                                .consensus
+                                .sign(&BTreeSet::from_iter(proposals.keys()))
+                                .unwrap(),
+                        );
+                        Ballot::SuperMajority {
+                            votes,
+                            proposals,
+                            certificate_share,
+                        }
                     }
                 }
             }
@@ -4,13 +4,15 @@ use std::fs::File;
 use std::io::Write as IoWrite;
 use std::iter;
 
-use blsttc::{SecretKeySet, SignatureShare};
 use log::info;
 use rand::prelude::{IteratorRandom, StdRng};
 use rand::Rng;
-use sn_consensus::{
-    consensus::VoteResponse, Ballot, Decision, Error, Generation, Membership, NodeId, Reconfig,
-    Result, SignedVote, Vote,
+use sn_consensus::{consensus::VoteResponse, CapacityPolicy, Error, Generation, NodeId, Result};
+
+#[path = "scheme.rs"]
+pub mod scheme;
+use scheme::{
+    Ballot, Decision, Membership, Reconfig, SecretKeySet, SignatureShare, SignedVote, Vote,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -24,20 +26,20 @@ pub struct Packet {
 pub struct Net {
     pub procs: Vec<Membership<u8>>,
     pub reconfigs_by_gen: BTreeMap<Generation, BTreeSet<Reconfig<u8>>>,
-    pub members_at_gen: BTreeMap<Generation, BTreeSet<u8>>,
     pub packets: BTreeMap<NodeId, VecDeque<Packet>>,
     pub delivered_packets: Vec<Packet>,
     pub decisions: BTreeMap<Generation, Decision<Reconfig<u8>>>,
 }
 
 impl Net {
-    pub fn with_procs(threshold: u8, n: u8, mut rng: &mut StdRng) -> Self {
-        let elders_sk = SecretKeySet::random(threshold as usize, &mut rng);
-        let procs = Vec::from_iter((1u8..(n + 1)).into_iter().map(|i| {
+    pub fn with_procs(threshold: u8, n: u8, rng: &mut StdRng) -> Self {
+        let elders_sk = SecretKeySet::random(threshold as usize, rng);
+        let procs = Vec::from_iter((1u8..(n + 1)).map(|i| {
             Membership::from(
                 (i, elders_sk.secret_key_share(i as u64)),
                 elders_sk.public_keys(),
                 n as usize,
+                CapacityPolicy::default(),
             )
         }));
         Self {
@@ -99,7 +101,21 @@ impl Net {
                             .take(n_proposals)
                             .collect::<Result<_>>()
                             .unwrap();
-                        Ballot::SuperMajority { votes, proposals }
+                        let certificate_share = (
+                            self.pick_id(rng),
+                            self.procs
+                                .iter()
+                                .choose(rng)
+                                .unwrap()
+                                .consensus
+                                .sign(&BTreeSet::from_iter(proposals.keys()))
+                                .unwrap(),
+                        );
+                        Ballot::SuperMajority {
+                            votes,
+                            proposals,
+                            certificate_share,
+                        }
                     }
                 }
             }
@@ -187,17 +203,21 @@ impl Net {
             Some(proc) => {
                 let network_decision = self.decisions.get(&packet_gen);
 
-                let proc_decision = proc
-                    .consensus_at_gen(packet_gen)
-                    .ok()
-                    .and_then(|c| c.decision.clone());
+                let proc_consensus = proc.consensus_at_gen(packet_gen).ok();
+                let proc_decision = proc_consensus.and_then(|c| c.decision.clone());
 
                 match (network_decision, proc_decision) {
                     (Some(net_d), Some(proc_d)) => {
-                        assert_eq!(net_d.proposals, proc_d.proposals);
+                        // multi-signature backends may combine a different super majority
+                        // of signatures per proc, only the decided proposals must agree.
+                        assert_eq!(
+                            BTreeSet::from_iter(net_d.proposals.keys()),
+                            BTreeSet::from_iter(proc_d.proposals.keys())
+                        );
                     }
                     (None, Some(proc_d)) => {
-                        assert!(proc_d.validate(&proc.consensus.elders).is_ok());
+                        let elders = &proc_consensus.unwrap().elders;
+                        assert!(proc_d.validate(elders).is_ok());
                         self.decisions.insert(packet_gen, proc_d);
                     }
                     (None | Some(_), None) => (),
@@ -264,6 +284,22 @@ impl Net {
         );
     }
 
+    /// Like `enqueue_anti_entropy` but only the votes `i` is missing are sent.
+    #[allow(dead_code)]
+    pub fn enqueue_missing_votes(&mut self, i: usize, j: usize) {
+        let digest = self.procs[i].digest();
+        let dest = self.procs[i].id();
+        let source = self.procs[j].id();
+
+        self.enqueue_packets(
+            self.procs[j]
+                .missing_votes(&digest)
+                .unwrap()
+                .into_iter()
+                .map(|vote| Packet { source, dest, vote }),
+        );
+    }
+
     pub fn generate_msc(&self, name: &str) -> Result<()> {
         // See: http://www.mcternan.me.uk/mscgen/
         let mut msc = String::from(
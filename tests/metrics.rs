@@ -0,0 +1,155 @@
+#![cfg(feature = "metrics")]
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::{rngs::StdRng, SeedableRng};
+use sn_consensus::metrics::{self, MemoryRecorder};
+use sn_consensus::{CapacityPolicy, Result, VoteResponse};
+
+mod scheme;
+use scheme::{Membership, Reconfig, SecretKeySet, SignedVote};
+
+// The recorder is global, the tests of this file must not run concurrently.
+static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn deliver(
+    procs: &mut [Membership<u8>],
+    vote: SignedVote<Reconfig<u8>>,
+    queue: &mut VecDeque<SignedVote<Reconfig<u8>>>,
+) -> Result<()> {
+    for proc in procs.iter_mut() {
+        if let VoteResponse::Broadcast(vote) = proc.handle_signed_vote(vote.clone())? {
+            queue.push_back(vote);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_metrics_of_membership_rounds() -> Result<()> {
+    let _guard = LOCK.lock().unwrap();
+    let recorder = MemoryRecorder::default();
+    metrics::set_recorder(recorder.clone());
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(2, &mut rng);
+    let mut procs = Vec::from_iter((1u8..=4).map(|i| {
+        Membership::from(
+            (i, elders_sk.secret_key_share(i as u64)),
+            elders_sk.public_keys(),
+            4,
+            CapacityPolicy::default(),
+        )
+    }));
+
+    // everyone proposes a different join, the votes are split
+    let mut queue = VecDeque::new();
+    for (i, proc) in procs.iter_mut().enumerate() {
+        queue.push_back(proc.propose(Reconfig::Join(i as u8))?);
+    }
+    // every delivery takes a second on the clock given to `tick`
+    let mut now = Duration::ZERO;
+    while let Some(vote) = queue.pop_front() {
+        for proc in procs.iter_mut() {
+            proc.tick(now);
+        }
+        deliver(&mut procs, vote, &mut queue)?;
+        now += Duration::from_secs(1);
+    }
+    metrics::clear_recorder();
+
+    let engine = [("engine", "vote")];
+    assert!(procs.iter().all(|p| p.gen == 1));
+    assert_eq!(recorder.counter("sn_consensus_decisions_total", &engine), 4);
+    assert_eq!(
+        recorder.counter("sn_consensus_generations_advanced_total", &engine),
+        4
+    );
+    assert!(recorder.counter("sn_consensus_votes_received_total", &engine) > 4);
+    assert!(recorder.counter("sn_consensus_split_votes_total", &engine) > 0);
+    assert_eq!(
+        recorder.counter("sn_consensus_faults_detected_total", &engine),
+        0
+    );
+
+    let merges = recorder.histogram("sn_consensus_merges_per_generation", &engine);
+    assert_eq!(merges.len(), 4);
+    assert_eq!(
+        merges.iter().sum::<f64>(),
+        recorder.counter("sn_consensus_merges_cast_total", &engine) as f64
+    );
+    let latencies = recorder.histogram("sn_consensus_decision_seconds", &engine);
+    assert_eq!(latencies.len(), 4);
+    assert!(latencies
+        .iter()
+        .all(|latency| *latency >= 1.0 && latency.fract() == 0.0));
+
+    let members = procs[0].members(1)?.len() as f64;
+    assert_eq!(
+        recorder.gauge("sn_consensus_membership_generation", &[]),
+        Some(1.0)
+    );
+    assert_eq!(
+        recorder.gauge("sn_consensus_membership_members", &[]),
+        Some(members)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_metrics_of_mvba_rounds() {
+    use sn_consensus::mvba::{bundle::Outgoing, consensus::Consensus, tag::Domain};
+    use std::rc::Rc;
+
+    let _guard = LOCK.lock().unwrap();
+    let recorder = MemoryRecorder::default();
+    metrics::set_recorder(recorder.clone());
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let sec_key_set = blsttc::SecretKeySet::random(1, &mut rng);
+    let parties = Vec::from_iter(0..4usize);
+    let mut cons = Vec::from_iter(parties.iter().map(|p| {
+        Consensus::init(
+            Domain::new("metrics", 3),
+            *p,
+            sec_key_set.secret_key_share(p),
+            sec_key_set.public_keys(),
+            parties.clone(),
+            Rc::new(|_, _: &Vec<u8>| true),
+        )
+    }));
+
+    let mut outgoing = VecDeque::new();
+    for c in cons.iter_mut() {
+        outgoing.extend(c.propose(vec![1, 2, 3]).unwrap());
+    }
+    while let Some(msg) = outgoing.pop_front() {
+        for (id, c) in cons.iter_mut().enumerate() {
+            let bundle = match &msg {
+                Outgoing::Gossip(bundle) => bundle,
+                Outgoing::Direct(dest, bundle) if *dest == id => bundle,
+                Outgoing::Direct(..) => continue,
+            };
+            outgoing.extend(c.process_bundle(bundle).unwrap());
+        }
+    }
+    metrics::clear_recorder();
+
+    let engine = [("engine", "mvba")];
+    assert!(cons.iter().all(|c| c.decision().is_some()));
+    assert_eq!(recorder.counter("sn_consensus_decisions_total", &engine), 4);
+    assert_eq!(
+        recorder
+            .histogram("sn_consensus_decision_seconds", &engine)
+            .len(),
+        4
+    );
+    assert!(recorder.counter("sn_consensus_votes_received_total", &engine) > 0);
+    for module in ["vcbc", "abba", "mvba"] {
+        let payload_bytes =
+            recorder.histogram("sn_consensus_bundle_payload_bytes", &[("module", module)]);
+        assert!(!payload_bytes.is_empty(), "no {module} bundles recorded");
+        assert!(payload_bytes.iter().all(|bytes| *bytes > 0.0));
+    }
+}
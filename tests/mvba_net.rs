@@ -0,0 +1,56 @@
+use log::info;
+use rand::{prelude::StdRng, Rng};
+
+use sn_consensus::{
+    mvba::bundle::Outgoing, Error, MvbaAdapter, MvbaApplication, NodeId, Proposition, Result,
+    SignedVote, VoteResponse,
+};
+
+#[allow(clippy::large_enum_variant)]
+enum Message<T: Proposition> {
+    Mvba(Outgoing),
+    Vote { voter: NodeId, vote: SignedVote<T> },
+}
+
+/// Delivers the MVBA messages of the adapters and the votes certifying their agreement
+/// in a random order until none are left.
+pub fn drain<A: MvbaApplication + 'static>(
+    adapters: &mut [MvbaAdapter<A>],
+    outgoing: Vec<Outgoing>,
+    rng: &mut StdRng,
+) -> Result<()> {
+    let mut msgs = Vec::from_iter(outgoing.into_iter().map(Message::Mvba));
+    while !msgs.is_empty() {
+        let msg = msgs.remove(rng.gen_range(0..msgs.len()));
+        for adapter in adapters.iter_mut() {
+            let id = adapter.id();
+            match &msg {
+                Message::Mvba(outgoing) => {
+                    let bundle = match outgoing {
+                        Outgoing::Gossip(bundle) => bundle,
+                        Outgoing::Direct(dest, bundle) if *dest == id as usize => bundle,
+                        Outgoing::Direct(..) => continue,
+                    };
+                    match adapter.handle_bundle(bundle) {
+                        Ok(outgoing) => msgs.extend(outgoing.into_iter().map(Message::Mvba)),
+                        Err(Error::Mvba(err)) => info!("[MVBA] {id} dropped message: {err}"),
+                        Err(err) => return Err(err),
+                    }
+                }
+                Message::Vote { voter, .. } if *voter == id => continue,
+                Message::Vote { vote, .. } => {
+                    if let VoteResponse::Broadcast(vote) =
+                        adapter.handle_signed_vote(vote.clone())?
+                    {
+                        msgs.push(Message::Vote { voter: id, vote });
+                    }
+                }
+            }
+
+            if let Some(vote) = adapter.take_vote() {
+                msgs.push(Message::Vote { voter: id, vote });
+            }
+        }
+    }
+    Ok(())
+}
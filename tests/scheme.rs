@@ -0,0 +1,26 @@
+// The crypto backend the tests run on: blsttc, unless the `ed25519` or `bad_crypto`
+// feature is enabled, `quickcheck_forever.sh` uses `bad_crypto` to run the props fast.
+#![allow(dead_code)]
+
+use sn_consensus::SignatureScheme;
+
+#[cfg(feature = "ed25519")]
+pub use sn_consensus::ed25519::{PublicKeySet, SecretKeySet};
+
+#[cfg(all(feature = "bad_crypto", not(feature = "ed25519")))]
+pub use sn_consensus::bad_crypto::{PublicKeySet, SecretKeySet};
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+pub use sn_consensus::{PublicKeySet, SecretKeySet};
+
+pub type SignatureShare = <PublicKeySet as SignatureScheme>::SignatureShare;
+pub type Ballot<T> = sn_consensus::Ballot<T, PublicKeySet>;
+pub type Vote<T> = sn_consensus::Vote<T, PublicKeySet>;
+pub type SignedVote<T> = sn_consensus::SignedVote<T, PublicKeySet>;
+pub type Decision<T> = sn_consensus::Decision<T, PublicKeySet>;
+pub type Fault<T> = sn_consensus::Fault<T, PublicKeySet>;
+pub type Reconfig<T> = sn_consensus::Reconfig<T, PublicKeySet>;
+pub type Membership<T> = sn_consensus::Membership<T, PublicKeySet>;
+pub type Handover<T> = sn_consensus::Handover<T, PublicKeySet>;
+pub type HandoverChain<T> = sn_consensus::HandoverChain<T, PublicKeySet>;
+pub type Consensus<T> = sn_consensus::Consensus<T, PublicKeySet>;
@@ -1,10 +1,24 @@
-use blsttc::{SecretKeySet, SecretKeyShare};
 use log::info;
-use rand::{prelude::StdRng, Rng, SeedableRng};
+use quickcheck::TestResult;
+use quickcheck_macros::quickcheck;
+use rand::{
+    prelude::{IteratorRandom, StdRng},
+    Rng, SeedableRng,
+};
 
 mod handover_net;
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+mod mvba_net;
+use handover_net::scheme::{
+    Ballot, Consensus, Decision, Handover, HandoverChain, SecretKeySet, SignedVote, Vote,
+};
 use handover_net::{Net, Packet};
-use sn_consensus::{Ballot, Error, Handover, Result, SignedVote, Vote};
+use sn_consensus::{
+    AcceptAll, ConsensusEvent, Error, FnResolver, FnValidator, MaxResolver, MemoryEventLog,
+    MemoryVoteLog, MinResolver, MostSupportedResolver, Resolver, Result, VoteResponse,
+};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
 
 static INIT: std::sync::Once = std::sync::Once::new();
 
@@ -88,6 +102,8 @@ fn test_handover_reject_voter_changing_proposal_when_one_is_in_progress() -> Res
         elders_sk.public_keys(),
         1,
         0,
+        MaxResolver,
+        AcceptAll,
     );
 
     proc.propose(111)?;
@@ -99,6 +115,114 @@ fn test_handover_reject_voter_changing_proposal_when_one_is_in_progress() -> Res
     Ok(())
 }
 
+#[test]
+fn test_handover_reject_changing_proposal_after_restart() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(1, &mut rng);
+    let vote_log = MemoryVoteLog::default();
+    let mut proc: Handover<u8> = Handover::from(
+        (0, elders_sk.secret_key_share(0)),
+        elders_sk.public_keys(),
+        2,
+        0,
+        MaxResolver,
+        AcceptAll,
+    );
+    proc.consensus = Consensus::with_vote_log(
+        (0, elders_sk.secret_key_share(0)),
+        elders_sk.public_keys(),
+        2,
+        Arc::new(vote_log.clone()),
+    )?;
+    proc.propose(111)?;
+
+    // the proc crashes and comes back up with its vote log
+    let mut proc: Handover<u8> = Handover::from(
+        (0, elders_sk.secret_key_share(0)),
+        elders_sk.public_keys(),
+        2,
+        0,
+        MaxResolver,
+        AcceptAll,
+    );
+    proc.consensus = Consensus::with_vote_log(
+        (0, elders_sk.secret_key_share(0)),
+        elders_sk.public_keys(),
+        2,
+        Arc::new(vote_log),
+    )?;
+
+    assert!(matches!(
+        proc.propose(222),
+        Err(Error::AttemptedFaultyProposal)
+    ));
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0} is not a known candidate")]
+struct UnknownCandidate(u8);
+
+#[test]
+fn test_handover_drops_votes_with_rejected_proposals() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(1, &mut rng);
+    let known_candidates = |proposal: &u8| match proposal {
+        0..=100 => Ok(()),
+        _ => Err(UnknownCandidate(*proposal).into()),
+    };
+    let mut procs = Vec::from_iter((0..3u8).map(|id| {
+        Handover::<u8>::from(
+            (id, elders_sk.secret_key_share(id as u64)),
+            elders_sk.public_keys(),
+            3,
+            0,
+            MaxResolver,
+            FnValidator(known_candidates),
+        )
+    }));
+
+    match procs[0].propose(111) {
+        Err(Error::ProposalRejected(err)) => {
+            assert_eq!(err.downcast_ref::<UnknownCandidate>().unwrap().0, 111)
+        }
+        res => panic!("expected the validator to reject the proposal, got {res:?}"),
+    }
+
+    // a faulty elder signs the proposal anyway
+    let rejected_vote = procs[0].sign_vote(Vote {
+        gen: 0,
+        ballot: Ballot::Propose(111),
+        faults: Default::default(),
+    })?;
+    assert!(matches!(
+        procs[1].handle_signed_vote(rejected_vote.clone()),
+        Err(Error::ProposalRejected(_))
+    ));
+    assert!(procs[1].consensus.votes.is_empty());
+
+    // and merges it with a valid proposal
+    let valid_vote = procs[0].sign_vote(Vote {
+        gen: 0,
+        ballot: Ballot::Propose(42),
+        faults: Default::default(),
+    })?;
+    let merge_vote = procs[0].sign_vote(Vote {
+        gen: 0,
+        ballot: Ballot::Merge(BTreeSet::from_iter([rejected_vote, valid_vote])),
+        faults: Default::default(),
+    })?;
+    assert!(matches!(
+        procs[2].handle_signed_vote(merge_vote),
+        Err(Error::ProposalRejected(_))
+    ));
+    assert!(procs[2].consensus.votes.is_empty());
+
+    Ok(())
+}
+
 #[test]
 fn test_handover_reject_vote_from_non_member() -> Result<()> {
     init();
@@ -109,6 +233,8 @@ fn test_handover_reject_vote_from_non_member() -> Result<()> {
         elders_sk.public_keys(),
         1,
         0,
+        MaxResolver,
+        AcceptAll,
     );
     let elders_sk = SecretKeySet::random(0, &mut rng);
     let mut p1 = Handover::<u8>::from(
@@ -116,6 +242,8 @@ fn test_handover_reject_vote_from_non_member() -> Result<()> {
         elders_sk.public_keys(),
         1,
         0,
+        MaxResolver,
+        AcceptAll,
     );
 
     let vote = p1.propose(111)?;
@@ -155,12 +283,16 @@ fn test_handover_reject_votes_with_invalid_signatures() -> Result<()> {
         elders_sk.public_keys(),
         1,
         0,
+        MaxResolver,
+        AcceptAll,
     );
     let ballot = Ballot::Propose(rng.gen());
     let gen = proc.gen;
     let voter = 1;
     let bytes = bincode::serialize(&(&ballot, &gen))?;
-    let sig = rng.gen::<SecretKeyShare>().sign(bytes);
+    let sig = SecretKeySet::random(0, &mut rng)
+        .secret_key_share(voter as u64)
+        .sign(&bytes);
     let vote = Vote {
         gen,
         ballot,
@@ -178,11 +310,11 @@ fn test_handover_reject_votes_with_invalid_signatures() -> Result<()> {
 fn test_handover_split_vote() -> eyre::Result<()> {
     init();
     let mut rng = StdRng::from_seed([0u8; 32]);
-    for nprocs in 1..7 {
+    for nprocs in 1usize..7 {
         println!("[TEST] testing with {nprocs} elders");
 
         // make network of nprocs elders
-        let mut net = Net::with_procs((nprocs * 2 + 2) / 3, nprocs, &mut rng);
+        let mut net = Net::with_procs((nprocs * 2).div_ceil(3), nprocs, &mut rng);
 
         // make each elder propose a different thing
         for i in 0..net.procs.len() {
@@ -208,7 +340,7 @@ fn test_handover_split_vote() -> eyre::Result<()> {
 fn test_handover_round_robin_split_vote() -> eyre::Result<()> {
     init();
     let mut rng = StdRng::from_seed([0u8; 32]);
-    for nprocs in 1..7 {
+    for nprocs in 1usize..7 {
         println!("[TEST] testing with {nprocs} elder(s)");
 
         // make network of nprocs elders
@@ -244,12 +376,53 @@ fn test_handover_round_robin_split_vote() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_handover_resolvers() -> eyre::Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    // the seed is only agreed on when the section signature is unique
+    #[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+    for proc in net.procs.iter_mut() {
+        proc.resolver = Arc::new(sn_consensus::SeededRandomResolver);
+    }
+
+    for (i, proposal) in [1, 1, 2, 3].into_iter().enumerate() {
+        let id = net.procs[i].id();
+        let vote = net.procs[i].propose(proposal)?;
+        net.broadcast(id, vote);
+    }
+    while !net.packets.is_empty() {
+        for i in 0..net.procs.len() {
+            net.deliver_packet_from_source(net.procs[i].id())?;
+        }
+    }
+
+    // every elder resolves the decision to the same value
+    let resolution = net.consensus_value(0);
+    assert!(resolution.is_some());
+    for i in 0..net.procs.len() {
+        assert_eq!(net.consensus_value(i), resolution);
+    }
+
+    let decision: Decision<u8> = net.procs[0].consensus.decision.clone().unwrap();
+    assert_eq!(net.procs[0].resolution().copied(), resolution);
+    assert_eq!(Vec::from_iter(decision.proposals.keys()), vec![&1, &2, &3]);
+    assert_eq!(MaxResolver.resolve(&decision), Some(3));
+    assert_eq!(MinResolver.resolve(&decision), Some(1));
+    assert_eq!(MostSupportedResolver.resolve(&decision), Some(1));
+    let even = FnResolver(|d: &Decision<u8>| d.proposals.keys().find(|p| *p % 2 == 0).cloned());
+    assert_eq!(even.resolve(&decision), Some(2));
+
+    Ok(())
+}
+
 #[test]
 fn test_handover_simple_proposal() {
     // make network of n elders
-    let n = 4;
+    let n: usize = 4;
     let mut rng = StdRng::from_seed([0u8; 32]);
-    let mut net = Net::with_procs((n * 2 + 2) / 3, n, &mut rng);
+    let mut net = Net::with_procs((n * 2).div_ceil(3), n, &mut rng);
 
     // release a proposal
     let p0 = net.procs[0].id();
@@ -268,3 +441,251 @@ fn test_handover_simple_proposal() {
         assert_eq!(decision, first_voters_value);
     }
 }
+
+#[test]
+fn test_handover_chain_advances_generations() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(1, &mut rng);
+    let mut procs = Vec::from_iter((0..4).map(|i| {
+        HandoverChain::<u8>::from(
+            (i as u8, elders_sk.secret_key_share(i)),
+            elders_sk.public_keys(),
+            4,
+            7,
+            MaxResolver,
+            AcceptAll,
+        )
+    }));
+
+    let event_log = MemoryEventLog::default();
+    procs[1].set_observer(event_log.clone());
+
+    // p3 is offline while the others decide two generations
+    let online = 3;
+    for proposal in [10, 20] {
+        let mut queue = VecDeque::from([procs[0].propose(proposal)?]);
+        while let Some(vote) = queue.pop_front() {
+            for proc in procs.iter_mut().take(online) {
+                if let VoteResponse::Broadcast(vote) = proc.handle_signed_vote(vote.clone())? {
+                    queue.push_back(vote);
+                }
+            }
+        }
+    }
+
+    for proc in procs.iter().take(online) {
+        assert_eq!(proc.gen(), 9);
+        assert_eq!(proc.resolution(7), Some(&10));
+        assert_eq!(proc.resolution(8), Some(&20));
+        assert!(proc.consensus_at_gen(7)?.decision.is_some());
+    }
+
+    let advanced = Vec::from_iter(
+        event_log
+            .events()
+            .into_iter()
+            .filter(|e| matches!(e, ConsensusEvent::GenerationAdvanced { .. })),
+    );
+    assert_eq!(
+        advanced,
+        [
+            ConsensusEvent::GenerationAdvanced { gen: 8 },
+            ConsensusEvent::GenerationAdvanced { gen: 9 }
+        ]
+    );
+
+    // p3 comes back and catches up through anti-entropy
+    assert_eq!(procs[3].gen(), 7);
+    for vote in procs[1].anti_entropy(7)? {
+        procs[3].handle_signed_vote(vote)?;
+    }
+    assert_eq!(procs[3].gen(), 9);
+    assert_eq!(procs[3].resolution(7), Some(&10));
+    assert_eq!(procs[3].resolution(8), Some(&20));
+
+    // an elder that is already caught up only gets the current generation's votes
+    assert!(procs[1].anti_entropy(9)?.is_empty());
+    assert!(procs[1].missing_votes(&procs[3].digest())?.is_empty());
+    let vote = procs[0].propose(30)?;
+    assert_eq!(procs[0].missing_votes(&procs[3].digest())?, vec![vote]);
+
+    assert!(matches!(
+        procs[3].consensus_at_gen(10),
+        Err(Error::BadGeneration {
+            requested_gen: 10,
+            gen: 9
+        })
+    ));
+    Ok(())
+}
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+#[test]
+fn test_handover_chain_mvba_engine_advances_generations() -> Result<()> {
+    use sn_consensus::MvbaAdapter;
+    use std::{cell::RefCell, rc::Rc};
+
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(2, &mut rng);
+    let procs = Vec::from_iter((0..4).map(|i| {
+        Rc::new(RefCell::new(HandoverChain::<u8>::from(
+            (i as u8, elders_sk.secret_key_share(i)),
+            elders_sk.public_keys(),
+            4,
+            7,
+            MaxResolver,
+            AcceptAll,
+        )))
+    }));
+    let parties = Vec::from_iter(0..4);
+
+    for gen in 7..=8 {
+        let mut adapters = Vec::from_iter(
+            procs
+                .iter()
+                .map(|proc| MvbaAdapter::new(proc.clone(), parties.clone())),
+        );
+        let mut outgoing = Vec::new();
+        for adapter in adapters.iter_mut() {
+            let proposal = adapter.id() + 10 * gen as u8;
+            outgoing.extend(adapter.propose(proposal)?);
+        }
+        mvba_net::drain(&mut adapters, outgoing, &mut rng)?;
+
+        let mvba_decision = adapters[0].decision().cloned().unwrap();
+        for proc in procs.iter() {
+            let proc = proc.borrow();
+            assert_eq!(proc.gen(), gen + 1);
+            assert_eq!(proc.resolution(gen), Some(&mvba_decision.proposal));
+
+            let consensus = proc.consensus_at_gen(gen)?;
+            let decision = consensus.decision.as_ref().unwrap();
+            assert!(decision.validate(&consensus.elders).is_ok());
+            assert_eq!(decision.proposals.len(), 1);
+            assert!(consensus.certificate.is_some());
+        }
+    }
+    Ok(())
+}
+
+#[quickcheck]
+fn prop_split_votes_decide_under_adversarial_scheduling(
+    n: u8,
+    proposals: Vec<u8>,
+    equivocate: bool,
+    exhaust: bool,
+    seed: u128,
+) -> eyre::Result<TestResult> {
+    init();
+    let n = n as usize % 6 + 1;
+    if proposals.len() < n {
+        return Ok(TestResult::discard());
+    }
+
+    let mut seed_buf = [0u8; 32];
+    seed_buf[0..16].copy_from_slice(&seed.to_le_bytes());
+    let mut rng = StdRng::from_seed(seed_buf);
+    let mut net = Net::with_procs((2 * n) / 3, n, &mut rng);
+    let event_logs = Vec::from_iter(net.procs.iter_mut().map(|proc| {
+        let event_log = MemoryEventLog::default();
+        proc.consensus.set_observer(event_log.clone());
+        event_log
+    }));
+
+    // proposals are drawn from a small domain so that split votes are likely
+    for (i, proposal) in proposals.into_iter().take(n).enumerate() {
+        let id = net.procs[i].id();
+        let vote = net.procs[i].propose(proposal % 3)?;
+        net.broadcast(id, vote);
+    }
+
+    // the last elder merges its own proposal until it reaches the merge bound for the
+    // single voter it counted, the others have to break the tie then
+    if exhaust && n > 1 {
+        let pn = net.procs[n - 1].id();
+        for _ in 0..2 {
+            let merge = net.procs[n - 1].sign_vote(Vote {
+                gen: 0,
+                ballot: Ballot::Merge(BTreeSet::from_iter([
+                    net.procs[n - 1].consensus.votes[&pn].clone()
+                ])),
+                faults: Default::default(),
+            })?;
+            let vote = net.procs[n - 1].consensus.cast_vote(merge)?;
+            net.broadcast(pn, vote);
+        }
+    }
+
+    // the first elder equivocates to half of the others, if the network can tolerate it
+    let faulty = equivocate && n >= 4;
+    if faulty {
+        let p0 = net.procs[0].id();
+        let proposal = net.procs[0].consensus.votes[&p0].proposals();
+        let other_proposal = (proposal.into_iter().next().unwrap() + 1) % 3;
+        let vote = net.procs[0].sign_vote(Vote {
+            gen: 0,
+            ballot: Ballot::Propose(other_proposal),
+            faults: Default::default(),
+        })?;
+        let packets = Vec::from_iter(net.procs.iter().skip(n / 2).map(|p| Packet {
+            source: p0,
+            dest: p.id(),
+            vote: vote.clone(),
+        }));
+        net.enqueue_packets(packets);
+    }
+
+    // adversarial scheduling, the least progressed votes are delivered first so that
+    // the votes stay split for as long as possible
+    let progress = |packet: &Packet| match packet.vote.vote.ballot {
+        Ballot::Propose(_) => 0,
+        Ballot::Merge(_) => 1,
+        Ballot::SuperMajority { .. } => 2,
+    };
+    loop {
+        let fronts = Vec::from_iter(
+            net.packets
+                .iter()
+                .filter_map(|(source, queue)| Some((*source, progress(queue.front()?)))),
+        );
+        let Some(least) = fronts.iter().map(|(_, progress)| *progress).min() else {
+            break;
+        };
+        let source = fronts
+            .iter()
+            .filter(|(_, progress)| *progress == least)
+            .map(|(source, _)| *source)
+            .choose(&mut rng)
+            .unwrap();
+        net.deliver_packet_from_source(source)?;
+        net.purge_empty_queues();
+    }
+
+    let decided_proposals = |proc: &Handover<u8>| {
+        proc.consensus
+            .decision
+            .as_ref()
+            .map(|d| BTreeSet::from_iter(d.proposals.keys().copied()))
+    };
+    for proc in net.procs.iter() {
+        assert!(decided_proposals(proc).is_some());
+    }
+    // an elder merges at most once for each proposal it learns of and once for each
+    // faulty elder, i.e. twice per elder
+    for event_log in event_logs.iter() {
+        let merges = event_log
+            .events()
+            .into_iter()
+            .filter(|e| matches!(e, ConsensusEvent::MergeCast { .. }))
+            .count();
+        assert!(merges <= 2 * n, "{merges} merges with {n} elders");
+    }
+    let honest = Vec::from_iter(net.procs.iter().skip(faulty as usize));
+    for proc in honest.iter() {
+        assert_eq!(decided_proposals(proc), decided_proposals(honest[0]));
+    }
+
+    Ok(TestResult::passed())
+}
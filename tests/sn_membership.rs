@@ -1,4 +1,3 @@
-use blsttc::{SecretKeySet, SecretKeyShare};
 use eyre::eyre;
 use log::info;
 use membership_net::{Net, Packet};
@@ -9,15 +8,28 @@ use rand::{
 use std::{
     collections::{BTreeMap, BTreeSet},
     iter,
+    sync::Arc,
+    time::Duration,
 };
 
 mod membership_net;
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+mod mvba_net;
 
+use membership_net::scheme::{
+    Ballot, Fault, Membership, PublicKeySet, Reconfig, SecretKeySet, SignedVote, Vote,
+};
 use quickcheck::{Arbitrary, Gen, TestResult};
 use quickcheck_macros::quickcheck;
 use sn_consensus::{
-    Ballot, Error, Fault, Generation, Membership, Reconfig, Result, SignedVote, Vote,
+    CapacityPolicy, ConsensusEvent, Error, FileStore, Generation, MembershipPolicy,
+    MembershipState, MemoryEventLog, MemoryStore, Persisted, PolicyError, Record, Result,
+    SignatureScheme, Store, TimeoutResponse, VoteDigest,
 };
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+use sn_consensus::{MvbaAdapter, VoteResponse};
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+use std::{cell::RefCell, rc::Rc};
 
 static INIT: std::sync::Once = std::sync::Once::new();
 
@@ -36,6 +48,7 @@ fn test_membership_reject_changing_reconfig_when_one_is_in_progress() -> Result<
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
     proc.propose(Reconfig::Join(rng.gen()))?;
     assert!(matches!(
@@ -54,12 +67,14 @@ fn test_membership_reject_vote_from_non_member() -> Result<()> {
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
     let elders_sk = SecretKeySet::random(0, &mut rng);
     let mut p1 = Membership::<u8>::from(
         (1, elders_sk.secret_key_share(1)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
 
     let vote = p1.propose(Reconfig::Join(rng.gen()))?;
@@ -77,12 +92,13 @@ fn test_membership_reject_join_if_actor_is_already_a_member() {
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
-    proc.force_join(111);
+    proc.force_join(111).unwrap();
 
     assert!(matches!(
         proc.propose(Reconfig::Join(111)),
-        Err(Error::JoinRequestForExistingMember { .. })
+        Err(Error::JoinRequestForExistingMember)
     ));
 }
 
@@ -95,12 +111,13 @@ fn test_membership_reject_leave_if_actor_is_not_a_member() {
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
-    proc.force_join(111);
+    proc.force_join(111).unwrap();
 
     assert!(matches!(
         proc.propose(Reconfig::Leave(222)),
-        Err(Error::LeaveRequestForNonMember { .. })
+        Err(Error::LeaveRequestForNonMember)
     ));
 }
 
@@ -113,6 +130,7 @@ fn test_membership_reject_votes_with_invalid_signatures() -> Result<()> {
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
     let ballot = Ballot::Propose(Reconfig::Join(rng.gen()));
     let gen = proc.gen + 1;
@@ -123,7 +141,9 @@ fn test_membership_reject_votes_with_invalid_signatures() -> Result<()> {
     };
     let bytes = vote.to_bytes()?;
     let voter = 0;
-    let sig = rng.gen::<SecretKeyShare>().sign(bytes);
+    let sig = SecretKeySet::random(0, &mut rng)
+        .secret_key_share(voter as u64)
+        .sign(&bytes);
     let resp = proc.handle_signed_vote(SignedVote { vote, voter, sig });
     assert!(matches!(resp, Err(Error::InvalidElderSignature)));
     Ok(())
@@ -161,6 +181,64 @@ fn test_membership_split_vote() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_membership_observer_sees_state_transitions() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let event_log = MemoryEventLog::default();
+    net.procs[0].set_observer(event_log.clone());
+
+    for i in 0..net.procs.len() {
+        let a_i = net.procs[i].id();
+        let vote = net.procs[i].propose(Reconfig::Join(i as u8))?;
+        net.broadcast(a_i, vote);
+    }
+    net.drain_queued_packets()?;
+
+    let proc = &net.procs[0];
+    assert_eq!(proc.gen, 1);
+    let decided = Vec::from_iter(
+        proc.consensus_at_gen(1)?
+            .decision
+            .as_ref()
+            .unwrap()
+            .proposals
+            .keys()
+            .cloned(),
+    );
+
+    let events = event_log.events();
+    assert!(events.contains(&ConsensusEvent::VoteReceived { gen: 1, voter: 2 }));
+    assert!(events.contains(&ConsensusEvent::SplitVote { gen: 1 }));
+    assert!(events.contains(&ConsensusEvent::MergeCast { gen: 1, round: 1 }));
+    assert!(events.contains(&ConsensusEvent::SuperMajorityCast { gen: 1 }));
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, ConsensusEvent::FaultDetected { .. })));
+    assert_eq!(
+        &events[events.len() - 2..],
+        [
+            ConsensusEvent::Decided {
+                gen: 1,
+                proposals: decided
+            },
+            ConsensusEvent::GenerationAdvanced { gen: 2 }
+        ]
+    );
+
+    // the observer carries over to the next generation
+    let vote = net.procs[0].propose(Reconfig::Join(9))?;
+    net.broadcast(net.procs[0].id(), vote);
+    net.drain_queued_packets()?;
+    assert_eq!(
+        event_log.events().last(),
+        Some(&ConsensusEvent::GenerationAdvanced { gen: 3 })
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_membership_round_robin_split_vote() -> Result<()> {
     init();
@@ -261,6 +339,556 @@ fn test_membership_simple_proposal() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_membership_decision_certificate_is_verifiable_with_section_key() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Join(0))?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    let section_key = SignatureScheme::public_key(&net.procs[0].consensus.elders);
+    for p in net.procs.iter() {
+        let cert = p.consensus_at_gen(1)?.certificate.clone().unwrap();
+        assert_eq!(cert.gen, 1);
+        assert_eq!(cert.proposals, BTreeSet::from_iter([Reconfig::Join(0)]));
+        assert!(cert.verify(&section_key).is_ok());
+
+        let mut wrong_gen = cert.clone();
+        wrong_gen.gen = 2;
+        assert!(matches!(
+            wrong_gen.verify(&section_key),
+            Err(Error::InvalidDecisionCertificate)
+        ));
+
+        let mut wrong_proposals = cert;
+        wrong_proposals.proposals.insert(Reconfig::Join(1));
+        assert!(matches!(
+            wrong_proposals.verify(&section_key),
+            Err(Error::InvalidDecisionCertificate)
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_membership_checkpoint_prunes_history() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let store = MemoryStore::default();
+    restore_proc_from_store(&mut net, 0, store.clone())?;
+
+    for member in 0..4 {
+        let p0 = net.procs[0].id();
+        let vote = net.procs[0].propose(Reconfig::Join(member))?;
+        net.broadcast(p0, vote);
+        net.drain_queued_packets()?;
+    }
+    assert_eq!(net.procs[0].gen, 4);
+
+    let members_before_checkpoint = Vec::from_iter((2..=4).map(|gen| net.procs[0].members(gen)));
+
+    // the current generation can't be checkpointed
+    assert!(matches!(
+        net.procs[0].checkpoint(4),
+        Err(Error::InvalidGeneration(4))
+    ));
+
+    net.procs[0].checkpoint(2)?;
+    let proc = &net.procs[0];
+    let checkpoint = proc.checkpoint.clone().unwrap();
+    assert_eq!(checkpoint.gen, 2);
+    assert_eq!(checkpoint.members, BTreeSet::from_iter([0, 1]));
+    assert!(checkpoint.validate(&proc.consensus.elders).is_ok());
+
+    // the certificate must prove the checkpointed decision
+    let mut forged = checkpoint.clone();
+    forged.certificate.gen = 3;
+    assert!(matches!(
+        forged.validate(&proc.consensus.elders),
+        Err(Error::InvalidDecisionCertificate)
+    ));
+    let mut forged = checkpoint.clone();
+    forged.certificate = proc.history[&3].certificate.clone().unwrap();
+    assert!(forged.validate(&proc.consensus.elders).is_err());
+    assert_eq!(Vec::from_iter(proc.history.keys().copied()), vec![2, 3, 4]);
+
+    // generations at or after the checkpoint are still available
+    for (gen, members) in (2..=4).zip(members_before_checkpoint) {
+        assert_eq!(proc.members(gen)?, members?);
+        assert!(proc.consensus_at_gen(gen).is_ok());
+    }
+    assert!(proc.anti_entropy(1).is_ok());
+
+    // and those before it are reported as pruned
+    assert!(matches!(
+        proc.members(1),
+        Err(Error::PrunedGeneration {
+            requested_gen: 1,
+            checkpoint_gen: 2
+        })
+    ));
+    assert!(matches!(
+        proc.consensus_at_gen(1),
+        Err(Error::PrunedGeneration { .. })
+    ));
+    assert!(matches!(
+        proc.anti_entropy(0),
+        Err(Error::PrunedGeneration { .. })
+    ));
+
+    // the checkpoint survives a restart
+    restore_proc_from_store(&mut net, 0, store)?;
+    assert_eq!(net.procs[0].checkpoint, Some(checkpoint));
+    assert_eq!(net.procs[0].members(4)?, BTreeSet::from_iter([0, 1, 2, 3]));
+    Ok(())
+}
+
+#[test]
+fn test_membership_missing_votes_only_sends_what_the_peer_lacks() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(1, 4, &mut rng);
+
+    // p4 is offline while the others decide two generations
+    let straggler = net.procs.pop().unwrap();
+    for member in 0..2 {
+        let p1 = net.procs[0].id();
+        let vote = net.procs[0].propose(Reconfig::Join(member))?;
+        net.broadcast(p1, vote);
+        net.drain_queued_packets()?;
+    }
+    assert_eq!(net.procs[0].gen, 2);
+
+    // a straggler is sent a single vote per decision it missed
+    let digest = straggler.digest();
+    assert_eq!(digest.gen, 1);
+    assert_eq!(net.procs[0].missing_votes(&digest)?.len(), 2);
+
+    net.procs.push(straggler);
+    net.enqueue_missing_votes(3, 0);
+    net.drain_queued_packets()?;
+    assert_eq!(net.procs[3].gen, 2);
+    assert_eq!(net.procs[3].members(2)?, net.procs[0].members(2)?);
+
+    // a vote in progress is only sent to those who haven't seen it yet
+    let vote = net.procs[0].propose(Reconfig::Join(2))?;
+    net.procs[1].handle_signed_vote(vote)?;
+
+    let missing = net.procs[0].missing_votes(&net.procs[1].digest())?;
+    assert!(missing.is_empty());
+    let missing = net.procs[1].missing_votes(&net.procs[0].digest())?;
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].voter, net.procs[1].id());
+    assert_eq!(net.procs[0].missing_votes(&net.procs[2].digest())?.len(), 1);
+
+    // peers ahead of us can't be helped
+    let mut digest = net.procs[2].digest();
+    digest.gen += 1;
+    assert!(net.procs[0].missing_votes(&digest)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_membership_recovers_from_dropped_packets_on_timeout() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let timeout = net.procs[0].consensus.timeout;
+
+    // every packet following the proposal is dropped
+    let p1 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Join(1))?;
+    net.broadcast(p1, vote);
+    net.deliver_packet_from_source(p1)?;
+    net.packets.clear();
+    assert_eq!(net.procs[0].gen, 0);
+
+    let mut now = Duration::ZERO;
+    for _ in 0..3 {
+        for i in 0..net.procs.len() {
+            let id = net.procs[i].id();
+            match net.procs[i].tick(now) {
+                Some(TimeoutResponse::Rebroadcast(vote)) => net.broadcast(id, vote),
+                Some(TimeoutResponse::AntiEntropy(digest)) => {
+                    for j in 0..net.procs.len() {
+                        let missing = net.procs[j].missing_votes(&digest)?;
+                        let source = net.procs[j].id();
+                        net.enqueue_packets(missing.into_iter().map(|vote| Packet {
+                            source,
+                            dest: id,
+                            vote,
+                        }));
+                    }
+                }
+                None => (),
+            }
+        }
+        net.drain_queued_packets()?;
+        now += timeout;
+    }
+
+    for proc in net.procs.iter() {
+        assert_eq!(proc.gen, 1);
+        assert_eq!(proc.members(1)?, BTreeSet::from_iter([1]));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+#[test]
+fn test_membership_mvba_engine_agrees_with_vote_based_engine() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut StdRng::from_seed([1u8; 32]));
+    let mvba_net = Net::with_procs(2, 4, &mut StdRng::from_seed([1u8; 32]));
+
+    // the vote based engine
+    for i in 0..net.procs.len() {
+        let id = net.procs[i].id();
+        let vote = net.procs[i].propose(Reconfig::Join(id))?;
+        net.broadcast(id, vote);
+    }
+    net.drain_queued_packets()?;
+    let decision = net.procs[0].consensus_at_gen(1)?.decision.clone().unwrap();
+
+    // the same proposals on the MVBA engine
+    let parties = Vec::from_iter(mvba_net.procs.iter().map(Membership::id));
+    let mut adapters = Vec::from_iter(
+        mvba_net
+            .procs
+            .into_iter()
+            .map(|proc| MvbaAdapter::new(Rc::new(RefCell::new(proc)), parties.clone())),
+    );
+    let mut outgoing = Vec::new();
+    for adapter in adapters.iter_mut() {
+        let id = adapter.id();
+        outgoing.extend(adapter.propose(Reconfig::Join(id))?);
+    }
+    mvba_net::drain(&mut adapters, outgoing, &mut rng)?;
+
+    let mvba_decision = adapters[0].decision().cloned().unwrap();
+    assert_eq!(mvba_decision.gen, 1);
+    assert_eq!(
+        mvba_decision.proposal,
+        Reconfig::Join(mvba_decision.proposer)
+    );
+    assert!(decision.proposals.contains_key(&mvba_decision.proposal));
+    for adapter in adapters.iter() {
+        assert_eq!(adapter.decision(), Some(&mvba_decision));
+
+        // the agreement is certified by the votes of the vote based engine
+        let proc = adapter.app.borrow();
+        let consensus = proc.consensus_at_gen(1)?;
+        let decision = consensus.decision.as_ref().unwrap();
+        assert_eq!(
+            Vec::from_iter(decision.proposals.keys()),
+            vec![&mvba_decision.proposal]
+        );
+        assert!(decision.validate(&consensus.elders).is_ok());
+        assert!(consensus.decision_vote.is_some());
+        let certificate = consensus.certificate.as_ref().unwrap();
+        assert!(certificate.verify(&consensus.elders.public_key()).is_ok());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+#[test]
+fn test_membership_mvba_certification_only_decides_the_agreed_proposal() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let net = Net::with_procs(2, 4, &mut rng);
+
+    let parties = Vec::from_iter(net.procs.iter().map(Membership::id));
+    let mut adapters = Vec::from_iter(
+        net.procs
+            .into_iter()
+            .map(|proc| MvbaAdapter::new(Rc::new(RefCell::new(proc)), parties.clone())),
+    );
+
+    // p4 votes for a reconfig of its own on the vote based engine before the agreement
+    let rogue_vote = adapters[3].app.borrow().sign_vote(Vote {
+        gen: 1,
+        ballot: Ballot::Propose(Reconfig::Join(9)),
+        faults: Default::default(),
+    })?;
+    for adapter in adapters.iter_mut().take(3) {
+        let resp = adapter.handle_signed_vote(rogue_vote.clone())?;
+        assert_eq!(resp, VoteResponse::WaitingForMoreVotes);
+    }
+
+    let mut outgoing = Vec::new();
+    for adapter in adapters.iter_mut() {
+        let id = adapter.id();
+        outgoing.extend(adapter.propose(Reconfig::Join(id))?);
+    }
+    mvba_net::drain(&mut adapters, outgoing, &mut rng)?;
+
+    let mvba_decision = adapters[0].decision().cloned().unwrap();
+    for adapter in adapters.iter() {
+        assert_eq!(
+            adapter.app.borrow().decided_reconfigs(1)?,
+            BTreeSet::from_iter([mvba_decision.proposal.clone()])
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+#[test]
+fn test_membership_mvba_engine_rejects_invalid_reconfigs() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+
+    // p4 is the only one to think 7 is a member, the others won't let 7 leave
+    net.procs[3].force_join(7)?;
+
+    let parties = Vec::from_iter(net.procs.iter().map(Membership::id));
+    let mut adapters = Vec::from_iter(
+        net.procs
+            .into_iter()
+            .map(|proc| MvbaAdapter::new(Rc::new(RefCell::new(proc)), parties.clone())),
+    );
+    let mut outgoing = adapters[3].propose(Reconfig::Leave(7))?;
+    for adapter in adapters.iter_mut().take(3) {
+        let id = adapter.id();
+        outgoing.extend(adapter.propose(Reconfig::Join(id))?);
+    }
+    assert!(matches!(
+        adapters[0].propose(Reconfig::Leave(7)),
+        Err(Error::LeaveRequestForNonMember)
+    ));
+    mvba_net::drain(&mut adapters, outgoing, &mut rng)?;
+
+    let mvba_decision = adapters[0].decision().cloned().unwrap();
+    assert_ne!(mvba_decision.proposal, Reconfig::Leave(7));
+    for adapter in adapters.iter() {
+        assert_eq!(adapter.decision(), Some(&mvba_decision));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "bad_crypto", feature = "ed25519")))]
+#[test]
+fn test_membership_mvba_engine_advances_generations() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let net = Net::with_procs(2, 4, &mut rng);
+
+    let parties = Vec::from_iter(net.procs.iter().map(Membership::id));
+    let procs = Vec::from_iter(net.procs.into_iter().map(|p| Rc::new(RefCell::new(p))));
+
+    let mut members = BTreeSet::new();
+    for gen in 1..=3 {
+        // every generation is agreed on by a fresh set of adapters
+        let mut adapters = Vec::from_iter(
+            procs
+                .iter()
+                .map(|proc| MvbaAdapter::new(proc.clone(), parties.clone())),
+        );
+        let mut outgoing = Vec::new();
+        for adapter in adapters.iter_mut() {
+            let actor = adapter.id() + 10 * gen as u8;
+            outgoing.extend(adapter.propose(Reconfig::Join(actor))?);
+        }
+        mvba_net::drain(&mut adapters, outgoing, &mut rng)?;
+
+        let mvba_decision = adapters[0].decision().cloned().unwrap();
+        assert_eq!(mvba_decision.gen, gen);
+        for adapter in adapters.iter() {
+            assert_eq!(adapter.decision(), Some(&mvba_decision));
+        }
+
+        if let Reconfig::Join(actor) = mvba_decision.proposal {
+            members.insert(actor);
+        }
+        for proc in procs.iter() {
+            let proc = proc.borrow();
+            assert_eq!(proc.gen, gen);
+            assert_eq!(proc.members(gen)?, members);
+            assert_eq!(
+                proc.decided_reconfigs(gen)?,
+                BTreeSet::from_iter([mvba_decision.proposal.clone()])
+            );
+        }
+    }
+
+    // the generations agreed on with MVBA are served and checkpointed like any other
+    for proc in procs.iter() {
+        let mut proc = proc.borrow_mut();
+        assert_eq!(proc.anti_entropy(0)?.len(), 3);
+        proc.checkpoint(2)?;
+        let checkpoint = proc.checkpoint.as_ref().unwrap();
+        assert!(checkpoint.validate(&proc.consensus.elders).is_ok());
+        assert_eq!(checkpoint.members.len(), 2);
+    }
+    Ok(())
+}
+
+fn restore_proc_from_store(
+    net: &mut Net,
+    i: usize,
+    store: impl Store<u8, PublicKeySet> + Send + Sync + 'static,
+) -> Result<()> {
+    let consensus = &net.procs[i].consensus;
+    net.procs[i] = Membership::restore(
+        consensus.secret_key.clone(),
+        consensus.elders.clone(),
+        consensus.n_elders,
+        CapacityPolicy::default(),
+        Arc::new(store),
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_membership_restore_from_memory_store_after_crash() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let store = MemoryStore::default();
+    restore_proc_from_store(&mut net, 0, store.clone())?;
+
+    // decide on a first generation
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Join(0))?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    // get a second generation underway without letting it complete
+    let p1 = net.procs[1].id();
+    let vote = net.procs[1].propose(Reconfig::Join(1))?;
+    net.broadcast(p1, vote);
+    net.deliver_packet_from_source(p1)?;
+    net.deliver_packet_from_source(p1)?;
+    assert!(!net.procs[0].consensus.votes.is_empty());
+
+    let state_before_crash = net.procs[0].state();
+    restore_proc_from_store(&mut net, 0, store)?;
+    assert_eq!(net.procs[0].state(), state_before_crash);
+    assert_eq!(net.procs[0].members(1)?, BTreeSet::from_iter([0]));
+
+    // the restored proc carries on with the rest of the network
+    net.drain_queued_packets()?;
+    for p in net.procs.iter() {
+        assert_eq!(p.members(2)?, BTreeSet::from_iter([0, 1]));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_membership_restore_from_file_store_after_crash() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let dir = std::env::temp_dir().join(format!(
+        "sn_consensus_restore_from_file_store_{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    restore_proc_from_store(&mut net, 0, FileStore::open(&dir)?)?;
+
+    net.procs[0].force_join(7)?;
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Join(0))?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    let vote = net.procs[0].propose(Reconfig::Leave(7))?;
+    net.broadcast(p0, vote);
+
+    let state_before_crash = net.procs[0].state();
+    restore_proc_from_store(&mut net, 0, FileStore::open(&dir)?)?;
+    assert_eq!(net.procs[0].state(), state_before_crash);
+    assert_eq!(net.procs[0].members(1)?, BTreeSet::from_iter([0, 7]));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_membership_restore_skips_stale_and_invalid_records() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let store = MemoryStore::default();
+    restore_proc_from_store(&mut net, 0, store.clone())?;
+
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Join(0))?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+    assert_eq!(net.procs[0].gen, 1);
+
+    // left behind by a crash before the log was truncated, the snapshot covers it
+    store.append(&Record::ForceJoin { gen: 0, actor: 7 })?;
+    // a vote that fails to replay, 0 is a member already
+    let invalid_vote = net.procs[1].sign_vote(Vote {
+        gen: 2,
+        ballot: Ballot::Propose(Reconfig::Join(0)),
+        faults: Default::default(),
+    })?;
+    store.append(&Record::Vote(invalid_vote))?;
+    store.append(&Record::ForceJoin { gen: 1, actor: 8 })?;
+
+    restore_proc_from_store(&mut net, 0, store)?;
+    let proc = &net.procs[0];
+    assert_eq!(proc.gen, 1);
+    assert!(proc.consensus.votes.is_empty());
+    assert_eq!(proc.members(0)?, BTreeSet::new());
+    assert_eq!(
+        proc.forced_reconfigs.get(&1),
+        Some(&BTreeSet::from_iter([Reconfig::Join(8)]))
+    );
+    Ok(())
+}
+
+/// Fails every append, e.g. a full disk.
+#[derive(Debug)]
+struct FailingStore;
+
+impl Store<u8, PublicKeySet> for FailingStore {
+    fn append(&self, _record: &Record<u8, PublicKeySet>) -> Result<()> {
+        Err(Error::IO(std::io::ErrorKind::Other.into()))
+    }
+
+    fn snapshot(&self, _state: &MembershipState<u8, PublicKeySet>) -> Result<()> {
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Persisted<u8, PublicKeySet>> {
+        Ok((None, Vec::new()))
+    }
+}
+
+#[test]
+fn test_membership_does_not_act_on_what_it_failed_to_persist() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    restore_proc_from_store(&mut net, 0, FailingStore)?;
+    let state_before = net.procs[0].clone();
+
+    let vote = net.procs[1].propose(Reconfig::Join(1))?;
+    assert!(matches!(
+        net.procs[0].handle_signed_vote(vote),
+        Err(Error::IO(_))
+    ));
+    assert!(matches!(
+        net.procs[0].propose(Reconfig::Join(0)),
+        Err(Error::IO(_))
+    ));
+    assert!(matches!(net.procs[0].force_join(7), Err(Error::IO(_))));
+    assert_eq!(net.procs[0], state_before);
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 enum Instruction {
     RequestJoin(u8, usize),
@@ -398,7 +1026,7 @@ fn test_membership_interpreter_qc3() {
     net.reconfigs_by_gen
         .entry(net.procs[0].gen + 1)
         .or_default()
-        .insert(reconfig);
+        .insert(reconfig.clone());
 
     let propose_vote = net.procs[0].propose(reconfig).unwrap();
     net.broadcast(p0, propose_vote);
@@ -409,7 +1037,7 @@ fn test_membership_interpreter_qc3() {
     net.reconfigs_by_gen
         .entry(net.procs[0].gen + 1)
         .or_default()
-        .insert(reconfig);
+        .insert(reconfig.clone());
 
     let propose_vote = net.procs[0].propose(reconfig).unwrap();
     net.broadcast(p0, propose_vote);
@@ -445,7 +1073,7 @@ fn test_membership_interpreter_qc4() -> Result<()> {
     let mut net = Net::with_procs(0, 1, &mut rng);
     {
         let reconfig = Reconfig::Join(0);
-        let vote = net.procs[0].propose(reconfig).unwrap();
+        let vote = net.procs[0].propose(reconfig.clone()).unwrap();
         net.reconfigs_by_gen.entry(1).or_default().insert(reconfig);
         net.broadcast(1, vote);
     }
@@ -468,7 +1096,7 @@ fn test_membership_interpreter_qc4() -> Result<()> {
 
     {
         let reconfig = Reconfig::Join(1);
-        let vote = net.procs[0].propose(reconfig).unwrap();
+        let vote = net.procs[0].propose(reconfig.clone()).unwrap();
         net.reconfigs_by_gen.entry(2).or_default().insert(reconfig);
         net.broadcast(1, vote);
     }
@@ -510,7 +1138,7 @@ fn test_membership_interpreter_qc4() -> Result<()> {
         }
     }
 
-    let proc_at_max_gen = procs_by_gen[max_gen].get(0).ok_or(Error::NoMembers)?;
+    let proc_at_max_gen = procs_by_gen[max_gen].first().ok_or(Error::NoMembers)?;
     assert!(super_majority(
         procs_by_gen[max_gen].len(),
         proc_at_max_gen.consensus.n_elders
@@ -528,6 +1156,7 @@ fn test_membership_procs_refuse_to_propose_competing_votes() -> Result<()> {
         (0, elders_sk.secret_key_share(0)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
 
     proc.propose(Reconfig::Join(0_u8))?;
@@ -535,7 +1164,7 @@ fn test_membership_procs_refuse_to_propose_competing_votes() -> Result<()> {
     // Proposing a second join reconfig for a different member should fail
     let reconfig = Reconfig::Join(1_u8);
     assert!(matches!(
-        proc.propose(reconfig),
+        proc.propose(reconfig.clone()),
         Err(Error::AttemptedFaultyProposal)
     ));
     assert!(!proc
@@ -561,20 +1190,275 @@ fn test_membership_validate_reconfig_rejects_when_members_at_capacity() -> Resul
         (0, elders_sk.secret_key_share(0usize)),
         elders_sk.public_keys(),
         1,
+        CapacityPolicy::default(),
     );
 
     for m in 0..7 {
-        proc.force_join(m);
+        proc.force_join(m).unwrap();
     }
 
     assert!(matches!(
         proc.validate_reconfig(Reconfig::Join(7), proc.gen + 1),
-        Err(Error::MembersAtCapacity)
+        Err(Error::PolicyViolation(PolicyError::MembersAtCapacity {
+            max_members: 7
+        }))
     ));
 
     Ok(())
 }
 
+#[derive(Debug)]
+struct EvenActorsOnly;
+
+#[derive(Debug, thiserror::Error)]
+#[error("actor {0} is odd")]
+struct OddActor(u8);
+
+impl MembershipPolicy<u8, PublicKeySet> for EvenActorsOnly {
+    fn validate(
+        &self,
+        reconfig: &Reconfig<u8>,
+        _members: &BTreeSet<u8>,
+        _gen: Generation,
+    ) -> std::result::Result<(), PolicyError> {
+        match reconfig {
+            Reconfig::Join(actor) if actor % 2 == 1 => {
+                Err(PolicyError::Rejected(Box::new(OddActor(*actor))))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_membership_validate_reconfig_consults_policy() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let elders_sk = SecretKeySet::random(0, &mut rng);
+    let mut proc = Membership::<u8>::from(
+        (0, elders_sk.secret_key_share(0usize)),
+        elders_sk.public_keys(),
+        1,
+        CapacityPolicy { max_members: 2 },
+    );
+    proc.force_join(0)?;
+    proc.force_join(1)?;
+    assert!(matches!(
+        proc.validate_reconfig(Reconfig::Join(2), proc.gen + 1),
+        Err(Error::PolicyViolation(PolicyError::MembersAtCapacity {
+            max_members: 2
+        }))
+    ));
+    assert!(proc
+        .validate_reconfig(Reconfig::Leave(1), proc.gen + 1)
+        .is_ok());
+
+    let mut proc = Membership::<u8>::from(
+        (0, elders_sk.secret_key_share(0usize)),
+        elders_sk.public_keys(),
+        1,
+        EvenActorsOnly,
+    );
+    assert!(proc.validate_reconfig(Reconfig::Join(2), 1).is_ok());
+    match proc.propose(Reconfig::Join(3)) {
+        Err(Error::PolicyViolation(PolicyError::Rejected(err))) => {
+            assert_eq!(err.downcast_ref::<OddActor>().unwrap().0, 3)
+        }
+        res => panic!("expected the policy to reject the join, got {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_trims_decided_joins_exceeding_capacity() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+
+    // leave room for a single join under the default capacity of 7 members
+    for proc in net.procs.iter_mut() {
+        for m in 0..6 {
+            proc.force_join(m)?;
+        }
+    }
+
+    // the elders are split over two joins, each valid on its own
+    for i in 0..net.procs.len() {
+        let id = net.procs[i].id();
+        let joining = if i < 2 { 10 } else { 11 };
+        let vote = net.procs[i].propose(Reconfig::Join(joining))?;
+        net.broadcast(id, vote);
+    }
+    net.drain_queued_packets()?;
+
+    for proc in net.procs.iter() {
+        assert_eq!(proc.gen, 1);
+        let decision = proc.consensus_at_gen(1)?.decision.as_ref().unwrap();
+        assert_eq!(
+            BTreeSet::from_iter(decision.proposals.keys().cloned()),
+            BTreeSet::from_iter([Reconfig::Join(10), Reconfig::Join(11)])
+        );
+
+        // only the first join fits, the other one is trimmed
+        assert_eq!(
+            proc.decided_reconfigs(1)?,
+            BTreeSet::from_iter([Reconfig::Join(10)])
+        );
+        let members = proc.members(1)?;
+        assert_eq!(members.len(), 7);
+        assert!(members.contains(&10));
+        assert!(!members.contains(&11));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_rotates_elders() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let old_elders = net.procs[0].consensus.elders.clone();
+    let new_elders_sk = SecretKeySet::random(2, &mut rng);
+
+    assert!(matches!(
+        net.procs[0].propose(Reconfig::Elders {
+            elders: new_elders_sk.public_keys(),
+            n_elders: 2,
+        }),
+        Err(Error::InvalidElders {
+            n_elders: 2,
+            threshold: 2
+        })
+    ));
+
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Elders {
+        elders: new_elders_sk.public_keys(),
+        n_elders: 4,
+    })?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    for proc in net.procs.iter_mut() {
+        assert_eq!(proc.gen, 1);
+        assert_eq!(proc.consensus_at_gen(1)?.elders, old_elders);
+        assert_eq!(proc.consensus.elders, new_elders_sk.public_keys());
+
+        // we can't vote with the key of the old elders
+        assert!(matches!(
+            proc.propose(Reconfig::Join(1)),
+            Err(Error::MissingSecretKey)
+        ));
+
+        let id = proc.id();
+        proc.set_secret_key((id, new_elders_sk.secret_key_share(id as u64)));
+    }
+
+    // the old elders can no longer vote
+    let stale_vote = net.procs[1].consensus_at_gen(1)?.sign_vote(Vote {
+        gen: 2,
+        ballot: Ballot::Propose(Reconfig::Join(1)),
+        faults: Default::default(),
+    })?;
+    assert!(matches!(
+        net.procs[0].handle_signed_vote(stale_vote),
+        Err(Error::NotElder)
+    ));
+
+    // while the new elders carry on
+    let vote = net.procs[0].propose(Reconfig::Join(1))?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    for proc in net.procs.iter() {
+        assert_eq!(proc.gen, 2);
+        assert_eq!(proc.members(2)?, BTreeSet::from_iter([1]));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_follows_decisions_while_awaiting_secret_key() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let new_elders_sk = SecretKeySet::random(2, &mut rng);
+
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Elders {
+        elders: new_elders_sk.public_keys(),
+        n_elders: 4,
+    })?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+
+    // everyone but p0 is given their share of the key of the new elders
+    for proc in net.procs.iter_mut().skip(1) {
+        let id = proc.id();
+        proc.set_secret_key((id, new_elders_sk.secret_key_share(id as u64)));
+    }
+
+    let p1 = net.procs[1].id();
+    let vote = net.procs[1].propose(Reconfig::Join(1))?;
+    net.broadcast(p1, vote);
+    net.drain_queued_packets()?;
+
+    // p0 handled the votes of the new elders without casting any
+    assert!(!net.procs[0].consensus_at_gen(2)?.votes.contains_key(&p0));
+    for proc in net.procs.iter() {
+        assert_eq!(proc.gen, 2);
+        assert_eq!(proc.members(2)?, BTreeSet::from_iter([1]));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_history_is_served_as_signed_by_its_elders() -> Result<()> {
+    init();
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs(2, 4, &mut rng);
+    let store = MemoryStore::default();
+    restore_proc_from_store(&mut net, 0, store.clone())?;
+    let old_elders = net.procs[0].consensus.elders.clone();
+    let new_elders_sk = SecretKeySet::random(2, &mut rng);
+
+    let p0 = net.procs[0].id();
+    let vote = net.procs[0].propose(Reconfig::Elders {
+        elders: new_elders_sk.public_keys(),
+        n_elders: 4,
+    })?;
+    net.broadcast(p0, vote);
+    net.drain_queued_packets()?;
+    for proc in net.procs.iter_mut() {
+        let id = proc.id();
+        proc.set_secret_key((id, new_elders_sk.secret_key_share(id as u64)));
+    }
+
+    // restart with our share of the key of the new elders
+    restore_proc_from_store(&mut net, 0, store)?;
+    let proc = &net.procs[0];
+    assert_eq!(proc.consensus.elders, new_elders_sk.public_keys());
+
+    let history = proc.anti_entropy(0)?;
+    assert_eq!(history.len(), 1);
+    let missing = proc.missing_votes(&VoteDigest {
+        gen: 1,
+        decided: false,
+        votes: Default::default(),
+    })?;
+    assert_eq!(missing.len(), 1);
+    for vote in history.iter().chain(missing.iter()) {
+        assert_eq!(vote.vote.gen, 1);
+        assert!(vote.validate(&old_elders, &Default::default()).is_ok());
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_membership_bft_consensus_qc1() -> Result<()> {
     init();
@@ -882,6 +1766,254 @@ fn test_membership_faulty_node_attempts_to_trick_honest_node() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_membership_fault_ledger_tracks_repeat_offenders() -> Result<()> {
+    init();
+    let n = 5;
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs((2 * n) / 3, n, &mut rng);
+    let faulty = 2;
+    let honest = 1;
+
+    // the faulty elder changes its vote in two consecutive generations
+    for (gen, a, b) in [(1, 22, 33), (2, 44, 55)] {
+        let faulty_proc = net.proc(faulty).unwrap();
+        let packet = Packet {
+            source: faulty,
+            dest: honest,
+            vote: faulty_proc.sign_vote(Vote {
+                gen,
+                ballot: Ballot::Propose(Reconfig::Join(a)),
+                faults: Default::default(),
+            })?,
+        };
+        net.enqueue_packets(vec![packet]);
+
+        let vote = net.proc_mut(faulty).unwrap().propose(Reconfig::Join(b))?;
+        net.broadcast(faulty, vote);
+        net.drain_queued_packets()?;
+    }
+
+    let proc = net.proc(honest).unwrap();
+    assert_eq!(proc.gen, 2);
+    assert_eq!(
+        BTreeMap::from_iter(
+            proc.faults_since(1)
+                .into_iter()
+                .map(|(elder, faults)| (elder, Vec::from_iter(faults.into_keys())))
+        ),
+        BTreeMap::from_iter([(faulty, vec![1, 2])])
+    );
+    assert_eq!(
+        Vec::from_iter(proc.faults_since(2)[&faulty].keys()),
+        vec![&2]
+    );
+
+    let vote = net.proc_mut(honest).unwrap().propose(Reconfig::Join(66))?;
+    net.broadcast(honest, vote);
+    net.drain_queued_packets()?;
+
+    // the ledger outlives the history pruned by a checkpoint
+    let proc = net.proc_mut(honest).unwrap();
+    assert_eq!(proc.gen, 3);
+    proc.checkpoint(2)?;
+    assert!(proc.consensus_at_gen(1).is_err());
+    assert_eq!(proc.faults_since(0)[&faulty].len(), 2);
+
+    let member_of = |elder| (elder == faulty).then_some(22);
+    assert_eq!(
+        proc.propose_leave_for_repeat_offender(1, 3, member_of)?,
+        None
+    );
+    let vote = proc
+        .propose_leave_for_repeat_offender(1, 2, member_of)?
+        .unwrap();
+    assert_eq!(vote.vote.ballot, Ballot::Propose(Reconfig::Leave(22)));
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_bogus_super_majority_is_a_fault() -> Result<()> {
+    init();
+    let n = 5;
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs((2 * n) / 3, n, &mut rng);
+    let faulty = 2;
+    let honest = 1;
+    {
+        // claim a super majority over nothing but our own proposal
+        let faulty_proc = net.proc(faulty).unwrap();
+        let proposal = faulty_proc.sign_vote(Vote {
+            gen: 1,
+            ballot: Ballot::Propose(Reconfig::Join(22)),
+            faults: Default::default(),
+        })?;
+        let bogus_sm = faulty_proc.consensus.build_super_majority_vote(
+            BTreeSet::from_iter([proposal]),
+            Default::default(),
+            1,
+        )?;
+        net.broadcast(faulty, bogus_sm);
+    }
+
+    {
+        let vote = net.proc_mut(honest).unwrap().propose(Reconfig::Join(11))?;
+        net.broadcast(honest, vote);
+    }
+
+    net.drain_queued_packets()?;
+
+    let honest_procs = Vec::from_iter(net.procs.iter().filter(|p| faulty != p.id()));
+
+    for p in honest_procs.iter() {
+        assert_eq!(p.gen, 1);
+        let decision = p.consensus_at_gen(1)?.decision.as_ref().unwrap();
+
+        assert!(matches!(
+            Vec::from_iter(&decision.faults)[..],
+            [Fault::BogusSuperMajority { signed_vote }] if signed_vote.voter == faulty
+        ));
+        assert_eq!(
+            BTreeSet::from_iter(decision.proposals.keys()),
+            BTreeSet::from_iter([&Reconfig::Join(11)])
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_super_majority_over_a_split_vote_is_a_fault() -> Result<()> {
+    init();
+    let n = 4;
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let net = Net::with_procs((2 * n) / 3, n, &mut rng);
+    let faulty = 1;
+
+    // every elder proposes someone else, a split vote that was never merged to the bound
+    let votes = net
+        .procs
+        .iter()
+        .map(|p| {
+            p.sign_vote(Vote {
+                gen: 1,
+                ballot: Ballot::Propose(Reconfig::Join(10 + p.id())),
+                faults: Default::default(),
+            })
+        })
+        .collect::<Result<BTreeSet<_>>>()?;
+
+    let faulty_proc = net.proc(faulty).unwrap();
+    let split_sm =
+        faulty_proc
+            .consensus_at_gen(1)?
+            .build_super_majority_vote(votes, Default::default(), 1)?;
+
+    assert!(!split_sm.vote.is_tie_break());
+    assert!(Fault::BogusSuperMajority {
+        signed_vote: split_sm
+    }
+    .validate(&faulty_proc.consensus_at_gen(1)?.elders)
+    .is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_mismatched_super_majority_proposals_is_a_fault() -> Result<()> {
+    init();
+    let n = 5;
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let mut net = Net::with_procs((2 * n) / 3, n, &mut rng);
+    let faulty = 2;
+
+    let vote = net.proc_mut(1).unwrap().propose(Reconfig::Join(11))?;
+    net.broadcast(1, vote);
+    net.drain_queued_packets()?;
+
+    let faulty_proc = net.proc(faulty).unwrap();
+    let consensus = faulty_proc.consensus_at_gen(1)?;
+    let votes = BTreeSet::from_iter(consensus.votes.values().cloned());
+
+    let honest_sm = consensus.build_super_majority_vote(votes.clone(), Default::default(), 1)?;
+    let (certificate_share, proposal_sig) = match honest_sm.vote.ballot.clone() {
+        Ballot::SuperMajority {
+            mut proposals,
+            certificate_share,
+            ..
+        } => (
+            certificate_share,
+            proposals.remove(&Reconfig::Join(11)).unwrap(),
+        ),
+        _ => panic!("expected a SuperMajority ballot"),
+    };
+
+    // signatures lifted from another super majority don't hold for the proposal they claim
+    let forged_sm = faulty_proc.sign_vote(Vote {
+        gen: 1,
+        ballot: Ballot::SuperMajority {
+            votes: votes.clone(),
+            proposals: BTreeMap::from_iter([(Reconfig::Join(99), proposal_sig)]),
+            certificate_share,
+        },
+        faults: Default::default(),
+    })?;
+    assert!(forged_sm
+        .validate(&consensus.elders, &Default::default())
+        .is_err());
+
+    // sign a super majority over the votes, but for a proposal nobody voted for
+    let mismatched_sm = faulty_proc.sign_vote(Vote {
+        gen: 1,
+        ballot: Ballot::SuperMajority {
+            votes,
+            proposals: BTreeMap::from_iter([(
+                Reconfig::Join(99),
+                (faulty, consensus.sign(&Reconfig::Join(99u8))?),
+            )]),
+            certificate_share: (
+                faulty,
+                consensus.sign(&(1u64, BTreeSet::from_iter([Reconfig::Join(99u8)])))?,
+            ),
+        },
+        faults: Default::default(),
+    })?;
+
+    let elders = &consensus.elders;
+    assert!(mismatched_sm.validate(elders, &Default::default()).is_ok());
+    assert!(Fault::MismatchedSuperMajorityProposals {
+        signed_vote: mismatched_sm.clone()
+    }
+    .validate(elders)
+    .is_ok());
+    assert!(Fault::BogusSuperMajority {
+        signed_vote: mismatched_sm.clone()
+    }
+    .validate(elders)
+    .is_err());
+    assert!(Fault::MismatchedSuperMajorityProposals {
+        signed_vote: honest_sm
+    }
+    .validate(elders)
+    .is_err());
+
+    let faults = mismatched_sm
+        .detect_byzantine_faults(elders, &Default::default(), &Default::default())
+        .unwrap_err();
+    assert_eq!(
+        faults,
+        BTreeMap::from_iter([(
+            faulty,
+            Fault::MismatchedSuperMajorityProposals {
+                signed_vote: mismatched_sm
+            }
+        )])
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_membership_we_can_agree_to_an_empty_set() -> Result<()> {
     init();
@@ -1158,7 +2290,11 @@ fn test_membership_ensure_we_are_validating_super_majority_proposals() -> Result
     let id_b = 4;
     {
         let reconfig = Reconfig::Join(1);
-        let vote = net.proc_mut(id_a).unwrap().propose(reconfig).unwrap();
+        let vote = net
+            .proc_mut(id_a)
+            .unwrap()
+            .propose(reconfig.clone())
+            .unwrap();
         net.reconfigs_by_gen
             .entry(vote.vote.gen)
             .or_default()
@@ -1173,7 +2309,11 @@ fn test_membership_ensure_we_are_validating_super_majority_proposals() -> Result
 
     {
         let reconfig = Reconfig::Join(0);
-        let vote = net.proc_mut(id_b).unwrap().propose(reconfig).unwrap();
+        let vote = net
+            .proc_mut(id_b)
+            .unwrap()
+            .propose(reconfig.clone())
+            .unwrap();
         net.reconfigs_by_gen
             .entry(vote.vote.gen)
             .or_default()
@@ -1210,7 +2350,7 @@ fn test_membership_ensure_we_are_validating_super_majority_proposals() -> Result
         }
     }
 
-    let proc_at_max_gen = procs_by_gen[max_gen].get(0).ok_or(Error::NoMembers)?;
+    let proc_at_max_gen = procs_by_gen[max_gen].first().ok_or(Error::NoMembers)?;
     assert!(super_majority(
         procs_by_gen[max_gen].len(),
         proc_at_max_gen.consensus.n_elders
@@ -1244,7 +2384,7 @@ fn prop_interpreter(n: u8, instructions: Vec<Instruction>, seed: u128) -> eyre::
 
                 let q = &mut net.procs[q_idx.min(n - 1)];
                 let q_id = q.id();
-                match q.propose(reconfig) {
+                match q.propose(reconfig.clone()) {
                     Ok(vote) => {
                         net.reconfigs_by_gen
                             .entry(vote.vote.gen)
@@ -1252,7 +2392,7 @@ fn prop_interpreter(n: u8, instructions: Vec<Instruction>, seed: u128) -> eyre::
                             .insert(reconfig);
                         net.broadcast(q_id, vote);
                     }
-                    Err(Error::JoinRequestForExistingMember { .. }) => {
+                    Err(Error::JoinRequestForExistingMember) => {
                         assert!(q.members(q.gen)?.contains(&p));
                     }
                     Err(Error::AttemptedFaultyProposal) => {
@@ -1278,7 +2418,7 @@ fn prop_interpreter(n: u8, instructions: Vec<Instruction>, seed: u128) -> eyre::
 
                 let q = &mut net.procs[q_idx.min(n - 1)];
                 let q_id = q.id();
-                match q.propose(reconfig) {
+                match q.propose(reconfig.clone()) {
                     Ok(vote) => {
                         net.reconfigs_by_gen
                             .entry(vote.vote.gen)
@@ -1286,7 +2426,7 @@ fn prop_interpreter(n: u8, instructions: Vec<Instruction>, seed: u128) -> eyre::
                             .insert(reconfig);
                         net.broadcast(q_id, vote);
                     }
-                    Err(Error::LeaveRequestForNonMember { .. }) => {
+                    Err(Error::LeaveRequestForNonMember) => {
                         assert!(!q.members(q.gen)?.contains(&p));
                     }
                     Err(Error::AttemptedFaultyProposal) => {
@@ -1356,7 +2496,7 @@ fn prop_interpreter(n: u8, instructions: Vec<Instruction>, seed: u128) -> eyre::
         }
     }
 
-    let proc_at_max_gen = procs_by_gen[max_gen].get(0).ok_or(Error::NoMembers)?;
+    let proc_at_max_gen = procs_by_gen[max_gen].first().ok_or(Error::NoMembers)?;
     assert!(super_majority(
         procs_by_gen[max_gen].len(),
         proc_at_max_gen.consensus.n_elders
@@ -1387,10 +2527,11 @@ fn prop_validate_reconfig(
         (1, elders_sk.secret_key_share(1usize)),
         elders_sk.public_keys(),
         (3 * threshold / 2) as usize,
+        CapacityPolicy::default(),
     );
 
     for m in initial_members.iter().copied() {
-        proc.force_join(m);
+        proc.force_join(m).unwrap();
     }
 
     let reconfig = match join_or_leave {
@@ -1398,17 +2539,22 @@ fn prop_validate_reconfig(
         false => Reconfig::Leave(member),
     };
 
-    let valid_res = proc.validate_reconfig(reconfig, proc.gen + 1);
+    let valid_res = proc.validate_reconfig(reconfig.clone(), proc.gen + 1);
     let proc_members = proc.members(proc.gen)?;
     match reconfig {
         Reconfig::Join(member) => {
             if proc_members.contains(&member) {
                 assert!(matches!(
                     valid_res,
-                    Err(Error::JoinRequestForExistingMember { .. })
+                    Err(Error::JoinRequestForExistingMember)
                 ));
             } else if initial_members.len() >= 7 {
-                assert!(matches!(valid_res, Err(Error::MembersAtCapacity)));
+                assert!(matches!(
+                    valid_res,
+                    Err(Error::PolicyViolation(
+                        PolicyError::MembersAtCapacity { .. }
+                    ))
+                ));
             } else {
                 assert!(valid_res.is_ok());
             }
@@ -1417,12 +2563,10 @@ fn prop_validate_reconfig(
             if proc_members.contains(&member) {
                 assert!(valid_res.is_ok());
             } else {
-                assert!(matches!(
-                    valid_res,
-                    Err(Error::LeaveRequestForNonMember { .. })
-                ));
+                assert!(matches!(valid_res, Err(Error::LeaveRequestForNonMember)));
             }
         }
+        Reconfig::Elders { .. } => unreachable!(),
     };
 
     Ok(TestResult::passed())